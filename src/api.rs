@@ -1,60 +1,331 @@
 use crate::errors::CalendarError;
 use crate::models::Event;
-use reqwest::{Client, StatusCode, header};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, header};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::time::Duration;
 use std::env;
+use std::fmt;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tracing::warn;
 
-const API_ENDPOINT: &str = "https://api.lu.ma/public/v1/entity/lookup?slug=";
-const ADD_EVENT_ENDPOINT: &str = "https://api.lu.ma/public/v1/calendar/add-event";
+const API_BASE_URL: &str = "https://api.lu.ma/public/v1";
+const API_ENDPOINT: &str = "/entity/lookup?slug=";
+const ADD_EVENT_ENDPOINT: &str = "/calendar/add-event";
+const REMOVE_EVENT_ENDPOINT: &str = "/calendar/remove-event";
+const LIST_EVENTS_ENDPOINT: &str = "/calendar/list-events";
+const GUEST_ENDPOINT: &str = "/event/get-guest";
+const SELF_ENDPOINT: &str = "/user/get-self";
+const EVENT_DETAILS_ENDPOINT: &str = "/event/get?api_id=";
 const API_KEY_ENV: &str = "LUMA_API_KEY";
 
+/// Default delay between API requests (1 request per second)
+pub const DEFAULT_RATE_LIMIT_MS: u64 = 1000;
+
+/// Default number of retries for 429 and 5xx responses
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry when the server sends no Retry-After header
+const INITIAL_BACKOFF_MS: u64 = 500;
+
+/// Entries requested per page from list endpoints
+const LIST_PAGE_SIZE: u32 = 50;
+
+/// Upper bound on any single retry delay
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Response of the `calendar/add-event` endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddEventResponse {
+    /// ID of the new calendar entry, which `remove_event` takes
+    pub calendar_event_id: String,
+    /// The full response body, for fields not captured above
+    #[serde(skip)]
+    pub raw: Value,
+}
+
+/// Settings sent with `add_event` alongside the event ID
+///
+/// The defaults match what the calendar has always been sent: a Luma-hosted
+/// event with a manually entered venue.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AddEventOptions {
+    /// Where the event is hosted, e.g. "luma" or "zoom"
+    pub platform: String,
+    /// Venue sent as `geo_address_json`
+    #[serde(rename = "geo_address_json")]
+    pub geo_address: Value,
+    /// Visibility of the calendar entry (e.g. "public"); Luma decides when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+}
+
+impl Default for AddEventOptions {
+    fn default() -> Self {
+        Self {
+            platform: "luma".to_string(),
+            geo_address: json!({ "type": "manual" }),
+            visibility: None,
+        }
+    }
+}
+
+impl AddEventOptions {
+    /// Sets a manually entered venue address
+    pub fn with_address(mut self, address: &str) -> Self {
+        self.geo_address = json!({ "type": "manual", "address": address });
+        self
+    }
+
+    /// Builds the `calendar/add-event` request body for an event
+    fn payload(&self, event_api_id: &str) -> Value {
+        let mut payload = json!(self);
+        payload["event_api_id"] = json!(event_api_id);
+        payload
+    }
+}
+
+/// Attendance figures for an event, as reported by the Luma API
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EventDetails {
+    /// The event name
+    pub name: Option<String>,
+    /// Number of registered guests
+    pub guest_count: Option<u64>,
+    /// Maximum number of guests, or `None` when registration is unlimited
+    pub capacity: Option<u64>,
+    /// True when new registrations go onto a waitlist
+    pub waitlist_active: bool,
+}
+
+impl EventDetails {
+    /// Parses the `event/get` response, reading fields from either the top
+    /// level or the nested `event` object
+    fn from_json(json: &Value) -> Self {
+        let field = |name: &str| {
+            json.get("event")
+                .and_then(|event| event.get(name))
+                .filter(|value| !value.is_null())
+                .or_else(|| json.get(name).filter(|value| !value.is_null()))
+        };
+        
+        Self {
+            name: field("name").and_then(Value::as_str).map(str::to_string),
+            guest_count: field("guest_count").and_then(Value::as_u64),
+            capacity: field("max_capacity")
+                .or_else(|| field("capacity"))
+                .and_then(Value::as_u64),
+            waitlist_active: field("waitlist_active")
+                .and_then(Value::as_bool)
+                .or_else(|| field("waitlist_status").and_then(Value::as_str).map(|status| status == "enabled"))
+                .unwrap_or(false),
+        }
+    }
+    
+    /// Remaining spots, when both the capacity and the guest count are known
+    pub fn spots_left(&self) -> Option<u64> {
+        Some(self.capacity?.saturating_sub(self.guest_count?))
+    }
+}
+
+/// Where the API key's owner stands with an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationStatus {
+    /// Registered and approved
+    Going,
+    /// Declined, or registration was rejected
+    NotGoing,
+    /// On the waitlist
+    Waitlisted,
+    /// Registered, waiting for the host to approve
+    PendingApproval,
+    /// Invited, without having answered
+    Invited,
+}
+
+impl RegistrationStatus {
+    /// Maps a guest's `approval_status`, returning `None` for values we don't know
+    fn from_api(status: &str) -> Option<Self> {
+        match status {
+            "approved" => Some(Self::Going),
+            "declined" | "rejected" => Some(Self::NotGoing),
+            "waitlist" => Some(Self::Waitlisted),
+            "pending_approval" => Some(Self::PendingApproval),
+            "invited" => Some(Self::Invited),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RegistrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Going => "going",
+            Self::NotGoing => "not going",
+            Self::Waitlisted => "waitlisted",
+            Self::PendingApproval => "pending approval",
+            Self::Invited => "invited",
+        })
+    }
+}
+
 /// API handler for interacting with the Luma API
 pub struct LumaApi {
     client: Client,
+    base_url: String, // Root that the endpoint paths are appended to
     api_key: Option<String>, // Luma API key
-    #[allow(dead_code)]
     rate_limit_ms: u64, // Rate limiting in milliseconds
+    max_retries: u32, // Retries for 429 and 5xx responses
+    own_email: OnceCell<String>, // Email of the key's owner, looked up on first use
 }
 
 impl LumaApi {
-    /// Creates a new API client
+    /// Creates a new API client with the default rate limit
     pub fn new() -> Self {
+        Self::with_rate_limit(DEFAULT_RATE_LIMIT_MS)
+    }
+    
+    /// Creates a new API client that waits `rate_limit_ms` between requests
+    pub fn with_rate_limit(rate_limit_ms: u64) -> Self {
         // Try to get API key from environment
-        let api_key = env::var(API_KEY_ENV).ok();
+        let api_key = Self::validate_key(env::var(API_KEY_ENV).ok());
         
         Self {
             client: Client::builder()
                 .timeout(Duration::from_secs(10))
                 .build()
                 .unwrap_or_default(),
+            base_url: API_BASE_URL.to_string(),
             api_key,
-            rate_limit_ms: 1000, // Default to 1 request per second
+            rate_limit_ms,
+            max_retries: DEFAULT_MAX_RETRIES,
+            own_email: OnceCell::new(),
+        }
+    }
+    
+    /// A client for a mock server at `base_url`, with a fixed key and no rate limit
+    #[cfg(test)]
+    pub(crate) fn for_mock_server(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            api_key: Some("test-key".to_string()),
+            ..Self::with_rate_limit(0)
         }
     }
+
+    /// Returns the key if it looks usable, warning about (and discarding) blank
+    /// keys or keys containing whitespace
+    pub fn validate_key(key: Option<String>) -> Option<String> {
+        let key = key?;
+        
+        if key.trim().is_empty() {
+            warn!("{} is set but empty; ignoring it", API_KEY_ENV);
+            return None;
+        }
+        
+        if key.trim().chars().any(char::is_whitespace) {
+            warn!("{} contains whitespace and is not a valid API key; ignoring it", API_KEY_ENV);
+            return None;
+        }
+        
+        Some(key.trim().to_string())
+    }
     
-    // Function removed to eliminate unused code warning
+    /// Returns an error telling the user to set the API key when none is available
+    pub fn require_key(&self) -> Result<&str, CalendarError> {
+        self.api_key.as_deref().ok_or_else(|| {
+            CalendarError::EnvError(format!(
+                "No valid Luma API key available. Set the {} environment variable",
+                API_KEY_ENV
+            ))
+        })
+    }
+    
+    /// Sets how many times 429 and 5xx responses are retried
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+    
+    /// Full URL of an endpoint path
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+    
+    /// The delay to wait between consecutive API requests
+    pub fn rate_limit(&self) -> Duration {
+        Duration::from_millis(self.rate_limit_ms)
+    }
 
+    /// Sends a request, retrying 429 and 5xx responses with exponential backoff
+    ///
+    /// A `Retry-After` header takes precedence over the computed backoff. Other
+    /// statuses are returned immediately for the caller to handle.
+    async fn send_with_retry(&self, request: RequestBuilder) -> Result<Response, CalendarError> {
+        let mut attempt = 0;
+        
+        loop {
+            let attempt_request = request.try_clone().ok_or_else(|| {
+                CalendarError::ParseError("API request cannot be retried".to_string())
+            })?;
+            
+            let response = attempt_request.send().await.map_err(|e| {
+                CalendarError::ParseError(format!("API request failed: {}", e))
+            })?;
+            
+            let status = response.status();
+            let retryable = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            if !retryable || attempt >= self.max_retries {
+                return Ok(response);
+            }
+            
+            let delay = retry_after(&response)
+                .unwrap_or_else(|| backoff(attempt))
+                .min(MAX_BACKOFF);
+            attempt += 1;
+            warn!(
+                "API request returned {}, retrying in {:.1?} (attempt {}/{})",
+                status, delay, attempt, self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    /// Checks that the API is reachable and accepts the key, without changing anything
+    pub async fn check_access(&self) -> Result<(), CalendarError> {
+        // Check if API key is available
+        let api_key = self.require_key()?;
+        
+        let request = self.client
+            .get(self.endpoint(SELF_ENDPOINT))
+            .header(header::AUTHORIZATION, format!("Bearer {}", api_key));
+        let response = self.send_with_retry(request).await?;
+        
+        if response.status().is_success() {
+            return Ok(());
+        }
+        
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        Err(CalendarError::ParseError(format!("API request failed with status: {} - {}", status, error_text)))
+    }
+    
     /// Lookup API ID for an event using its slug
     pub async fn lookup_event_id(&self, slug: &str) -> Result<String, CalendarError> {
         // Check if API key is available
-        let api_key = self.api_key.as_ref().ok_or_else(|| {
-            CalendarError::ParseError(format!("No API key available. Set {} environment variable", API_KEY_ENV))
-        })?;
+        let api_key = self.require_key()?;
         
         // Clean the slug thoroughly before using it in the URL
         let clean_slug = Event::clean_string(slug);
         
-        let url = format!("{}{}", API_ENDPOINT, clean_slug);
+        let url = format!("{}{}", self.endpoint(API_ENDPOINT), clean_slug);
         
-        let response = self.client
+        let request = self.client
             .get(&url)
-            .header(header::AUTHORIZATION, format!("Bearer {}", api_key))
-            .send()
-            .await
-            .map_err(|e| {
-                CalendarError::ParseError(format!("API request failed: {}", e))
-            })?;
+            .header(header::AUTHORIZATION, format!("Bearer {}", api_key));
+        let response = self.send_with_retry(request).await?;
         
         match response.status() {
             StatusCode::OK => {
@@ -80,6 +351,158 @@ impl LumaApi {
         }
     }
     
+    /// Looks up whether the API key's owner is registered for an event
+    ///
+    /// Returns `None` when they have no relationship with the event (no guest
+    /// entry, or a status we don't recognize).
+    pub async fn get_registration_status(&self, api_id: &str) -> Result<Option<RegistrationStatus>, CalendarError> {
+        // Check if API key is available
+        let api_key = self.require_key()?;
+        let email = self.own_email.get_or_try_init(|| self.fetch_own_email()).await?;
+        
+        let request = self.client
+            .get(self.endpoint(GUEST_ENDPOINT))
+            .query(&[("event_api_id", Event::clean_string(api_id).as_str()), ("email", email.as_str())])
+            .header(header::AUTHORIZATION, format!("Bearer {}", api_key));
+        let response = self.send_with_retry(request).await?;
+        
+        match response.status() {
+            StatusCode::OK => {
+                let json: Value = response.json().await.map_err(|e| {
+                    CalendarError::ParseError(format!("Failed to parse API response: {}", e))
+                })?;
+                
+                let guest = json.get("guest").unwrap_or(&json);
+                let status = guest.get("approval_status").and_then(Value::as_str);
+                Ok(status.and_then(|status| {
+                    let parsed = RegistrationStatus::from_api(status);
+                    if parsed.is_none() {
+                        warn!("Unknown registration status '{}' for event {}", status, api_id);
+                    }
+                    parsed
+                }))
+            },
+            // Not a guest of this event
+            StatusCode::NOT_FOUND => Ok(None),
+            status => {
+                let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                Err(CalendarError::ParseError(format!("API request failed with status: {} - {}", status, error_text)))
+            }
+        }
+    }
+    
+    /// Fetches the email of the API key's owner, which guest lookups are keyed on
+    async fn fetch_own_email(&self) -> Result<String, CalendarError> {
+        // Check if API key is available
+        let api_key = self.require_key()?;
+        
+        let request = self.client
+            .get(self.endpoint(SELF_ENDPOINT))
+            .header(header::AUTHORIZATION, format!("Bearer {}", api_key));
+        let response = self.send_with_retry(request).await?;
+        
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(CalendarError::ParseError(format!("API request failed with status: {} - {}", status, error_text)));
+        }
+        
+        let json: Value = response.json().await.map_err(|e| {
+            CalendarError::ParseError(format!("Failed to parse API response: {}", e))
+        })?;
+        json.get("user")
+            .unwrap_or(&json)
+            .get("email")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| CalendarError::ParseError("Email not found in user response".to_string()))
+    }
+    
+    /// Lists every event of a calendar through the API, following `next_cursor`
+    /// until the last page
+    ///
+    /// The events come back with `api_id` set. Entries without a name or valid
+    /// start time are skipped with a warning.
+    pub async fn list_calendar_events(&self, calendar_id: &str) -> Result<Vec<Event>, CalendarError> {
+        // Check if API key is available
+        let api_key = self.require_key()?;
+        
+        let page_size = LIST_PAGE_SIZE.to_string();
+        let mut events = Vec::new();
+        let mut cursor: Option<String> = None;
+        
+        loop {
+            let mut query = vec![("calendar_api_id", calendar_id), ("pagination_limit", page_size.as_str())];
+            if let Some(cursor) = &cursor {
+                query.push(("pagination_cursor", cursor.as_str()));
+            }
+            
+            let request = self.client
+                .get(self.endpoint(LIST_EVENTS_ENDPOINT))
+                .query(&query)
+                .header(header::AUTHORIZATION, format!("Bearer {}", api_key));
+            let response = self.send_with_retry(request).await?;
+            
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(CalendarError::ParseError(format!("API request failed with status: {} - {}", status, error_text)));
+            }
+            
+            let json: Value = response.json().await.map_err(|e| {
+                CalendarError::ParseError(format!("Failed to parse API response: {}", e))
+            })?;
+            
+            for entry in json.get("entries").and_then(Value::as_array).into_iter().flatten() {
+                match event_from_api(entry) {
+                    Some(event) => events.push(event),
+                    None => warn!("Skipping API event without a name or valid start time: {}", entry),
+                }
+            }
+            
+            let has_more = json.get("has_more").and_then(Value::as_bool).unwrap_or(true);
+            cursor = json.get("next_cursor").and_then(Value::as_str).map(str::to_string);
+            if !has_more || cursor.is_none() {
+                break;
+            }
+            
+            // Add a small delay to respect rate limits
+            tokio::time::sleep(self.rate_limit()).await;
+        }
+        
+        Ok(events)
+    }
+    
+    /// Fetches guest count, capacity and waitlist status for an event by its API ID
+    pub async fn get_event_details(&self, api_id: &str) -> Result<EventDetails, CalendarError> {
+        // Check if API key is available
+        let api_key = self.require_key()?;
+        
+        let url = format!("{}{}", self.endpoint(EVENT_DETAILS_ENDPOINT), Event::clean_string(api_id));
+        
+        let request = self.client
+            .get(&url)
+            .header(header::AUTHORIZATION, format!("Bearer {}", api_key));
+        let response = self.send_with_retry(request).await?;
+        
+        match response.status() {
+            StatusCode::OK => {
+                let json: Value = response.json().await.map_err(|e| {
+                    CalendarError::ParseError(format!("Failed to parse API response: {}", e))
+                })?;
+                
+                Ok(EventDetails::from_json(&json))
+            },
+            StatusCode::NOT_FOUND => {
+                Err(CalendarError::ParseError(format!("No event with API ID {} was found", api_id)))
+            },
+            status => {
+                let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                Err(CalendarError::ParseError(format!("API request failed with status: {} - {}", status, error_text)))
+            }
+        }
+    }
+    
     /// Enrich an event with API data
     pub async fn enrich_event(&self, event: &mut Event) -> Result<(), CalendarError> {
         // If the event already has an API ID, no need to fetch it again
@@ -89,9 +512,6 @@ impl LumaApi {
         
         // Extract slug from URL
         if let Some(slug) = event.extract_slug() {
-            // Add a small delay for rate limiting
-            tokio::time::sleep(Duration::from_millis(self.rate_limit_ms)).await;
-            
             // Lookup the API ID
             let api_id = self.lookup_event_id(&slug).await?;
             
@@ -113,40 +533,47 @@ impl LumaApi {
             let result = self.enrich_event(event).await;
             results.push(result);
             
-            // Add a small delay for rate limiting
-            tokio::time::sleep(Duration::from_millis(self.rate_limit_ms)).await;
+            // Wait between requests to respect the configured rate limit
+            tokio::time::sleep(self.rate_limit()).await;
         }
         
         results
     }
     
+    /// Looks up the API ID of each slug in turn, waiting the rate limit between
+    /// requests; a failed lookup doesn't stop the rest
+    pub async fn lookup_event_ids(&self, slugs: &[String]) -> Vec<Result<String, CalendarError>> {
+        let mut results = Vec::with_capacity(slugs.len());
+
+        for (index, slug) in slugs.iter().enumerate() {
+            if index > 0 {
+                tokio::time::sleep(self.rate_limit()).await;
+            }
+            results.push(self.lookup_event_id(slug).await);
+        }
+
+        results
+    }
+
     /// Add an event to a Luma calendar based on its event API ID
-    pub async fn add_event(&self, event_api_id: &str) -> Result<Value, CalendarError> {
+    pub async fn add_event(
+        &self,
+        event_api_id: &str,
+        options: &AddEventOptions,
+    ) -> Result<AddEventResponse, CalendarError> {
         // Check if API key is available
-        let api_key = self.api_key.as_ref().ok_or_else(|| {
-            CalendarError::ParseError(format!("No API key available. Set {} environment variable", API_KEY_ENV))
-        })?;
+        let api_key = self.require_key()?;
         
         // Prepare the request payload
-        let payload = json!({
-            "platform": "luma",
-            "geo_address_json": {
-                "type": "manual"
-            },
-            "event_api_id": event_api_id
-        });
+        let payload = options.payload(event_api_id);
         
         // Make the API request
-        let response = self.client
-            .post(ADD_EVENT_ENDPOINT)
+        let request = self.client
+            .post(self.endpoint(ADD_EVENT_ENDPOINT))
             .header(header::AUTHORIZATION, format!("Bearer {}", api_key))
             .header(header::CONTENT_TYPE, "application/json")
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| {
-                CalendarError::ParseError(format!("API request failed: {}", e))
-            })?;
+            .json(&payload);
+        let response = self.send_with_retry(request).await?;
         
         match response.status() {
             StatusCode::OK | StatusCode::CREATED => {
@@ -154,7 +581,58 @@ impl LumaApi {
                     CalendarError::ParseError(format!("Failed to parse API response: {}", e))
                 })?;
                 
-                Ok(json)
+                // A missing calendar_event_id means the response shape changed
+                let mut added: AddEventResponse = serde_json::from_value(json.clone()).map_err(|e| {
+                    CalendarError::ParseError(format!("Unexpected add-event response {}: {}", json, e))
+                })?;
+                added.raw = json;
+                
+                Ok(added)
+            },
+            status => {
+                let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                Err(CalendarError::ParseError(format!("API request failed with status: {} - {}", status, error_text)))
+            }
+        }
+    }
+    
+    /// Remove an event from the calendar using its calendar event ID
+    pub async fn remove_event(&self, calendar_event_id: &str) -> Result<Value, CalendarError> {
+        // Check if API key is available
+        let api_key = self.require_key()?;
+        
+        let payload = json!({
+            "calendar_event_id": calendar_event_id
+        });
+        
+        // Make the API request
+        let request = self.client
+            .post(self.endpoint(REMOVE_EVENT_ENDPOINT))
+            .header(header::AUTHORIZATION, format!("Bearer {}", api_key))
+            .header(header::CONTENT_TYPE, "application/json")
+            .json(&payload);
+        let response = self.send_with_retry(request).await?;
+        
+        match response.status() {
+            StatusCode::OK | StatusCode::NO_CONTENT => {
+                // Some successful responses carry no body
+                let body = response.text().await.map_err(|e| {
+                    CalendarError::ParseError(format!("Failed to read API response: {}", e))
+                })?;
+                
+                if body.trim().is_empty() {
+                    return Ok(Value::Null);
+                }
+                
+                serde_json::from_str(&body).map_err(|e| {
+                    CalendarError::ParseError(format!("Failed to parse API response: {}", e))
+                })
+            },
+            StatusCode::NOT_FOUND => {
+                Err(CalendarError::ParseError(format!(
+                    "No event with calendar event ID {} was found in your calendar",
+                    calendar_event_id
+                )))
             },
             status => {
                 let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
@@ -164,8 +642,496 @@ impl LumaApi {
     }
 }
 
+/// Parses a Retry-After header given either as delay-seconds or an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    
+    let retry_at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    (retry_at - Utc::now()).to_std().ok()
+}
+
+/// Exponential backoff before retry number `attempt + 1`, capped at [`MAX_BACKOFF`]
+///
+/// Saturates instead of overflowing, since `--max-retries` has no upper bound.
+fn backoff(attempt: u32) -> Duration {
+    let factor = 2u64.checked_pow(attempt).unwrap_or(u64::MAX);
+    Duration::from_millis(INITIAL_BACKOFF_MS.saturating_mul(factor)).min(MAX_BACKOFF)
+}
+
+/// Builds an event from a `list-events` entry, reading the nested `event` object
+/// when there is one
+fn event_from_api(entry: &Value) -> Option<Event> {
+    let event = entry.get("event").unwrap_or(entry);
+    let text = |name: &str| event.get(name).and_then(Value::as_str).map(str::to_string);
+    let time = |name: &str| {
+        DateTime::parse_from_rfc3339(event.get(name)?.as_str()?)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc))
+    };
+    
+    let summary = text("name")?;
+    let start = time("start_at")?;
+    let end = time("end_at").unwrap_or(start);
+    let location = event
+        .get("geo_address_json")
+        .and_then(|geo| geo.get("full_address").or_else(|| geo.get("address")))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    // Luma sends either a full link or just the slug
+    let url = text("url").map(|url| {
+        if url.starts_with("http") {
+            url
+        } else {
+            format!("https://lu.ma/{}", url)
+        }
+    });
+    
+    let mut parsed = Event::new(summary, text("description"), location, start, end, url);
+    parsed.api_id = text("api_id").or_else(|| entry.get("api_id").and_then(Value::as_str).map(str::to_string));
+    Some(parsed)
+}
+
 impl Default for LumaApi {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::time::Instant;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn api_for(server: &MockServer) -> LumaApi {
+        LumaApi::for_mock_server(&server.uri())
+    }
+
+    /// A response with the given status that asks to be retried immediately
+    fn retry_now(status: u16) -> ResponseTemplate {
+        ResponseTemplate::new(status).insert_header("Retry-After", "0")
+    }
+
+    fn lookup_found(api_id: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({ "entity": { "event": { "api_id": api_id } } }))
+    }
+
+    /// An event with no URL, so enriching it fails without a request
+    fn event_without_url() -> Event {
+        let start = Utc.with_ymd_and_hms(2030, 1, 15, 18, 0, 0).unwrap();
+        Event::new("Meetup".to_string(), None, None, start, start + chrono::Duration::hours(1), None)
+    }
+
+    #[test]
+    fn rate_limit_is_kept_from_the_constructor() {
+        assert_eq!(LumaApi::new().rate_limit(), Duration::from_millis(DEFAULT_RATE_LIMIT_MS));
+        assert_eq!(LumaApi::with_rate_limit(250).rate_limit(), Duration::from_millis(250));
+        assert_eq!(LumaApi::with_rate_limit(0).rate_limit(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn enrich_events_waits_the_rate_limit_after_each_event() {
+        let api = LumaApi::with_rate_limit(100);
+        let mut events = vec![event_without_url(), event_without_url(), event_without_url()];
+
+        let started = Instant::now();
+        let results = api.enrich_events(&mut events).await;
+
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(Result::is_err));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_are_retried_until_they_succeed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/entity/lookup"))
+            .respond_with(retry_now(429))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/entity/lookup"))
+            .and(query_param("slug", "rust-meetup"))
+            .respond_with(lookup_found("evt-123"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api_id = api_for(&server).lookup_event_id("rust-meetup").await.unwrap();
+        assert_eq!(api_id, "evt-123");
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_at_most_max_retries_times() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/entity/lookup"))
+            .respond_with(retry_now(503))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let result = api_for(&server).with_max_retries(2).lookup_event_id("rust-meetup").await;
+        assert!(result.unwrap_err().to_string().contains("503"));
+    }
+
+    #[tokio::test]
+    async fn client_errors_fail_without_a_retry() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/entity/lookup"))
+            .respond_with(retry_now(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert!(api_for(&server).lookup_event_id("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn remove_event_posts_the_calendar_event_id() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/calendar/remove-event"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_json(json!({ "calendar_event_id": "calev-1" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "removed": true })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = api_for(&server).remove_event("calev-1").await.unwrap();
+        assert_eq!(response, json!({ "removed": true }));
+    }
+
+    #[tokio::test]
+    async fn removing_an_unknown_event_says_it_was_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/calendar/remove-event"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("not found"))
+            .mount(&server)
+            .await;
+
+        let error = api_for(&server).remove_event("calev-missing").await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            CalendarError::ParseError(
+                "No event with calendar event ID calev-missing was found in your calendar".to_string()
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn blank_api_keys_are_discarded() {
+        assert_eq!(LumaApi::validate_key(None), None);
+        assert_eq!(LumaApi::validate_key(Some(String::new())), None);
+        assert_eq!(LumaApi::validate_key(Some("  \t ".to_string())), None);
+    }
+
+    #[test]
+    fn api_keys_containing_whitespace_are_discarded() {
+        assert_eq!(LumaApi::validate_key(Some("secret key".to_string())), None);
+    }
+
+    #[test]
+    fn valid_api_keys_are_kept_without_surrounding_whitespace() {
+        assert_eq!(LumaApi::validate_key(Some("secret-key".to_string())).as_deref(), Some("secret-key"));
+        assert_eq!(LumaApi::validate_key(Some(" secret-key\n".to_string())).as_deref(), Some("secret-key"));
+    }
+
+    #[tokio::test]
+    async fn requests_without_a_key_fail_before_reaching_the_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).expect(0).mount(&server).await;
+        let api = LumaApi { api_key: None, ..api_for(&server) };
+
+        let error = api.lookup_event_id("rust-meetup").await.unwrap_err();
+        assert!(error.to_string().contains(API_KEY_ENV));
+    }
+
+    #[tokio::test]
+    async fn event_details_are_read_from_the_api() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/event/get"))
+            .and(query_param("api_id", "evt-123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "event": {
+                    "api_id": "evt-123",
+                    "name": "Rust Meetup",
+                    "guest_count": 42,
+                    "max_capacity": 50,
+                    "waitlist_status": "enabled"
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let details = api_for(&server).get_event_details("evt-123").await.unwrap();
+
+        assert_eq!(
+            details,
+            EventDetails {
+                name: Some("Rust Meetup".to_string()),
+                guest_count: Some(42),
+                capacity: Some(50),
+                waitlist_active: true,
+            }
+        );
+        assert_eq!(details.spots_left(), Some(8));
+    }
+
+    #[tokio::test]
+    async fn events_without_a_capacity_are_unlimited() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/event/get"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "event": { "name": "Open House", "guest_count": 120, "max_capacity": null }
+            })))
+            .mount(&server)
+            .await;
+
+        let details = api_for(&server).get_event_details("evt-456").await.unwrap();
+
+        assert_eq!(details.capacity, None);
+        assert_eq!(details.spots_left(), None);
+        assert!(!details.waitlist_active);
+    }
+
+    #[tokio::test]
+    async fn details_of_an_unknown_event_are_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+
+        let error = api_for(&server).get_event_details("evt-missing").await.unwrap_err();
+        assert!(error.to_string().contains("No event with API ID evt-missing"));
+    }
+
+    #[test]
+    fn add_event_responses_deserialize_into_their_fields() {
+        let body = r#"{"calendar_event_id": "calev-1", "status": "approved"}"#;
+        let added: AddEventResponse = serde_json::from_str(body).unwrap();
+
+        assert_eq!(added.calendar_event_id, "calev-1");
+        assert!(serde_json::from_str::<AddEventResponse>(r#"{"status": "approved"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn add_event_posts_the_options_and_keeps_the_raw_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/calendar/add-event"))
+            .and(body_json(json!({
+                "event_api_id": "evt-123",
+                "platform": "luma",
+                "geo_address_json": { "type": "manual", "address": "1 Main St" }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "calendar_event_id": "calev-1",
+                "status": "approved"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let options = AddEventOptions::default().with_address("1 Main St");
+        let added = api_for(&server).add_event("evt-123", &options).await.unwrap();
+
+        assert_eq!(added.calendar_event_id, "calev-1");
+        assert_eq!(added.raw["status"], "approved");
+    }
+
+    #[tokio::test]
+    async fn add_event_responses_without_an_id_are_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "status": "approved" })))
+            .mount(&server)
+            .await;
+
+        let result = api_for(&server).add_event("evt-123", &AddEventOptions::default()).await;
+        assert!(result.unwrap_err().to_string().contains("Unexpected add-event response"));
+    }
+
+    #[tokio::test]
+    async fn batch_lookups_carry_on_past_a_failed_slug() {
+        let server = MockServer::start().await;
+        for (slug, api_id) in [("rust-meetup", "evt-rust"), ("go-meetup", "evt-go")] {
+            Mock::given(method("GET"))
+                .and(path("/entity/lookup"))
+                .and(query_param("slug", slug))
+                .respond_with(lookup_found(api_id))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/entity/lookup"))
+            .and(query_param("slug", "gone"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let slugs = ["rust-meetup", "gone", "go-meetup"].map(String::from);
+        let results = api_for(&server).lookup_event_ids(&slugs).await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_deref().unwrap(), "evt-rust");
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_deref().unwrap(), "evt-go");
+    }
+
+    #[test]
+    fn add_event_payload_reflects_the_overrides() {
+        assert_eq!(
+            AddEventOptions::default().payload("evt-123"),
+            json!({
+                "event_api_id": "evt-123",
+                "platform": "luma",
+                "geo_address_json": { "type": "manual" }
+            })
+        );
+
+        let options = AddEventOptions {
+            platform: "zoom".to_string(),
+            visibility: Some("private".to_string()),
+            ..AddEventOptions::default().with_address("Online")
+        };
+        assert_eq!(
+            options.payload("evt-123"),
+            json!({
+                "event_api_id": "evt-123",
+                "platform": "zoom",
+                "geo_address_json": { "type": "manual", "address": "Online" },
+                "visibility": "private"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn check_access_reports_whether_the_key_is_accepted() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/user/get-self"))
+            .and(header("Authorization", "Bearer test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "user": {} })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        api_for(&server).check_access().await.unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid api key"))
+            .mount(&server)
+            .await;
+        let error = api_for(&server).check_access().await.unwrap_err();
+        assert!(error.to_string().contains("invalid api key"));
+    }
+
+    #[tokio::test]
+    async fn calendar_listings_follow_the_cursor_across_pages() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/calendar/list-events"))
+            .and(query_param("pagination_cursor", "page-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "entries": [
+                    { "api_id": "evt-3", "event": {
+                        "name": "Hack Night", "start_at": "2030-01-29T18:00:00Z", "url": "hack-night"
+                    } },
+                    { "api_id": "evt-4", "event": { "name": "No Start Time" } }
+                ],
+                "has_more": false
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/calendar/list-events"))
+            .and(query_param("calendar_api_id", "cal-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "entries": [
+                    { "api_id": "evt-1", "event": {
+                        "name": "Rust Meetup",
+                        "start_at": "2030-01-15T18:00:00Z",
+                        "end_at": "2030-01-15T20:00:00Z",
+                        "url": "https://lu.ma/rust-meetup",
+                        "geo_address_json": { "full_address": "1 Main St" }
+                    } },
+                    { "api_id": "evt-2", "event": { "name": "Go Meetup", "start_at": "2030-01-22T18:00:00Z" } }
+                ],
+                "has_more": true,
+                "next_cursor": "page-2"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let events = api_for(&server).list_calendar_events("cal-1").await.unwrap();
+
+        let listed: Vec<(&str, Option<&str>)> =
+            events.iter().map(|event| (event.summary.as_str(), event.api_id.as_deref())).collect();
+        assert_eq!(
+            listed,
+            [("Rust Meetup", Some("evt-1")), ("Go Meetup", Some("evt-2")), ("Hack Night", Some("evt-3"))]
+        );
+        assert_eq!(events[0].location.as_deref(), Some("1 Main St"));
+        assert_eq!(events[0].end, Utc.with_ymd_and_hms(2030, 1, 15, 20, 0, 0).unwrap());
+        assert_eq!(events[2].url.as_deref(), Some("https://lu.ma/hack-night"));
+    }
+
+    #[tokio::test]
+    async fn registration_status_covers_going_waitlisted_and_not_registered() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/user/get-self"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "user": { "email": "me@example.com" } })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        for (api_id, status) in [("evt-going", "approved"), ("evt-waitlist", "waitlist")] {
+            Mock::given(method("GET"))
+                .and(path("/event/get-guest"))
+                .and(query_param("event_api_id", api_id))
+                .and(query_param("email", "me@example.com"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "guest": { "approval_status": status } })))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/event/get-guest"))
+            .and(query_param("event_api_id", "evt-stranger"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api = api_for(&server);
+        assert_eq!(api.get_registration_status("evt-going").await.unwrap(), Some(RegistrationStatus::Going));
+        assert_eq!(api.get_registration_status("evt-waitlist").await.unwrap(), Some(RegistrationStatus::Waitlisted));
+        assert_eq!(api.get_registration_status("evt-stranger").await.unwrap(), None);
+    }
+
+    #[test]
+    fn backoff_doubles_and_saturates_at_the_cap() {
+        assert_eq!(backoff(0), Duration::from_millis(INITIAL_BACKOFF_MS));
+        assert_eq!(backoff(3), Duration::from_millis(INITIAL_BACKOFF_MS * 8));
+        for attempt in [7, 55, 64, 1000, u32::MAX] {
+            assert_eq!(backoff(attempt), MAX_BACKOFF, "{}", attempt);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::models::StableHasher;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Subdirectory of the user cache directory holding cached calendars
+const CACHE_DIR_NAME: &str = "lumabot";
+
+/// A calendar body together with the validators needed to revalidate it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedCalendar {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+/// On-disk cache of fetched calendars, one JSON file per URL
+pub struct CalendarCache {
+    dir: PathBuf,
+}
+
+impl CalendarCache {
+    /// Opens the cache in `dir`, or in the user cache directory if the platform has one
+    pub fn open(dir: Option<PathBuf>) -> Option<Self> {
+        let dir = dir.or_else(|| dirs::cache_dir().map(|root| root.join(CACHE_DIR_NAME)))?;
+        Some(Self { dir })
+    }
+
+    /// Returns the cached copy of `key`, ignoring missing or unreadable entries
+    pub fn load(&self, key: &CacheKey) -> Option<CachedCalendar> {
+        let contents = fs::read_to_string(self.path_for(key)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Stores the latest copy of `key`
+    pub fn store(&self, key: &CacheKey, calendar: &CachedCalendar) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string(calendar).map_err(io::Error::other)?;
+        fs::write(self.path_for(key), json)
+    }
+
+    fn path_for(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", key.0))
+    }
+}
+
+/// What a cached response is filed under: the URL together with the token and
+/// extra headers it was requested with, since those can change the response
+pub struct CacheKey(u64);
+
+impl CacheKey {
+    pub fn new(url: &str, auth_token: Option<&str>, headers: &[(String, String)]) -> Self {
+        let mut hasher = StableHasher::new();
+        hasher.field(url.as_bytes());
+        hasher.field(auth_token.unwrap_or_default().as_bytes());
+        for (name, value) in headers {
+            hasher.field(name.to_ascii_lowercase().as_bytes());
+            hasher.field(value.as_bytes());
+        }
+        Self(hasher.finish())
+    }
+}
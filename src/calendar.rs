@@ -1,37 +1,390 @@
+use crate::cache::{CacheKey, CachedCalendar, CalendarCache};
 use crate::errors::CalendarError;
-use crate::models::Event;
-use chrono::{DateTime, TimeZone, Utc};
-use ical::parser::ical::component::IcalCalendar;
+use crate::models::{CalendarMethod, Event, EventStatus};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use ical::parser::ical::component::{IcalCalendar, IcalEvent};
 use ical::parser::ical::IcalParser;
-use reqwest::blocking::Client;
-use std::io::BufReader;
+use ical::property::Property;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{NoProxy, Proxy, StatusCode};
+use std::collections::HashSet;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{debug, warn};
+use url::Url;
+
+/// Events that ended more than this many days ago are dropped by default
+pub const DEFAULT_RETENTION_DAYS: u32 = 2;
+
+/// Length in minutes given to timed events that have no duration of their own
+pub const DEFAULT_EVENT_DURATION_MINUTES: u32 = 60;
+
+/// Options controlling which events are kept when parsing a calendar
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Keep events whose STATUS is CANCELLED
+    pub include_cancelled: bool,
+    /// Drop events that ended more than this many days ago
+    pub retention_days: u32,
+    /// Fail on the first malformed event instead of skipping it with a warning
+    pub strict: bool,
+    /// Length in minutes given to timed events that end when (or before) they
+    /// start; 0 keeps them zero-length
+    pub default_duration_minutes: u32,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            include_cancelled: false,
+            retention_days: DEFAULT_RETENTION_DAYS,
+            strict: false,
+            default_duration_minutes: DEFAULT_EVENT_DURATION_MINUTES,
+        }
+    }
+}
+
+/// User-Agent sent with calendar requests unless overridden
+pub const DEFAULT_USER_AGENT: &str = concat!("Luma-Calendar-CLI/", env!("CARGO_PKG_VERSION"));
+
+/// Luma's ICS feed for a calendar, to which the calendar ID is appended
+const LUMA_ICS_ENDPOINT: &str = "https://api.lu.ma/ics/get?entity=calendar&id=";
+
+/// Returns the ICS feed URL of a Luma calendar given its ID (e.g. `cal-4dWxlBFjW9Cd6ou`)
+pub fn luma_calendar_url(calendar_id: &str) -> String {
+    format!("{}{}", LUMA_ICS_ENDPOINT, calendar_id.trim())
+}
+
+/// Default limit on a calendar fetch, including streaming the body
+pub const DEFAULT_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Default number of times a fetch is retried after a connection error or timeout
+pub const DEFAULT_FETCH_RETRIES: u32 = 2;
+
+/// Delay before the first retry of a fetch; later retries wait longer
+const FETCH_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Options controlling how a calendar is requested
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// Revalidate the cached copy with the server and reuse it when unchanged
+    pub use_cache: bool,
+    /// Directory holding cached calendars, instead of the user cache directory
+    pub cache_dir: Option<PathBuf>,
+    /// Extra `(name, value)` headers sent with the request
+    pub headers: Vec<(String, String)>,
+    /// User-Agent to send instead of [`DEFAULT_USER_AGENT`]
+    pub user_agent: Option<String>,
+    /// Token sent as `Authorization: Bearer <token>`, for private calendar feeds
+    pub auth_token: Option<String>,
+    /// Give up on a request that takes longer than this
+    pub timeout: Duration,
+    /// Retries after a connection error or timeout (HTTP error statuses are not retried)
+    pub retries: u32,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            use_cache: false,
+            cache_dir: None,
+            headers: Vec::new(),
+            user_agent: None,
+            auth_token: None,
+            timeout: DEFAULT_FETCH_TIMEOUT,
+            retries: DEFAULT_FETCH_RETRIES,
+        }
+    }
+}
 
 /// Fetches and parses a calendar from a URL
-pub fn fetch_and_parse_calendar(url: &str) -> Result<Vec<Event>, CalendarError> {
-    // Fetch the calendar
-    let response = Client::new()
+///
+/// Cancelled events are skipped unless `options.include_cancelled` is set. With
+/// `fetch.use_cache`, the previous response is revalidated with `If-None-Match` /
+/// `If-Modified-Since` and reused when the server answers `304 Not Modified`.
+/// Proxies are taken from `HTTPS_PROXY` / `HTTP_PROXY`, excluding hosts in `NO_PROXY`.
+/// A URL without a scheme is fetched over https; see [`normalize_calendar_url`].
+/// Calls share one HTTP client, so repeated fetches reuse open connections.
+pub fn fetch_and_parse_calendar(
+    url: &str,
+    options: ParseOptions,
+    fetch: &FetchOptions,
+) -> Result<Vec<Event>, CalendarError> {
+    let url = normalize_calendar_url(url)?;
+    let url = url.as_str();
+    let cache = if fetch.use_cache { CalendarCache::open(fetch.cache_dir.clone()) } else { None };
+    let cache_key = CacheKey::new(url, fetch.auth_token.as_deref(), &fetch.headers);
+    let cached = cache.as_ref().and_then(|cache| cache.load(&cache_key));
+
+    // Fetch the calendar, sending the cached validators if there are any
+    let user_agent = fetch.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+    let mut request = http_client()?
         .get(url)
-        .header("User-Agent", "Luma-Calendar-CLI/0.1.0")
-        .send()
-        .map_err(CalendarError::FetchError)?;
+        .header(header::USER_AGENT, user_agent)
+        .headers(request_headers(&fetch.headers)?);
+    if let Some(token) = &fetch.auth_token {
+        request = request.bearer_auth(token);
+    }
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = send_with_retry(request, fetch.timeout, fetch.retries)?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(cached) = cached {
+            debug!("Calendar not modified, using cached copy of {}", url);
+            return parse_calendar_from_reader(cached.body.as_bytes(), options);
+        }
+    }
 
     if !response.status().is_success() {
-        return Err(CalendarError::ParseError(
-            format!("Failed to fetch calendar: HTTP {}", response.status())
-        ));
+        let status = response.status().as_u16();
+        // The body usually says why (e.g. the calendar is private); a failure to
+        // read it should not hide the status itself
+        let body = response.text().unwrap_or_default();
+        return Err(CalendarError::HttpStatus { status, body: truncate_body(&body) });
     }
 
-    // Parse the calendar
-    let content = response.text().map_err(CalendarError::FetchError)?;
-    let buf_reader = BufReader::new(content.as_bytes());
-    let parser = IcalParser::new(buf_reader);
+    let header_value = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    // Web pages (e.g. the calendar's lu.ma page instead of its ICS link) would
+    // otherwise fail deep inside the parser with an unhelpful error
+    if let Some(content_type) = header_value(header::CONTENT_TYPE) {
+        if content_type.trim_start().to_ascii_lowercase().starts_with("text/html") {
+            return Err(CalendarError::NotCalendar(format!(
+                "{} returned an HTML page ({}), which doesn't look like an ICS feed; \
+                 use the calendar's ICS subscription link instead",
+                url, content_type
+            )));
+        }
+    }
+
+    let etag = header_value(header::ETAG);
+    let last_modified = header_value(header::LAST_MODIFIED);
+
+    // Only responses with validators are worth caching, so only those keep a
+    // copy of the body while it is streamed into the parser
+    let cache = cache.filter(|_| etag.is_some() || last_modified.is_some());
+    let mut body = TeeReader { inner: response, copy: cache.as_ref().map(|_| Vec::new()) };
+    let events = parse_calendar_from_reader(BufReader::new(&mut body), options)?;
+
+    if let (Some(cache), Some(copy)) = (&cache, body.copy) {
+        let body = String::from_utf8_lossy(&copy).into_owned();
+        let entry = CachedCalendar { etag, last_modified, body };
+        if let Err(e) = cache.store(&cache_key, &entry) {
+            warn!("Failed to write calendar cache: {}", e);
+        }
+    }
+
+    Ok(events)
+}
+
+/// Checks a calendar URL before it is fetched
+///
+/// A URL without a scheme (e.g. `api.lu.ma/ics/get?entity=calendar&id=...`) is
+/// taken to mean https. Anything but http(s), such as `file:` or `webcal:`, is
+/// rejected.
+pub fn normalize_calendar_url(url: &str) -> Result<Url, CalendarError> {
+    let url = url.trim();
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(url::ParseError::RelativeUrlWithoutBase) => Url::parse(&format!("https://{}", url))
+            .map_err(|e| CalendarError::InvalidUrl(format!("{}: {}", url, e)))?,
+        Err(e) => return Err(CalendarError::InvalidUrl(format!("{}: {}", url, e))),
+    };
+
+    // `host:port/path` parses with `host` as its scheme
+    if parsed.cannot_be_a_base() && !url.contains("://") {
+        return normalize_calendar_url(&format!("https://{}", url));
+    }
+
+    match parsed.scheme() {
+        "http" | "https" if parsed.host_str().is_some_and(|host| !host.is_empty()) => Ok(parsed),
+        "http" | "https" => Err(CalendarError::InvalidUrl(format!("{}: missing host", url))),
+        scheme => Err(CalendarError::InvalidUrl(format!(
+            "{}: unsupported scheme {:?}, only http and https calendars can be fetched",
+            url, scheme
+        ))),
+    }
+}
+
+/// Client shared by every fetch in the process, so `--watch` and repeated
+/// fetches reuse its keep-alive connections instead of reconnecting each time
+static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Returns the shared HTTP client, building it with the proxies from the
+/// environment on first use
+fn http_client() -> Result<Client, CalendarError> {
+    if let Some(client) = HTTP_CLIENT.get() {
+        debug!("Reusing HTTP client and its open connections");
+        return Ok(client.clone());
+    }
+
+    let client = build_client(env_proxies()?)?;
+
+    // Cloning only bumps a reference count; the connection pool is shared
+    Ok(HTTP_CLIENT.get_or_init(|| client).clone())
+}
+
+/// Builds an HTTP client sending the default User-Agent through `proxies`
+fn build_client(proxies: Vec<Proxy>) -> Result<Client, CalendarError> {
+    let mut builder = Client::builder().user_agent(DEFAULT_USER_AGENT);
+    for proxy in proxies {
+        builder = builder.proxy(proxy);
+    }
+    builder.build().map_err(CalendarError::FetchError)
+}
+
+/// Sends a request with `timeout`, retrying up to `retries` times after
+/// connection errors and timeouts
+///
+/// Any response, whatever its status, is returned as is: the server answered,
+/// so asking again is unlikely to help.
+fn send_with_retry(
+    request: RequestBuilder,
+    timeout: Duration,
+    retries: u32,
+) -> Result<Response, CalendarError> {
+    let mut attempt = 0;
+    loop {
+        // A GET has no body, so the request can always be cloned. Cloning drops
+        // the timeout, which is why it is set on every attempt
+        let Some(this_try) = request.try_clone() else {
+            return request.timeout(timeout).send().map_err(CalendarError::FetchError);
+        };
+
+        match this_try.timeout(timeout).send() {
+            Err(e) if (e.is_connect() || e.is_timeout()) && attempt < retries => {
+                attempt += 1;
+                let delay = FETCH_RETRY_DELAY * attempt;
+                warn!("Fetch failed ({}), retrying in {:?} ({}/{})", e, delay, attempt, retries);
+                std::thread::sleep(delay);
+            }
+            result => return result.map_err(CalendarError::FetchError),
+        }
+    }
+}
+
+/// Turns `(name, value)` pairs from `--header` into request headers
+fn request_headers(headers: &[(String, String)]) -> Result<HeaderMap, CalendarError> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| CalendarError::EnvError(format!("Invalid header name {:?}: {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| CalendarError::EnvError(format!("Invalid value for header {}: {}", name, e)))?;
+        map.append(name, value);
+    }
+
+    Ok(map)
+}
+
+/// Proxies configured by `HTTPS_PROXY` and `HTTP_PROXY` (or their lowercase forms),
+/// each skipping the hosts listed in `NO_PROXY`
+fn env_proxies() -> Result<Vec<Proxy>, CalendarError> {
+    proxies_from(|name| env::var(name).ok())
+}
+
+/// Proxies configured by the variables `var` looks up; see [`env_proxies`]
+fn proxies_from(var: impl Fn(&str) -> Option<String>) -> Result<Vec<Proxy>, CalendarError> {
+    let env_var = |names: [&str; 2]| {
+        names
+            .iter()
+            .find_map(|name| var(name))
+            .filter(|value| !value.trim().is_empty())
+    };
+    let no_proxy = || env_var(["NO_PROXY", "no_proxy"]).and_then(|hosts| NoProxy::from_string(&hosts));
+    let invalid = |var: &str, e: reqwest::Error| {
+        CalendarError::EnvError(format!("Invalid proxy URL in {}: {}", var, e))
+    };
+
+    let mut proxies = Vec::new();
+    if let Some(url) = env_var(["HTTPS_PROXY", "https_proxy"]) {
+        debug!("Using HTTPS proxy {}", url);
+        let proxy = Proxy::https(url.trim()).map_err(|e| invalid("HTTPS_PROXY", e))?;
+        proxies.push(proxy.no_proxy(no_proxy()));
+    }
+    if let Some(url) = env_var(["HTTP_PROXY", "http_proxy"]) {
+        debug!("Using HTTP proxy {}", url);
+        let proxy = Proxy::http(url.trim()).map_err(|e| invalid("HTTP_PROXY", e))?;
+        proxies.push(proxy.no_proxy(no_proxy()));
+    }
+
+    Ok(proxies)
+}
+
+/// Longest response body quoted in an HTTP status error, in characters
+const MAX_ERROR_BODY_CHARS: usize = 200;
+
+/// Collapses a response body to a single trimmed line of at most `MAX_ERROR_BODY_CHARS`
+fn truncate_body(body: &str) -> String {
+    let body = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    match body.char_indices().nth(MAX_ERROR_BODY_CHARS) {
+        Some((end, _)) => format!("{}…", &body[..end]),
+        None => body,
+    }
+}
+
+/// A reader that keeps a copy of everything read through it when `copy` is set
+struct TeeReader<R> {
+    inner: R,
+    copy: Option<Vec<u8>>,
+}
+
+impl<R: Read> Read for TeeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(copy) = &mut self.copy {
+            copy.extend_from_slice(&buf[..read]);
+        }
+        Ok(read)
+    }
+}
+
+/// Reads and parses a calendar from a local `.ics` file, or stdin when `path` is `-`
+pub fn parse_calendar_file(path: &Path, options: ParseOptions) -> Result<Vec<Event>, CalendarError> {
+    if path == Path::new("-") {
+        return parse_calendar_from_reader(io::stdin().lock(), options);
+    }
+
+    let file = File::open(path).map_err(|e| {
+        io::Error::new(e.kind(), format!("Failed to open {}: {}", path.display(), e))
+    })?;
+    parse_calendar_from_reader(BufReader::new(file), options)
+}
+
+/// Parses every calendar in an iCalendar stream, returning events sorted by start time
+///
+/// The stream is unfolded and parsed line by line, so only the parsed events
+/// (not the raw document) are held in memory.
+pub fn parse_calendar_from_reader<R: BufRead>(
+    reader: R,
+    options: ParseOptions,
+) -> Result<Vec<Event>, CalendarError> {
+    let parser = IcalParser::new(UnfoldingReader::new(reader));
 
     let mut events = Vec::new();
 
     for calendar in parser {
         match calendar {
             Ok(cal) => {
-                let parsed_events = parse_calendar_events(&cal)?;
+                let parsed_events = parse_calendar_events(&cal, options)?;
                 events.extend(parsed_events);
             }
             Err(e) => {
@@ -44,159 +397,657 @@ pub fn fetch_and_parse_calendar(url: &str) -> Result<Vec<Event>, CalendarError>
     }
 
     // Sort events by start time
-    events.sort_by(|a, b| a.start.cmp(&b.start));
+    events.sort_by_key(|e| e.start);
     Ok(events)
 }
 
+/// Unfolds RFC 5545 continuation lines (a line break followed by a space or tab)
+/// while streaming, buffering at most one logical line at a time
+///
+/// Line endings are normalized to `\n` on the way: some calendars are served
+/// with bare `\r` (old Mac) or a mix of `\r\n` and `\n`, which the ical crate
+/// would otherwise merge into or split across lines.
+struct UnfoldingReader<R> {
+    inner: R,
+    /// The unfolded line currently being handed out
+    line: Vec<u8>,
+    pos: usize,
+    /// The physical line read ahead to check for a continuation
+    next: Vec<u8>,
+}
+
+impl<R: BufRead> UnfoldingReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, line: Vec::new(), pos: 0, next: Vec::new() }
+    }
+
+    /// Reads the next logical line into `line`, joining any continuation lines
+    fn read_logical_line(&mut self) -> io::Result<()> {
+        self.line.clear();
+        self.pos = 0;
+        if self.next.is_empty() {
+            read_physical_line(&mut self.inner, &mut self.line)?;
+        } else {
+            std::mem::swap(&mut self.line, &mut self.next);
+        }
+
+        while self.line.ends_with(b"\n") {
+            self.next.clear();
+            read_physical_line(&mut self.inner, &mut self.next)?;
+            if !self.next.starts_with(b" ") && !self.next.starts_with(b"\t") {
+                break;
+            }
+
+            self.line.pop();
+            self.line.extend_from_slice(&self.next[1..]);
+            self.next.clear();
+        }
+
+        Ok(())
+    }
+}
+
+/// Appends one physical line to `line`, ending it with `\n` whichever of
+/// `\r\n`, `\n` or a bare `\r` the stream uses; the last line may have no ending
+fn read_physical_line<R: BufRead>(reader: &mut R, line: &mut Vec<u8>) -> io::Result<()> {
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(());
+        }
+
+        let Some(index) = available.iter().position(|&byte| byte == b'\n' || byte == b'\r') else {
+            let read = available.len();
+            line.extend_from_slice(available);
+            reader.consume(read);
+            continue;
+        };
+
+        let ending = available[index];
+        line.extend_from_slice(&available[..index]);
+        line.push(b'\n');
+        reader.consume(index + 1);
+
+        // The \n of a \r\n pair may only arrive with the next chunk
+        if ending == b'\r' && reader.fill_buf()?.first() == Some(&b'\n') {
+            reader.consume(1);
+        }
+        return Ok(());
+    }
+}
+
+impl<R: BufRead> Read for UnfoldingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for UnfoldingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.line.len() {
+            self.read_logical_line()?;
+        }
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.pos = (self.pos + amount).min(self.line.len());
+    }
+}
+
 /// Parses events from a calendar
-fn parse_calendar_events(calendar: &IcalCalendar) -> Result<Vec<Event>, CalendarError> {
+fn parse_calendar_events(
+    calendar: &IcalCalendar,
+    options: ParseOptions,
+) -> Result<Vec<Event>, CalendarError> {
     let mut events = Vec::new();
-    // Calculate the date that is two days ago from now
-    let two_days_ago = Utc::now() - chrono::Duration::days(2);
+    // Calculate the start of the retention window
+    let cutoff = Utc::now() - chrono::Duration::days(options.retention_days as i64);
 
-    for component in &calendar.events {
-        // Extract event properties
-        let summary = component
-            .properties
-            .iter()
-            .find(|p| p.name == "SUMMARY")
-            .and_then(|p| p.value.clone())
-            .unwrap_or_else(|| "Untitled Event".to_string());
+    // Invitations and cancellations sent by email say so in the calendar-level METHOD
+    let method = calendar
+        .properties
+        .iter()
+        .find(|p| p.name == "METHOD")
+        .and_then(|p| p.value.as_deref())
+        .map(CalendarMethod::from_ical)
+        .unwrap_or_default();
+    if method != CalendarMethod::Publish {
+        debug!("Calendar uses METHOD {:?} with {} event(s)", method, calendar.events.len());
+    }
+
+    let mut skipped = 0;
+    for (index, component) in calendar.events.iter().enumerate() {
+        match parse_event(component, method, options, cutoff) {
+            Ok(Some(event)) => events.push(event),
+            Ok(None) => {}
+            Err(e) if options.strict => return Err(e),
+            Err(e) => {
+                // One malformed VEVENT should not cost the rest of the calendar
+                let uid = component
+                    .properties
+                    .iter()
+                    .find(|p| p.name == "UID")
+                    .and_then(|p| p.value.as_deref())
+                    .unwrap_or("no UID");
+                warn!("Skipping event #{} ({}): {}", index + 1, uid, e);
+                skipped += 1;
+            }
+        }
+    }
+    if skipped > 0 {
+        warn!("Skipped {} malformed event(s); use --strict to fail instead", skipped);
+    }
+
+    warn_duplicate_uids(&events);
+
+    Ok(events)
+}
+
+/// Parses one VEVENT, returning `None` for events that are filtered out
+/// (cancelled, or ended before the retention window)
+fn parse_event(
+    component: &IcalEvent,
+    method: CalendarMethod,
+    options: ParseOptions,
+    cutoff: DateTime<Utc>,
+) -> Result<Option<Event>, CalendarError> {
+    let status = component
+        .properties
+        .iter()
+        .find(|p| p.name == "STATUS")
+        .and_then(|p| p.value.as_deref())
+        .map(EventStatus::from_ical)
+        .unwrap_or_default();
+
+    // A METHOD:CANCEL message withdraws its events whatever their STATUS says
+    let status = if method == CalendarMethod::Cancel {
+        EventStatus::Cancelled
+    } else {
+        status
+    };
+
+    // Skip cancelled events unless explicitly requested
+    if status == EventStatus::Cancelled && !options.include_cancelled {
+        return Ok(None);
+    }
 
-        let description = component
+    // Extract event properties, keeping the raw values for the legacy UIDs
+    let raw_value = |name: &str| {
+        component
             .properties
             .iter()
-            .find(|p| p.name == "DESCRIPTION")
-            .and_then(|p| p.value.clone());
+            .find(|p| p.name == name)
+            .and_then(|p| p.value.as_deref())
+    };
+    let raw_summary = raw_value("SUMMARY").unwrap_or("Untitled Event");
+    let raw_description = raw_value("DESCRIPTION");
+    let raw_location = raw_value("LOCATION");
 
-        let location = component
-            .properties
+    let summary = Event::unescape_ical(raw_summary);
+    let description = raw_description.map(Event::unescape_ical);
+    let location = raw_location.map(Event::unescape_ical);
+
+    let description_links = description.as_deref().map(extract_links).unwrap_or_default();
+
+    // Check for both URL and url property names (case sensitivity matters in iCal)
+    let url = component
+        .properties
+        .iter()
+        .find(|p| p.name == "URL" || p.name == "url")
+        .and_then(|p| p.value.clone());
+    
+    // Clean up the URL if it exists
+    let url = if let Some(url_str) = url {
+        // Clean the URL by removing anything after Address: or newlines
+        let mut clean_url = url_str.trim().to_string();
+        
+        // Remove anything after Address: (case insensitive and with various formats)
+        for pattern in &["\n\nAddress:", "\nAddress:", "Address:", "\n\naddress:", "\naddress:", "address:"] {
+            if let Some(idx) = clean_url.to_lowercase().find(&pattern.to_lowercase()) {
+                clean_url = clean_url[0..idx].trim().to_string();
+                break;
+            }
+        }
+        
+        // Remove any newlines from the URL completely
+        clean_url = clean_url.replace('\n', "").trim().to_string();
+        
+        Some(clean_url)
+    } else {
+        // If URL is not found, fall back to the links in DESCRIPTION, preferring
+        // the lu.ma registration page over e.g. maps or video-call links
+        description_links
             .iter()
-            .find(|p| p.name == "LOCATION")
-            .and_then(|p| p.value.clone());
+            .find(|link| is_luma_url(link))
+            .or_else(|| description_links.first())
+            .cloned()
+    };
+
+    // Every other link in the description is kept alongside the main URL
+    let links = description_links
+        .into_iter()
+        .filter(|link| Some(link) != url.as_ref())
+        .collect();
+
+    let organizer = component
+        .properties
+        .iter()
+        .find(|p| p.name == "ORGANIZER")
+        .and_then(person_name);
+
+    let attendees = component
+        .properties
+        .iter()
+        .filter(|p| p.name == "ATTENDEE")
+        .filter_map(person_name)
+        .collect();
+
+    // CATEGORIES may repeat and each holds a comma-separated list
+    let mut categories: Vec<String> = Vec::new();
+    for category in component
+        .properties
+        .iter()
+        .filter(|p| p.name == "CATEGORIES")
+        .filter_map(|p| p.value.as_deref())
+        .flat_map(split_categories)
+    {
+        if !categories.iter().any(|known| known.eq_ignore_ascii_case(&category)) {
+            categories.push(category);
+        }
+    }
+
+    // Malformed coordinates are dropped rather than failing the whole event
+    let geo = component
+        .properties
+        .iter()
+        .find(|p| p.name == "GEO")
+        .and_then(|p| p.value.as_deref())
+        .and_then(parse_geo);
+
+    // Revision markers used to tell whether a stored copy is out of date
+    let sequence = component
+        .properties
+        .iter()
+        .find(|p| p.name == "SEQUENCE")
+        .and_then(|p| p.value.as_deref())
+        .and_then(|value| value.trim().parse::<i32>().ok());
+
+    let last_modified = component
+        .properties
+        .iter()
+        .find(|p| p.name == "LAST-MODIFIED")
+        .and_then(|p| p.value.as_deref())
+        .and_then(|value| parse_ical_datetime(value, None, None).ok());
+
+    // Parse start and end times
+    let start_prop = component
+        .properties
+        .iter()
+        .find(|p| p.name == "DTSTART")
+        .filter(|p| p.value.is_some())
+        .ok_or_else(|| {
+            CalendarError::ParseError("Event missing DTSTART property".to_string())
+        })?;
+
+    let end_prop = component
+        .properties
+        .iter()
+        .find(|p| p.name == "DTEND")
+        .filter(|p| p.value.is_some());
+
+    let duration = component
+        .properties
+        .iter()
+        .find(|p| p.name == "DURATION")
+        .and_then(|p| p.value.clone());
 
-        // Check for both URL and url property names (case sensitivity matters in iCal)
-        let url = component
+    // Parse dates in format: 20220101T120000Z, honoring any TZID parameter.
+    // VALUE=DATE or VALUE=DATE-TIME decides the kind; without it the digits do
+    let start_value = start_prop.value.as_deref().unwrap_or_default();
+    let start_kind = match DateValue::of(start_prop) {
+        Some(kind) => kind,
+        None => DateValue::infer(start_value)?,
+    };
+    let start_time = parse_ical_datetime(start_value, get_param(start_prop, "TZID"), Some(start_kind))?;
+
+    // Fall back to DTSTART + DURATION when DTEND is absent, or to a zero-length
+    // event when neither is present
+    let end_time = match (end_prop, duration) {
+        (Some(end_prop), _) => parse_ical_datetime(
+            end_prop.value.as_deref().unwrap_or_default(),
+            get_param(end_prop, "TZID"),
+            DateValue::of(end_prop),
+        )?,
+        (None, Some(duration)) => start_time + parse_ical_duration(&duration)?,
+        (None, None) => start_time,
+    };
+    // Malformed feeds (or timezone bugs upstream) can end an event before it starts
+    let end_time = if end_time < start_time {
+        warn!(
+            "{} ends at {} before it starts at {}; treating it as zero-length",
+            summary, end_time, start_time
+        );
+        start_time
+    } else {
+        end_time
+    };
+
+    // Date-only values (VALUE=DATE) mark an all-day event
+    let all_day = start_kind == DateValue::Date;
+
+    // Filter out events that ended before the retention window
+    if end_time < cutoff {
+        return Ok(None);
+    }
+
+    let mut event = Event::new(
+        summary,
+        description,
+        location,
+        start_time,
+        end_time,
+        url,
+    );
+    event.all_day = all_day;
+    event.status = status;
+    event.organizer = organizer;
+    event.attendees = attendees;
+    event.geo = geo;
+    event.sequence = sequence;
+    event.last_modified = last_modified;
+    event.method = method;
+    event.links = links;
+    event.categories = categories;
+
+    // A zero-length event would display as "6:00 PM - 6:00 PM"; give it a visible
+    // length, after the UID was derived from the feed's own times
+    if !all_day && end_time == start_time && options.default_duration_minutes > 0 {
+        event.original_end = Some(end_time);
+        event.end = start_time + chrono::Duration::minutes(options.default_duration_minutes as i64);
+    }
+
+    // The feed's own UID identifies the event better than its content does
+    let property_value = |name: &str| {
+        component
             .properties
             .iter()
-            .find(|p| p.name == "URL" || p.name == "url")
-            .and_then(|p| p.value.clone());
-        
-        // Clean up the URL if it exists
-        let url = if let Some(url_str) = url {
-            // Clean the URL by removing anything after Address: or newlines
-            let mut clean_url = url_str.trim().to_string();
-            
-            // Remove anything after Address: (case insensitive and with various formats)
-            for pattern in &["\n\nAddress:", "\nAddress:", "Address:", "\n\naddress:", "\naddress:", "address:"] {
-                if let Some(idx) = clean_url.to_lowercase().find(&pattern.to_lowercase()) {
-                    clean_url = clean_url[0..idx].trim().to_string();
-                    break;
+            .find(|p| p.name == name)
+            .and_then(|p| p.value.as_deref())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    if let Some(uid) = property_value("UID") {
+        // UID is a TEXT value, so it may carry escapes (e.g. from our own export)
+        event.set_ical_uid(&Event::unescape_ical(uid), property_value("RECURRENCE-ID"));
+    }
+    event.record_legacy_uids(raw_summary, raw_description, raw_location);
+    Ok(Some(event))
+}
+
+/// Warns about events sharing a UID, which the database would store as one row
+fn warn_duplicate_uids(events: &[Event]) {
+    let mut seen = HashSet::new();
+    for event in events {
+        if !seen.insert(event.event_uid.as_str()) {
+            warn!(
+                "Duplicate event UID {} ({} at {}); only one of them will be stored",
+                event.event_uid, event.summary, event.start
+            );
+        }
+    }
+}
+
+/// Returns the first value of a property parameter (e.g. TZID), if present
+fn get_param<'a>(property: &'a Property, key: &str) -> Option<&'a str> {
+    property
+        .params
+        .as_ref()?
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .and_then(|(_, values)| values.first())
+        .map(|value| value.as_str())
+}
+
+/// Returns every distinct http(s) link in a text, in order of appearance
+///
+/// A link ends at whitespace, and anything from an `Address:` label onwards
+/// (which Luma appends to descriptions) is dropped.
+fn extract_links(text: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("http") {
+        let candidate = &rest[start..];
+        let end = candidate.find(char::is_whitespace).unwrap_or(candidate.len());
+        rest = &candidate[end..];
+
+        let mut link = &candidate[..end];
+        if let Some(idx) = link.to_ascii_lowercase().find("address:") {
+            link = &link[..idx];
+        }
+        // Drop sentence punctuation that commonly follows a link
+        let link = link.trim_end_matches(['.', ',', ';', ')', '>']);
+
+        let is_link = link.starts_with("http://") || link.starts_with("https://");
+        if is_link && !links.iter().any(|known| known == link) {
+            links.push(link.to_string());
+        }
+    }
+
+    links
+}
+
+/// Returns true for links to a lu.ma page
+fn is_luma_url(link: &str) -> bool {
+    Url::parse(link)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host == "lu.ma" || host.ends_with(".lu.ma")))
+        .unwrap_or(false)
+}
+
+/// Splits a CATEGORIES value on unescaped commas, unescaping and trimming each tag
+fn split_categories(value: &str) -> Vec<String> {
+    let mut categories = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
                 }
             }
-            
-            // Remove any newlines from the URL completely
-            clean_url = clean_url.replace('\n', "").trim().to_string();
-            
-            Some(clean_url)
-        } else {
-            // If URL is not found, try to look for it in DESCRIPTION
-            if let Some(desc) = &description {
-                // Try to extract a URL from the description
-                // This is a simple extraction that looks for http:// or https:// patterns
-                if let Some(start_idx) = desc.find("http") {
-                    // Find the end of the URL (whitespace, newline, or end of string)
-                    let substring = &desc[start_idx..];
-                    let end_idx = substring.find(|c: char| c.is_whitespace())
-                        .map(|pos| pos)
-                        .unwrap_or_else(|| substring.len());
-                    
-                    // Get just the URL portion and clean it
-                    let mut url_str = substring[0..end_idx].to_string();
-                    
-                    // Remove anything after Address: (case insensitive and with various formats)
-                    for pattern in &["\n\nAddress:", "\nAddress:", "Address:", "\n\naddress:", "\naddress:", "address:"] {
-                        if let Some(idx) = url_str.to_lowercase().find(&pattern.to_lowercase()) {
-                            url_str = url_str[0..idx].trim().to_string();
-                            break;
-                        }
-                    }
-                    
-                    // Remove any newlines from the URL completely
-                    url_str = url_str.replace('\n', "").trim().to_string();
-                    
-                    Some(url_str)
-                } else {
-                    None
-                }
-            } else {
-                None
+            ',' => categories.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    categories.push(current);
+
+    categories
+        .iter()
+        .map(|category| Event::unescape_ical(category).trim().to_string())
+        .filter(|category| !category.is_empty())
+        .collect()
+}
+
+/// Returns the CN display name of an ORGANIZER/ATTENDEE, falling back to its mailto address
+fn person_name(property: &Property) -> Option<String> {
+    if let Some(name) = get_param(property, "CN") {
+        let name = Event::unescape_ical(name.trim_matches('"'));
+        if !name.trim().is_empty() {
+            return Some(name.trim().to_string());
+        }
+    }
+
+    let value = property.value.as_deref()?.trim();
+    let address = match value.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &value[7..],
+        _ => value,
+    };
+
+    (!address.is_empty()).then(|| address.to_string())
+}
+
+/// Parses a GEO value (`latitude;longitude`), rejecting malformed or out-of-range coordinates
+fn parse_geo(value: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = value.split_once(';')?;
+    let lat: f64 = lat.trim().parse().ok()?;
+    let lon: f64 = lon.trim().parse().ok()?;
+
+    let valid = (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon);
+    valid.then_some((lat, lon))
+}
+
+/// Parses an iCal (ISO-8601) duration such as `PT2H30M`, `P1D` or `-PT15M`
+fn parse_ical_duration(duration_str: &str) -> Result<chrono::Duration, CalendarError> {
+    let invalid = || {
+        CalendarError::TimeConversionError(format!("Invalid duration format: {}", duration_str))
+    };
+
+    let trimmed = duration_str.trim();
+    let (negative, rest) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut total = chrono::Duration::zero();
+    let mut in_time = false;
+    let mut number = String::new();
+
+    for c in rest.chars() {
+        match c {
+            'T' if !in_time && number.is_empty() => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                let value = number.parse::<i64>().map_err(|_| invalid())?;
+                number.clear();
+                total += match (unit, in_time) {
+                    ('W', false) => chrono::Duration::weeks(value),
+                    ('D', false) => chrono::Duration::days(value),
+                    ('H', true) => chrono::Duration::hours(value),
+                    ('M', true) => chrono::Duration::minutes(value),
+                    ('S', true) => chrono::Duration::seconds(value),
+                    _ => return Err(invalid()),
+                };
             }
-        };
+        }
+    }
 
-        // Parse start and end times
-        let start = component
-            .properties
-            .iter()
-            .find(|p| p.name == "DTSTART")
-            .and_then(|p| p.value.clone())
-            .ok_or_else(|| {
-                CalendarError::ParseError("Event missing DTSTART property".to_string())
-            })?;
+    if !number.is_empty() {
+        return Err(invalid());
+    }
 
-        let end = component
-            .properties
-            .iter()
-            .find(|p| p.name == "DTEND")
-            .and_then(|p| p.value.clone())
-            .ok_or_else(|| CalendarError::ParseError("Event missing DTEND property".to_string()))?;
+    Ok(if negative { -total } else { total })
+}
 
-        // Parse dates in format: 20220101T120000Z
-        let start_time = parse_ical_datetime(&start)?;
-        let end_time = parse_ical_datetime(&end)?;
+/// Kind of value in a DTSTART or DTEND property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateValue {
+    /// A calendar date (YYYYMMDD), for all-day events
+    Date,
+    /// A date and time (YYYYMMDDTHHMMSS, optionally ending in Z)
+    DateTime,
+}
 
-        // Filter out events that ended more than two days ago
-        if end_time >= two_days_ago {
-            // Create a new event
-            events.push(Event::new(
-                summary,
-                description,
-                location,
-                start_time,
-                end_time,
-                url,
-            ));
+impl DateValue {
+    /// Reads the property's VALUE parameter, if it names a date type
+    fn of(property: &Property) -> Option<Self> {
+        match get_param(property, "VALUE")?.trim().to_ascii_uppercase().as_str() {
+            "DATE" => Some(DateValue::Date),
+            "DATE-TIME" => Some(DateValue::DateTime),
+            _ => None,
         }
     }
 
-    Ok(events)
+    /// Guesses the kind from the number of digits, for values without a VALUE parameter
+    fn infer(dt_str: &str) -> Result<Self, CalendarError> {
+        match dt_str.trim().replace(['Z', 'T'], "").len() {
+            8 => Ok(DateValue::Date),
+            14 => Ok(DateValue::DateTime),
+            digits => Err(CalendarError::TimeConversionError(format!(
+                "Invalid datetime format: {} has {} digits, expected 8 (YYYYMMDD) or 14 (YYYYMMDDTHHMMSS)",
+                dt_str, digits
+            ))),
+        }
+    }
 }
 
-/// Parses an iCal datetime string
-fn parse_ical_datetime(dt_str: &str) -> Result<DateTime<Utc>, CalendarError> {
-    // Handle different date formats
-    let cleaned = dt_str.replace("Z", "").replace("T", "");
+/// Parses an iCal datetime string into UTC
+///
+/// `value` comes from the property's VALUE parameter and decides whether the
+/// string is a date or a date-time; when it is `None` the length decides.
+/// Values ending in `Z` are UTC. Values with a `TZID` parameter are resolved
+/// with chrono-tz. Floating values (no `Z`, no `TZID`) are interpreted in the
+/// system local zone, as are values whose `TZID` is not a known IANA name.
+fn parse_ical_datetime(
+    dt_str: &str,
+    tzid: Option<&str>,
+    value: Option<DateValue>,
+) -> Result<DateTime<Utc>, CalendarError> {
+    let value = match value {
+        Some(value) => value,
+        None => DateValue::infer(dt_str)?,
+    };
+    let is_utc = dt_str.trim().ends_with('Z');
+    let naive = parse_naive_datetime(dt_str, value)?;
 
-    if cleaned.len() != 14 && cleaned.len() != 8 {
-        return Err(CalendarError::TimeConversionError(format!(
-            "Invalid datetime format: {}",
-            dt_str
-        )));
+    if is_utc {
+        return Ok(Utc.from_utc_datetime(&naive));
     }
 
-    let (year, month, day, hour, minute, second) = if cleaned.len() == 14 {
+    let local_result = match tzid.and_then(|id| id.trim_matches('"').parse::<Tz>().ok()) {
+        Some(tz) => tz.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc)),
+        None => Local.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc)),
+    };
+
+    local_result.ok_or_else(|| {
+        CalendarError::TimeConversionError(format!(
+            "Time does not exist in zone {}: {}",
+            tzid.unwrap_or("local"),
+            dt_str
+        ))
+    })
+}
+
+/// Parses the date/time components of an iCal datetime string without any zone
+///
+/// A `Date` is midnight at the start of the day; a time part is ignored.
+fn parse_naive_datetime(dt_str: &str, value: DateValue) -> Result<NaiveDateTime, CalendarError> {
+    // Handle different date formats
+    let cleaned = dt_str.trim().replace(['Z', 'T'], "");
+
+    let (year, month, day, hour, minute, second) = match (value, cleaned.len()) {
         // Format: YYYYMMDDHHMMSS
-        (
+        (DateValue::DateTime, 14) => (
             &cleaned[0..4],
             &cleaned[4..6],
             &cleaned[6..8],
             &cleaned[8..10],
             &cleaned[10..12],
             &cleaned[12..14],
-        )
-    } else {
-        // Format: YYYYMMDD (date only)
-        (&cleaned[0..4], &cleaned[4..6], &cleaned[6..8], "00", "00", "00")
+        ),
+        // Format: YYYYMMDD (date only), tolerating a time from sloppy feeds
+        (DateValue::Date, 8 | 14) => (&cleaned[0..4], &cleaned[4..6], &cleaned[6..8], "00", "00", "00"),
+        (DateValue::DateTime, digits) => {
+            return Err(CalendarError::TimeConversionError(format!(
+                "Invalid DATE-TIME value: {} has {} digits, expected 14 (YYYYMMDDTHHMMSS)",
+                dt_str, digits
+            )))
+        }
+        (DateValue::Date, digits) => {
+            return Err(CalendarError::TimeConversionError(format!(
+                "Invalid DATE value: {} has {} digits, expected 8 (YYYYMMDD)",
+                dt_str, digits
+            )))
+        }
     };
 
     // Parse components
@@ -219,13 +1070,856 @@ fn parse_ical_datetime(dt_str: &str) -> Result<DateTime<Utc>, CalendarError> {
         CalendarError::TimeConversionError(format!("Invalid second: {} - {}", second, e))
     })?;
 
-    // Create DateTime in UTC
-    Utc.with_ymd_and_hms(year, month, day, hour, minute, second)
-        .single()
+    NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_opt(hour, minute, second))
         .ok_or_else(|| {
             CalendarError::TimeConversionError(format!(
                 "Invalid date/time combination: {}-{}-{} {}:{}:{}",
                 year, month, day, hour, minute, second
             ))
         })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Wraps the given lines in a VCALENDAR, with CRLF line endings
+    fn ics(lines: &[&str]) -> String {
+        let mut calendar = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//lumabot//test//EN\r\n");
+        for line in lines {
+            calendar.push_str(line);
+            calendar.push_str("\r\n");
+        }
+        calendar.push_str("END:VCALENDAR\r\n");
+        calendar
+    }
+
+    fn parse(calendar: &str) -> Vec<Event> {
+        parse_calendar_from_reader(calendar.as_bytes(), ParseOptions::default()).unwrap()
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn tzid_times_are_read_in_their_zone() {
+        let events = parse(&ics(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Meetup",
+            "DTSTART;TZID=America/New_York:20300115T180000",
+            "DTEND;TZID=America/New_York:20300115T200000",
+            "END:VEVENT",
+        ]));
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].start, utc("2030-01-15T23:00:00Z"));
+        assert_eq!(events[0].end, utc("2030-01-16T01:00:00Z"));
+    }
+
+    #[test]
+    fn utc_times_are_kept() {
+        let events = parse(&ics(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Meetup",
+            "DTSTART:20300115T180000Z",
+            "DTEND:20300115T200000Z",
+            "END:VEVENT",
+        ]));
+
+        assert_eq!(events[0].start, utc("2030-01-15T18:00:00Z"));
+        assert_eq!(events[0].end, utc("2030-01-15T20:00:00Z"));
+    }
+
+    #[test]
+    fn floating_times_are_read_in_the_local_zone() {
+        let events = parse(&ics(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Meetup",
+            "DTSTART:20300115T180000",
+            "DTEND:20300115T200000",
+            "END:VEVENT",
+        ]));
+
+        let local = |hour| {
+            let naive = NaiveDate::from_ymd_opt(2030, 1, 15).unwrap().and_hms_opt(hour, 0, 0).unwrap();
+            Local.from_local_datetime(&naive).earliest().unwrap().with_timezone(&Utc)
+        };
+        assert_eq!(events[0].start, local(18));
+        assert_eq!(events[0].end, local(20));
+    }
+
+    #[test]
+    fn unknown_tzid_falls_back_to_the_local_zone() {
+        let with_unknown = parse_ical_datetime("20300115T180000", Some("Not/A_Zone"), None).unwrap();
+        let floating = parse_ical_datetime("20300115T180000", None, None).unwrap();
+
+        assert_eq!(with_unknown, floating);
+    }
+
+    #[test]
+    fn date_only_events_are_all_day() {
+        let events = parse(&ics(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Conference",
+            "DTSTART;VALUE=DATE:20300115",
+            "DTEND;VALUE=DATE:20300117",
+            "END:VEVENT",
+        ]));
+
+        let midnight = |day| {
+            let naive = NaiveDate::from_ymd_opt(2030, 1, day).unwrap().and_hms_opt(0, 0, 0).unwrap();
+            Local.from_local_datetime(&naive).earliest().unwrap().with_timezone(&Utc)
+        };
+        assert!(events[0].all_day);
+        assert_eq!(events[0].start, midnight(15));
+        assert_eq!(events[0].end, midnight(17));
+    }
+
+    /// A timed event starting 2030-01-15 18:00 UTC with the given extra properties
+    fn timed_event(properties: &[&str]) -> String {
+        let mut lines = vec!["BEGIN:VEVENT", "SUMMARY:Meetup", "DTSTART:20300115T180000Z"];
+        lines.extend_from_slice(properties);
+        lines.push("END:VEVENT");
+        ics(&lines)
+    }
+
+    #[test]
+    fn duration_gives_the_end_when_dtend_is_absent() {
+        let start = utc("2030-01-15T18:00:00Z");
+
+        let hour = parse(&timed_event(&["DURATION:PT1H"]));
+        assert_eq!(hour[0].end, start + chrono::Duration::hours(1));
+
+        let day = parse(&timed_event(&["DURATION:P1D"]));
+        assert_eq!(day[0].end, start + chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn events_without_dtend_or_duration_are_zero_length() {
+        let options = ParseOptions { default_duration_minutes: 0, ..ParseOptions::default() };
+        let events = parse_calendar_from_reader(timed_event(&[]).as_bytes(), options).unwrap();
+
+        assert_eq!(events[0].end, events[0].start);
+    }
+
+    #[test]
+    fn folded_lines_are_unfolded() {
+        let events = parse(&timed_event(&[
+            "DESCRIPTION:Join us for an evening of talks about Rust follo",
+            " wed by drinks at the venue next",
+            "\tdoor.",
+        ]));
+
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Join us for an evening of talks about Rust followed by drinks at the venue nextdoor.")
+        );
+    }
+
+    #[test]
+    fn cancelled_events_are_dropped_unless_included() {
+        let calendar = ics(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Called off",
+            "STATUS:CANCELLED",
+            "DTSTART:20300115T180000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "SUMMARY:Still on",
+            "STATUS:CONFIRMED",
+            "DTSTART:20300116T180000Z",
+            "END:VEVENT",
+        ]);
+
+        let events = parse(&calendar);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Still on");
+
+        let options = ParseOptions { include_cancelled: true, ..ParseOptions::default() };
+        let events = parse_calendar_from_reader(calendar.as_bytes(), options).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].status, EventStatus::Cancelled);
+    }
+
+    #[test]
+    fn calendar_files_are_parsed_from_disk() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/meetups.ics");
+        let events = parse_calendar_file(&path, ParseOptions::default()).unwrap();
+
+        let summaries: Vec<&str> = events.iter().map(|event| event.summary.as_str()).collect();
+        assert_eq!(summaries, ["Rust Meetup", "Embedded Workshop"]);
+        assert_eq!(events[0].start, utc("2030-01-15T18:00:00Z"));
+        assert_eq!(events[0].location.as_deref(), Some("Community Hall"));
+        assert_eq!(events[1].description.as_deref(), Some("Bring a microcontroller"));
+    }
+
+    #[test]
+    fn invitation_files_carry_their_method() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/invitation.ics");
+        let events = parse_calendar_file(&path, ParseOptions::default()).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].method, CalendarMethod::Request);
+        assert!(events[0].is_invitation());
+        assert_eq!(events[0].organizer.as_deref(), Some("Launch Team"));
+    }
+
+    #[test]
+    fn subscription_files_are_published_calendars() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/meetups.ics");
+        let events = parse_calendar_file(&path, ParseOptions::default()).unwrap();
+
+        assert!(events.iter().all(|event| event.method == CalendarMethod::Publish));
+        assert!(!events[0].is_invitation());
+    }
+
+    #[test]
+    fn missing_calendar_files_are_an_error() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/missing.ics");
+        assert!(parse_calendar_file(&path, ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn unmodified_calendars_are_served_from_the_cache() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(MockServer::start());
+        let calendar = ics(&[
+            "BEGIN:VEVENT",
+            "UID:meetup@lu.ma",
+            "SUMMARY:Meetup",
+            "DTSTART:20300115T180000Z",
+            "END:VEVENT",
+        ]);
+        runtime.block_on(async {
+            Mock::given(method("GET"))
+                .and(header("If-None-Match", "\"v1\""))
+                .respond_with(ResponseTemplate::new(304))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .insert_header("ETag", "\"v1\"")
+                        .set_body_raw(calendar, "text/calendar"),
+                )
+                .up_to_n_times(1)
+                .expect(1)
+                .mount(&server)
+                .await;
+        });
+
+        let cache_dir = std::env::temp_dir().join(format!("lumabot-cache-{}", uuid::Uuid::new_v4()));
+        let fetch = FetchOptions { use_cache: true, cache_dir: Some(cache_dir.clone()), ..FetchOptions::default() };
+        let url = format!("{}/calendar.ics", server.uri());
+        let first = fetch_and_parse_calendar(&url, ParseOptions::default(), &fetch).unwrap();
+        let second = fetch_and_parse_calendar(&url, ParseOptions::default(), &fetch).unwrap();
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(second, first);
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn cached_calendars_are_not_shared_between_tokens() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(MockServer::start());
+        let calendar = ics(&["BEGIN:VEVENT", "SUMMARY:Meetup", "DTSTART:20300115T180000Z", "END:VEVENT"]);
+        runtime.block_on(async {
+            Mock::given(method("GET"))
+                .and(header("If-None-Match", "\"v1\""))
+                .respond_with(ResponseTemplate::new(304))
+                .expect(0)
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .insert_header("ETag", "\"v1\"")
+                        .set_body_raw(calendar, "text/calendar"),
+                )
+                .expect(2)
+                .mount(&server)
+                .await;
+        });
+
+        let cache_dir = std::env::temp_dir().join(format!("lumabot-cache-{}", uuid::Uuid::new_v4()));
+        let url = format!("{}/calendar.ics", server.uri());
+        for token in ["alice-token", "bob-token"] {
+            let fetch = FetchOptions {
+                use_cache: true,
+                cache_dir: Some(cache_dir.clone()),
+                auth_token: Some(token.to_string()),
+                ..FetchOptions::default()
+            };
+            fetch_and_parse_calendar(&url, ParseOptions::default(), &fetch).unwrap();
+        }
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn organizer_and_attendees_are_read_from_their_names() {
+        let events = parse(&ics(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Meetup",
+            "DTSTART:20300115T180000Z",
+            "ORGANIZER;CN=\"Rust Club\":mailto:hosts@example.com",
+            "ATTENDEE;CN=Ada Lovelace;ROLE=REQ-PARTICIPANT:mailto:ada@example.com",
+            "ATTENDEE:mailto:grace@example.com",
+            "END:VEVENT",
+        ]));
+
+        assert_eq!(events[0].organizer.as_deref(), Some("Rust Club"));
+        assert_eq!(events[0].attendees, ["Ada Lovelace", "grace@example.com"]);
+    }
+
+    #[test]
+    fn geo_is_read_as_latitude_and_longitude() {
+        let events = parse(&timed_event(&["GEO:37.7749;-122.4194"]));
+        assert_eq!(events[0].geo, Some((37.7749, -122.4194)));
+    }
+
+    #[test]
+    fn malformed_geo_is_ignored() {
+        for geo in ["GEO:somewhere", "GEO:37.7749", "GEO:137.0;10.0", "GEO:10.0;200.0"] {
+            let events = parse(&timed_event(&[geo]));
+            assert_eq!(events.len(), 1, "{}", geo);
+            assert_eq!(events[0].geo, None, "{}", geo);
+        }
+    }
+
+    /// Streams a calendar of `count` events, each with a folded description,
+    /// producing each event only when the reader gets to it
+    struct GeneratedCalendar {
+        count: usize,
+        produced: usize,
+        chunk: Vec<u8>,
+        pos: usize,
+    }
+
+    impl GeneratedCalendar {
+        fn new(count: usize) -> Self {
+            let header = b"BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//lumabot//test//EN\r\n".to_vec();
+            Self { count, produced: 0, chunk: header, pos: 0 }
+        }
+
+        fn next_chunk(&mut self) -> Vec<u8> {
+            self.produced += 1;
+            match self.produced {
+                n if n <= self.count => format!(
+                    "BEGIN:VEVENT\r\nUID:event-{n}@lu.ma\r\nSUMMARY:Event {n}\r\n\
+                     DTSTART:20300115T180000Z\r\nDTEND:20300115T200000Z\r\n\
+                     DESCRIPTION:{line}\r\n {line}\r\n {line}\r\nEND:VEVENT\r\n",
+                    line = "x".repeat(72),
+                )
+                .into_bytes(),
+                n if n == self.count + 1 => b"END:VCALENDAR\r\n".to_vec(),
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    impl Read for GeneratedCalendar {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pos == self.chunk.len() {
+                self.chunk = self.next_chunk();
+                self.pos = 0;
+            }
+            let read = (self.chunk.len() - self.pos).min(buf.len());
+            buf[..read].copy_from_slice(&self.chunk[self.pos..self.pos + read]);
+            self.pos += read;
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn multi_megabyte_calendars_are_unfolded_a_line_at_a_time() {
+        // About 5 MB of calendar
+        let mut reader = UnfoldingReader::new(BufReader::new(GeneratedCalendar::new(15_000)));
+
+        let mut line = String::new();
+        let mut total = 0;
+        let mut largest_buffer = 0;
+        while reader.read_line(&mut line).unwrap() > 0 {
+            total += line.len();
+            largest_buffer = largest_buffer.max(reader.line.capacity() + reader.next.capacity());
+            line.clear();
+        }
+
+        assert!(total > 5_000_000, "{}", total);
+        assert!(largest_buffer < 1024, "{}", largest_buffer);
+    }
+
+    #[test]
+    fn multi_megabyte_calendars_parse_completely() {
+        let reader = BufReader::new(GeneratedCalendar::new(15_000));
+        let events = parse_calendar_from_reader(reader, ParseOptions::default()).unwrap();
+
+        assert_eq!(events.len(), 15_000);
+        assert_eq!(events[0].description.as_deref().map(str::len), Some(3 * 72));
+    }
+
+    #[test]
+    fn the_luma_link_in_a_description_is_preferred_over_earlier_links() {
+        let events = parse(&timed_event(&[
+            "DESCRIPTION:Directions: https://maps.google.com/?q=hall\\nRegister at https://lu.ma/rust-meetup.\\n\\nAddress: 1 Main St",
+        ]));
+
+        assert_eq!(events[0].url.as_deref(), Some("https://lu.ma/rust-meetup"));
+        assert_eq!(events[0].links, ["https://maps.google.com/?q=hall"]);
+    }
+
+    #[test]
+    fn the_first_description_link_is_used_without_a_luma_link() {
+        let events = parse(&timed_event(&[
+            "DESCRIPTION:Join at https://meet.example.com/abc or see https://example.com/info",
+        ]));
+
+        assert_eq!(events[0].url.as_deref(), Some("https://meet.example.com/abc"));
+        assert_eq!(events[0].links, ["https://example.com/info"]);
+    }
+
+    #[test]
+    fn an_explicit_url_keeps_every_description_link() {
+        let events = parse(&timed_event(&[
+            "URL:https://lu.ma/rust-meetup",
+            "DESCRIPTION:Directions: https://maps.google.com/?q=hall",
+        ]));
+
+        assert_eq!(events[0].url.as_deref(), Some("https://lu.ma/rust-meetup"));
+        assert_eq!(events[0].links, ["https://maps.google.com/?q=hall"]);
+    }
+
+    #[test]
+    fn extract_links_stops_at_an_address_label() {
+        assert_eq!(
+            extract_links("See https://lu.ma/rust-meetupAddress: 1 Main St"),
+            ["https://lu.ma/rust-meetup"]
+        );
+    }
+
+    /// Starts a mock server answering every request with `response`
+    ///
+    /// The runtime keeps the server alive and must outlive the test's fetches,
+    /// which use the blocking client and so run outside it.
+    fn serve(response: ResponseTemplate) -> (tokio::runtime::Runtime, MockServer) {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET")).respond_with(response).mount(&server).await;
+            server
+        });
+        (runtime, server)
+    }
+
+    #[test]
+    fn http_errors_keep_the_status_and_body() {
+        let (_runtime, server) = serve(ResponseTemplate::new(403).set_body_string("This calendar is private"));
+
+        let error = fetch_and_parse_calendar(&server.uri(), ParseOptions::default(), &FetchOptions::default())
+            .unwrap_err();
+
+        match &error {
+            CalendarError::HttpStatus { status, body } => {
+                assert_eq!(*status, 403);
+                assert_eq!(body, "This calendar is private");
+            }
+            other => panic!("expected an HTTP status error, got {:?}", other),
+        }
+        assert_eq!(error.to_string(), "Failed to fetch calendar: HTTP 403 - This calendar is private");
+    }
+
+    #[test]
+    fn requests_go_through_the_configured_proxy_with_custom_headers() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let proxy = runtime.block_on(async {
+            let proxy = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(header("X-Team", "platform"))
+                .respond_with(ResponseTemplate::new(200).set_body_string("proxied"))
+                .expect(1)
+                .mount(&proxy)
+                .await;
+            proxy
+        });
+        let vars = |name: &str| (name == "HTTP_PROXY").then(|| proxy.uri());
+
+        let client = build_client(proxies_from(vars).unwrap()).unwrap();
+        let headers = request_headers(&[("X-Team".to_string(), "platform".to_string())]).unwrap();
+        let response = client.get("http://calendar.invalid/feed.ics").headers(headers).send().unwrap();
+
+        assert_eq!(response.text().unwrap(), "proxied");
+        runtime.block_on(proxy.verify());
+    }
+
+    #[test]
+    fn no_proxy_hosts_bypass_the_proxy() {
+        let (_runtime, server) = serve(ResponseTemplate::new(200).set_body_string("direct"));
+        let vars = |name: &str| match name {
+            "HTTP_PROXY" => Some("http://proxy.invalid:3128".to_string()),
+            "NO_PROXY" => Some("127.0.0.1,localhost".to_string()),
+            _ => None,
+        };
+
+        let client = build_client(proxies_from(vars).unwrap()).unwrap();
+        let response = client.get(server.uri()).send().unwrap();
+
+        assert_eq!(response.text().unwrap(), "direct");
+    }
+
+    #[test]
+    fn invalid_proxies_and_headers_are_config_errors() {
+        let vars = |name: &str| (name == "HTTPS_PROXY").then(|| "http://[bad".to_string());
+        assert!(matches!(proxies_from(vars), Err(CalendarError::EnvError(_))));
+
+        let headers = [("Bad Header".to_string(), "value".to_string())];
+        assert!(matches!(request_headers(&headers), Err(CalendarError::EnvError(_))));
+    }
+
+    #[test]
+    fn non_http_schemes_are_rejected_before_fetching() {
+        for url in ["file:///etc/passwd", "webcal://lu.ma/ics/get", "ftp://example.com/cal.ics"] {
+            let error = fetch_and_parse_calendar(url, ParseOptions::default(), &FetchOptions::default())
+                .unwrap_err();
+            assert!(matches!(error, CalendarError::InvalidUrl(_)), "{}: {:?}", url, error);
+        }
+    }
+
+    #[test]
+    fn urls_without_a_scheme_are_fetched_over_https() {
+        let url = normalize_calendar_url("api.lu.ma/ics/get?entity=calendar&id=cal-1").unwrap();
+        assert_eq!(url.as_str(), "https://api.lu.ma/ics/get?entity=calendar&id=cal-1");
+
+        let url = normalize_calendar_url("localhost:8080/calendar.ics").unwrap();
+        assert_eq!(url.as_str(), "https://localhost:8080/calendar.ics");
+    }
+
+    #[test]
+    fn html_pages_are_not_parsed_as_calendars() {
+        let page = "<!doctype html><html><body>Rust Club</body></html>";
+        let (_runtime, server) = serve(ResponseTemplate::new(200).set_body_raw(page, "text/html; charset=utf-8"));
+
+        let error = fetch_and_parse_calendar(&server.uri(), ParseOptions::default(), &FetchOptions::default())
+            .unwrap_err();
+
+        assert!(matches!(error, CalendarError::NotCalendar(_)), "{:?}", error);
+        assert!(error.to_string().contains("doesn't look like an ICS feed"));
+    }
+
+    #[test]
+    fn events_ending_before_they_start_are_clamped() {
+        let calendar = timed_event(&["DTEND:20300115T170000Z"]);
+        let start = utc("2030-01-15T18:00:00Z");
+
+        let options = ParseOptions { default_duration_minutes: 0, ..ParseOptions::default() };
+        let events = parse_calendar_from_reader(calendar.as_bytes(), options).unwrap();
+        assert_eq!((events[0].start, events[0].end), (start, start));
+
+        // Clamped to zero-length, the event then gets the default length like any other
+        let events = parse(&calendar);
+        assert_eq!(events[0].end, start + chrono::Duration::minutes(DEFAULT_EVENT_DURATION_MINUTES as i64));
+        assert_eq!(events[0].original_end, Some(start));
+    }
+
+    /// Fetches an empty calendar from a server that only answers requests
+    /// carrying `user_agent`
+    fn fetch_expecting_user_agent(user_agent: &str, fetch: &FetchOptions) {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(header("User-Agent", user_agent))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(ics(&[]), "text/calendar"))
+                .expect(1)
+                .mount(&server)
+                .await;
+            server
+        });
+
+        fetch_and_parse_calendar(&server.uri(), ParseOptions::default(), fetch).unwrap();
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn the_configured_user_agent_is_sent() {
+        let fetch = FetchOptions { user_agent: Some("my-deployment/2.0".to_string()), ..FetchOptions::default() };
+        fetch_expecting_user_agent("my-deployment/2.0", &fetch);
+    }
+
+    #[test]
+    fn the_default_user_agent_carries_the_crate_version() {
+        assert!(DEFAULT_USER_AGENT.ends_with(env!("CARGO_PKG_VERSION")));
+        fetch_expecting_user_agent(DEFAULT_USER_AGENT, &FetchOptions::default());
+    }
+
+    #[test]
+    fn categories_are_split_and_trimmed() {
+        let events = parse(&timed_event(&["CATEGORIES:Tech, Social", "CATEGORIES:Food\\,Drink"]));
+        assert_eq!(events[0].categories, ["Tech", "Social", "Food,Drink"]);
+    }
+
+    /// Two valid events around one whose start can't be parsed
+    fn calendar_with_a_malformed_event() -> String {
+        ics(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:First",
+            "DTSTART:20300115T180000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "SUMMARY:Broken",
+            "DTSTART:not-a-date",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "SUMMARY:Second",
+            "DTSTART:20300116T180000Z",
+            "END:VEVENT",
+        ])
+    }
+
+    #[test]
+    fn malformed_events_are_skipped() {
+        let events = parse(&calendar_with_a_malformed_event());
+
+        let summaries: Vec<&str> = events.iter().map(|event| event.summary.as_str()).collect();
+        assert_eq!(summaries, ["First", "Second"]);
+    }
+
+    #[test]
+    fn strict_parsing_fails_on_a_malformed_event() {
+        let options = ParseOptions { strict: true, ..ParseOptions::default() };
+        let result = parse_calendar_from_reader(calendar_with_a_malformed_event().as_bytes(), options);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn timed_out_fetches_are_retried() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(ics(&[]), "text/calendar"))
+                .expect(1)
+                .mount(&server)
+                .await;
+            server
+        });
+        // The abandoned first request is never recorded, so only the retry is verified
+        let fetch = FetchOptions {
+            timeout: Duration::from_millis(200),
+            retries: 1,
+            ..FetchOptions::default()
+        };
+
+        let events = fetch_and_parse_calendar(&server.uri(), ParseOptions::default(), &fetch).unwrap();
+
+        assert!(events.is_empty());
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn http_error_statuses_are_not_retried() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(503))
+                .expect(1)
+                .mount(&server)
+                .await;
+            server
+        });
+
+        let error = fetch_and_parse_calendar(&server.uri(), ParseOptions::default(), &FetchOptions::default())
+            .unwrap_err();
+
+        assert!(matches!(error, CalendarError::HttpStatus { status: 503, .. }), "{:?}", error);
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn value_date_parameters_decide_the_kind() {
+        let events = parse(&ics(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Meetup",
+            "DTSTART;VALUE=DATE-TIME:20300115T180000Z",
+            "DTEND;VALUE=DATE-TIME:20300115T200000Z",
+            "END:VEVENT",
+        ]));
+        assert!(!events[0].all_day);
+        assert_eq!(events[0].start, utc("2030-01-15T18:00:00Z"));
+        assert_eq!(events[0].end, utc("2030-01-15T20:00:00Z"));
+
+        // A sloppy feed's time part is dropped from an explicit DATE
+        let date = parse_ical_datetime("20300115T180000", None, Some(DateValue::Date)).unwrap();
+        let midnight = NaiveDate::from_ymd_opt(2030, 1, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(date, Local.from_local_datetime(&midnight).earliest().unwrap().with_timezone(&Utc));
+
+        // An explicit DATE-TIME needs a time part
+        assert!(parse_ical_datetime("20300115", None, Some(DateValue::DateTime)).is_err());
+    }
+
+    #[test]
+    fn datetimes_of_unexpected_length_are_rejected() {
+        for value in [None, Some(DateValue::DateTime), Some(DateValue::Date)] {
+            let error = parse_ical_datetime("20300115T1800", None, value).unwrap_err();
+            assert!(error.to_string().contains("12 digits"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn feed_uids_identify_events() {
+        let revision = |summary: &str, start: &str| {
+            parse(&ics(&[
+                "BEGIN:VEVENT",
+                "UID:rust-meetup@lu.ma",
+                &format!("SUMMARY:{}", summary),
+                &format!("DTSTART:{}", start),
+                "END:VEVENT",
+            ]))
+            .remove(0)
+        };
+
+        let original = revision("Rust Meetup", "20300115T180000Z");
+        let renamed_and_moved = revision("Rust Meetup (moved!)", "20300116T180000Z");
+        assert_eq!(original.event_uid, "rust-meetup@lu.ma");
+        assert_eq!(original.ical_uid.as_deref(), Some("rust-meetup@lu.ma"));
+        assert_eq!(renamed_and_moved.event_uid, original.event_uid);
+
+        let without_uid = parse(&timed_event(&[])).remove(0);
+        assert_eq!(without_uid.ical_uid, None);
+        assert_eq!(without_uid.event_uid, without_uid.content_uid());
+    }
+
+    #[test]
+    fn legacy_content_uids_ignore_the_default_url() {
+        let events = parse(&ics(&[
+            "BEGIN:VEVENT",
+            "UID:office-hours@lu.ma",
+            "SUMMARY:Office Hours",
+            "DTSTART:20300115T180000Z",
+            "DTEND:20300115T190000Z",
+            "END:VEVENT",
+        ]));
+        let parsed_content_uid = events[0].content_uid();
+
+        // Saving fills in a default URL, which must not change the old ID to adopt
+        let saved = crate::sync::with_clean_urls(&events);
+        assert_ne!(saved[0].content_uid(), parsed_content_uid);
+        assert_eq!(saved[0].legacy_uids()[0], parsed_content_uid);
+    }
+
+    #[test]
+    fn recurrence_instances_keep_apart_from_their_series() {
+        let events = parse(&ics(&[
+            "BEGIN:VEVENT",
+            "UID:office-hours@lu.ma",
+            "SUMMARY:Office Hours",
+            "DTSTART:20300115T180000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:office-hours@lu.ma",
+            "RECURRENCE-ID:20300122T180000Z",
+            "SUMMARY:Office Hours (rescheduled)",
+            "DTSTART:20300123T180000Z",
+            "END:VEVENT",
+        ]));
+
+        assert_eq!(events[0].event_uid, "office-hours@lu.ma");
+        assert_eq!(events[1].event_uid, "office-hours@lu.ma#20300122T180000Z");
+        assert_eq!(events[1].ical_uid.as_deref(), Some("office-hours@lu.ma"));
+    }
+
+    #[test]
+    fn calendars_with_bare_cr_line_endings_parse() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mac_line_endings.ics");
+        let events = parse_calendar_file(&path, ParseOptions::default()).unwrap();
+
+        let summaries: Vec<&str> = events.iter().map(|event| event.summary.as_str()).collect();
+        assert_eq!(
+            summaries,
+            [
+                "Rust Meetup",
+                "Embedded Workshop for Microcontroller Enthusiasts and Curious Beginners",
+                "Hack Night"
+            ]
+        );
+        assert_eq!(events[2].end, utc("2030-01-29T22:00:00Z"));
+    }
+
+    #[test]
+    fn mixed_line_endings_parse_one_byte_at_a_time() {
+        let calendar = "BEGIN:VCALENDAR\r\nVERSION:2.0\n\
+            BEGIN:VEVENT\rSUMMARY:Rust\r\n  Meetup\rDTSTART:20300115T180000Z\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\nSUMMARY:Hack Night\r\nDTSTART:20300129T180000Z\rEND:VEVENT\n\
+            END:VCALENDAR";
+
+        // A one-byte buffer makes every line ending straddle a refill
+        let reader = io::BufReader::with_capacity(1, calendar.as_bytes());
+        let events = parse_calendar_from_reader(reader, ParseOptions::default()).unwrap();
+
+        let summaries: Vec<&str> = events.iter().map(|event| event.summary.as_str()).collect();
+        assert_eq!(summaries, ["Rust Meetup", "Hack Night"]);
+    }
+
+    #[test]
+    fn feed_tokens_are_sent_as_bearer_auth() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(header("Authorization", "Bearer s3cret"))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(ics(&[]), "text/calendar"))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(401))
+                .expect(1)
+                .mount(&server)
+                .await;
+            server
+        });
+
+        let fetch = FetchOptions { auth_token: Some("s3cret".to_string()), ..FetchOptions::default() };
+        fetch_and_parse_calendar(&server.uri(), ParseOptions::default(), &fetch).unwrap();
+
+        let error = fetch_and_parse_calendar(&server.uri(), ParseOptions::default(), &FetchOptions::default())
+            .unwrap_err();
+        assert!(matches!(error, CalendarError::HttpStatus { status: 401, .. }), "{:?}", error);
+
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn zero_length_events_get_the_default_duration() {
+        let start = utc("2030-01-15T18:00:00Z");
+        let calendar = timed_event(&["DTEND:20300115T180000Z"]);
+
+        let options = ParseOptions { default_duration_minutes: 90, ..ParseOptions::default() };
+        let events = parse_calendar_from_reader(calendar.as_bytes(), options).unwrap();
+        assert_eq!(events[0].end, start + chrono::Duration::minutes(90));
+        assert_eq!(events[0].original_end, Some(start));
+
+        // Events with a real length and all-day events are left alone
+        let events = parse_calendar_from_reader(timed_event(&["DURATION:PT30M"]).as_bytes(), options).unwrap();
+        assert_eq!(events[0].end, start + chrono::Duration::minutes(30));
+        assert_eq!(events[0].original_end, None);
+
+        let all_day = ics(&["BEGIN:VEVENT", "SUMMARY:Holiday", "DTSTART;VALUE=DATE:20300115", "END:VEVENT"]);
+        let events = parse_calendar_from_reader(all_day.as_bytes(), options).unwrap();
+        assert_eq!(events[0].end, events[0].start);
+    }
+}
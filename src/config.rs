@@ -0,0 +1,55 @@
+use crate::errors::CalendarError;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Defaults read from the TOML config file; command-line flags take precedence
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Calendar URL to fetch
+    pub url: Option<String>,
+    /// Number of events to display
+    pub limit: Option<usize>,
+    /// IANA zone for displayed times
+    pub timezone: Option<String>,
+    /// Delay between Luma API requests in milliseconds
+    pub rate_limit_ms: Option<u64>,
+    /// Days after an event ends before it is dropped from listings
+    pub retention_days: Option<u32>,
+}
+
+/// Returns `~/.config/lumabot/config.toml`
+pub fn default_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config").join("lumabot").join("config.toml"))
+}
+
+/// Loads the config file at `path`, or at the default location when `path` is `None`
+///
+/// A missing default file yields an empty config; a missing explicit file is an error.
+pub fn load(path: Option<&Path>) -> Result<Config, CalendarError> {
+    let (path, explicit) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !explicit => return Ok(Config::default()),
+        Err(e) => {
+            return Err(CalendarError::EnvError(format!(
+                "Failed to read config file {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+
+    toml::from_str(&contents).map_err(|e| {
+        CalendarError::EnvError(format!("Invalid config file {}: {}", path.display(), e))
+    })
+}
@@ -0,0 +1,260 @@
+mod postgres;
+mod sqlite;
+
+pub use postgres::PostgresDatabase;
+pub use sqlite::SqliteDatabase;
+
+use crate::errors::{CalendarError, DatabaseError};
+use crate::models::Event;
+use chrono::{DateTime, Utc};
+use std::env;
+use std::fmt;
+use tokio::runtime::Handle;
+
+/// Environment variable selecting the storage backend (`sqlite` or `sqlite://path`)
+const DB_BACKEND_ENV: &str = "LUMABOT_DB";
+
+/// Metadata key holding the RFC 3339 time of the last sync from the calendar
+const LAST_SYNC_KEY: &str = "last_sync";
+
+/// Metadata key marking which schema version `init_schema` last brought the database to
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Current schema version; bump it whenever `init_schema` gains a table,
+/// column or index so existing databases are migrated on their next connect
+const SCHEMA_VERSION: &str = "3";
+
+/// When `connect_db` creates or migrates the schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaInit {
+    /// Only when the schema marker is missing or older than this build, which
+    /// costs a single query on an up-to-date database
+    IfNeeded,
+    /// Every time, as `db-init` does
+    Always,
+    /// Never, leaving the schema to `db-init`
+    Never,
+}
+
+/// SQLite file used when `LUMABOT_DB=sqlite` is set without an explicit path
+const DEFAULT_SQLITE_PATH: &str = "lumabot.db";
+
+/// Storage operations shared by every database backend
+pub trait EventStore {
+    /// Creates the events table, adding columns and indexes missing from older schemas
+    fn init_schema(&self) -> Result<(), DatabaseError>;
+
+    /// Saves an event to the database
+    fn save_event(&self, event: &Event) -> Result<(), DatabaseError>;
+
+    /// Saves a list of events atomically, returning how many were inserted or changed
+    fn save_events(&self, events: &[Event]) -> Result<SaveSummary, DatabaseError>;
+
+    /// Retrieves all events from the database that ended within the retention window
+    fn get_all_events(&self) -> Result<Vec<Event>, DatabaseError>;
+
+    /// Retrieves every stored event, including those past the retention window
+    fn get_all_events_unfiltered(&self) -> Result<Vec<Event>, DatabaseError>;
+
+    /// Retrieves one page of events (ordered by start time) that ended within the
+    /// retention window, skipping `offset` events; a `limit` of 0 means no limit
+    fn get_events_page(&self, limit: i64, offset: i64) -> Result<Vec<Event>, DatabaseError>;
+
+    /// Retrieves events first stored at or after `since` that ended within the retention window
+    fn get_events_added_since(&self, since: DateTime<Utc>) -> Result<Vec<Event>, DatabaseError>;
+
+    /// Retrieves events in a date range, excluding events that ended before the retention window
+    #[allow(dead_code)]
+    fn get_events_in_range(
+        &self,
+        start_date: &DateTime<Utc>,
+        end_date: &DateTime<Utc>,
+    ) -> Result<Vec<Event>, DatabaseError>;
+
+    /// Retrieves events with no resolved api_id that ended within the retention window
+    fn get_events_missing_api_id(&self) -> Result<Vec<Event>, DatabaseError>;
+
+    /// Gets the count of events in the database that ended within the retention window
+    fn get_event_count(&self) -> Result<i64, DatabaseError>;
+
+    /// Clears all events from the database
+    fn clear_all_events(&self) -> Result<u64, DatabaseError>;
+
+    /// Deletes events that ended before `before`, returning the number of rows removed
+    fn clear_past_events(&self, before: DateTime<Utc>) -> Result<u64, DatabaseError>;
+
+    /// Deletes the event with the given UID, returning the number of rows removed
+    fn delete_event(&self, event_uid: &str) -> Result<u64, DatabaseError>;
+    /// Reads a value from the metadata table, `None` when the key was never set
+    fn get_metadata(&self, key: &str) -> Result<Option<String>, DatabaseError>;
+
+    /// Writes a value to the metadata table, replacing any previous value
+    fn set_metadata(&self, key: &str, value: &str) -> Result<(), DatabaseError>;
+}
+
+/// What saving one event did to the stored copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SaveOutcome {
+    /// No row existed for the event UID
+    Inserted,
+    /// The stored row was older and has been overwritten
+    Updated,
+    /// The stored row was at least as recent and was kept (apart from a new api_id)
+    Unchanged,
+}
+
+impl SaveOutcome {
+    /// Decides how to store an event given the SEQUENCE of the stored row
+    ///
+    /// `stored` is `None` when there is no row yet. Rows without a sequence
+    /// (saved before it was tracked, or from feeds that omit it) are always
+    /// refreshed; otherwise only a strictly higher incoming sequence wins.
+    fn for_sequences(stored: Option<Option<i32>>, incoming: Option<i32>) -> Self {
+        match (stored, incoming) {
+            (None, _) => SaveOutcome::Inserted,
+            (Some(None), _) => SaveOutcome::Updated,
+            (Some(Some(stored)), Some(incoming)) if incoming > stored => SaveOutcome::Updated,
+            (Some(Some(_)), _) => SaveOutcome::Unchanged,
+        }
+    }
+}
+
+/// Counts of what a batch save did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaveSummary {
+    /// Events stored for the first time
+    pub inserted: usize,
+    /// Stored events overwritten by a newer revision
+    pub updated: usize,
+    /// Stored events left as they were
+    pub unchanged: usize,
+}
+
+impl fmt::Display for SaveSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "inserted {} new, updated {} changed events ({} unchanged)",
+            self.inserted, self.updated, self.unchanged
+        )
+    }
+}
+
+impl SaveSummary {
+    fn record(&mut self, outcome: SaveOutcome) {
+        match outcome {
+            SaveOutcome::Inserted => self.inserted += 1,
+            SaveOutcome::Updated => self.updated += 1,
+            SaveOutcome::Unchanged => self.unchanged += 1,
+        }
+    }
+}
+
+/// A connected database, whichever backend was selected
+pub type Database = Box<dyn EventStore>;
+
+/// Helper function to connect to the database
+///
+/// Uses SQLite when `LUMABOT_DB` is `sqlite` or a `sqlite://path` URL, and
+/// PostgreSQL otherwise. `insecure_tls` disables PostgreSQL certificate verification.
+/// Reads skip events that ended more than `retention_days` ago. `schema` decides
+/// whether the tables are created or migrated first; by default that only
+/// happens when the stored schema version is missing or out of date.
+pub fn connect_db(
+    rt: &Handle,
+    insecure_tls: bool,
+    retention_days: u32,
+    schema: SchemaInit,
+) -> Result<Database, CalendarError> {
+    let backend = env::var(DB_BACKEND_ENV).unwrap_or_default();
+
+    let db: Result<Database, DatabaseError> = match sqlite_path(&backend) {
+        Some(path) => SqliteDatabase::open(&path, retention_days).map(|db| Box::new(db) as Database),
+        None => PostgresDatabase::new(rt.clone(), insecure_tls, retention_days)
+            .map(|db| Box::new(db) as Database),
+    };
+    let db = db.map_err(CalendarError::StorageError)?;
+    ensure_schema(db.as_ref(), schema).map_err(CalendarError::StorageError)?;
+
+    Ok(db)
+}
+
+/// Creates or migrates the schema when `schema` calls for it
+fn ensure_schema(db: &dyn EventStore, schema: SchemaInit) -> Result<(), DatabaseError> {
+    let run_ddl = match schema {
+        SchemaInit::Always => true,
+        SchemaInit::Never => false,
+        // A missing metadata table reads as an error, which means a first run too
+        SchemaInit::IfNeeded => !matches!(
+            db.get_metadata(SCHEMA_VERSION_KEY),
+            Ok(Some(version)) if version == SCHEMA_VERSION
+        ),
+    };
+    if run_ddl {
+        db.init_schema()?;
+        db.set_metadata(SCHEMA_VERSION_KEY, SCHEMA_VERSION)?;
+    }
+
+    Ok(())
+}
+
+/// Lists the environment variables the selected backend needs but are not set
+///
+/// SQLite needs none; PostgreSQL needs `DATABASE_URL` or every `PG*` variable.
+pub fn missing_env_vars() -> Vec<&'static str> {
+    let backend = env::var(DB_BACKEND_ENV).unwrap_or_default();
+    if sqlite_path(&backend).is_some() {
+        return Vec::new();
+    }
+
+    postgres::missing_env_vars()
+}
+
+/// Records `now` as the time the stored events were last synced from the calendar
+pub fn record_last_sync(db: &dyn EventStore) -> Result<(), DatabaseError> {
+    db.set_metadata(LAST_SYNC_KEY, &Utc::now().to_rfc3339())
+}
+
+/// When the stored events were last synced, `None` if they never were
+pub fn last_sync(db: &dyn EventStore) -> Result<Option<DateTime<Utc>>, DatabaseError> {
+    let Some(value) = db.get_metadata(LAST_SYNC_KEY)? else {
+        return Ok(None);
+    };
+
+    DateTime::parse_from_rfc3339(&value)
+        .map(|dt| Some(dt.with_timezone(&Utc)))
+        .map_err(|e| DatabaseError::DataConversionError(format!("Invalid {} value {}: {}", LAST_SYNC_KEY, value, e)))
+}
+
+/// Returns the SQLite file path selected by a `LUMABOT_DB` value, if any
+fn sqlite_path(backend: &str) -> Option<String> {
+    let backend = backend.trim();
+
+    if backend.eq_ignore_ascii_case("sqlite") {
+        return Some(DEFAULT_SQLITE_PATH.to_string());
+    }
+
+    backend.strip_prefix("sqlite://").map(|path| {
+        if path.is_empty() {
+            DEFAULT_SQLITE_PATH.to_string()
+        } else {
+            path.to_string()
+        }
+    })
+}
+
+/// Serializes a list for a JSON column such as `attendees` or `categories`
+fn list_json(values: &[String]) -> String {
+    serde_json::to_string(values).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Reads a JSON list column, treating NULL or malformed values as an empty list
+fn list_from_json(json: Option<String>) -> Vec<String> {
+    json.and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Start of the retention window: events that ended before this are not read back
+fn retention_cutoff(retention_days: u32) -> DateTime<Utc> {
+    Utc::now() - chrono::Duration::days(retention_days as i64)
+}
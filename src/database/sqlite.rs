@@ -0,0 +1,725 @@
+use super::{list_from_json, list_json, retention_cutoff, EventStore, SaveOutcome, SaveSummary};
+use crate::errors::DatabaseError;
+use crate::models::{Event, EventStatus};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use tracing::info;
+
+/// Columns selected by every event query, in the order `event_from_row` expects
+const EVENT_COLUMNS: &str =
+    "summary, description, location, start_time, end_time, url, event_uid, api_id, organizer, attendees, sequence, last_modified, categories, created_at, ical_uid, recurrence_id, original_end, all_day, status";
+
+/// Columns added after the original schema, created on databases that predate them
+const ADDED_COLUMNS: [(&str, &str); 10] = [
+    ("organizer", "TEXT"),
+    ("attendees", "TEXT"),
+    ("sequence", "INTEGER"),
+    ("last_modified", "TEXT"),
+    ("categories", "TEXT"),
+    ("ical_uid", "TEXT"),
+    ("original_end", "TEXT"),
+    ("all_day", "INTEGER"),
+    ("status", "TEXT"),
+    ("recurrence_id", "TEXT"),
+];
+
+/// Database handler for a local SQLite file
+pub struct SqliteDatabase {
+    conn: Connection,
+    retention_days: u32,
+}
+
+impl SqliteDatabase {
+    /// Opens (or creates) a SQLite database file, leaving the schema alone
+    pub fn open(path: &str, retention_days: u32) -> Result<Self, DatabaseError> {
+        let conn = Connection::open(path).map_err(|e| {
+            DatabaseError::ConnectionError(format!("Failed to open SQLite database {}: {}", path, e))
+        })?;
+
+        Ok(Self { conn, retention_days })
+    }
+
+    fn retention_cutoff(&self) -> DateTime<Utc> {
+        retention_cutoff(self.retention_days)
+    }
+
+    /// Inserts an event, or overwrites the stored copy when the incoming SEQUENCE is newer
+    ///
+    /// A stored copy that is kept still picks up a newly resolved api_id.
+    fn insert_event(conn: &Connection, event: &Event) -> Result<SaveOutcome, DatabaseError> {
+        // Rows saved under an older UID scheme would otherwise stay behind as
+        // duplicates; adopt one (keeping its api_id) unless the current UID is taken
+        for legacy_uid in event.legacy_uids() {
+            conn.execute(
+                "UPDATE events SET event_uid = ?1
+                 WHERE event_uid = ?2 AND NOT EXISTS (SELECT 1 FROM events WHERE event_uid = ?1)",
+                [&event.event_uid, legacy_uid],
+            )?;
+            conn.execute("DELETE FROM events WHERE event_uid = ?1", [legacy_uid])?;
+        }
+
+        let stored: Option<Option<i32>> = conn
+            .query_row(
+                "SELECT sequence FROM events WHERE event_uid = ?1",
+                [&event.event_uid],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let outcome = SaveOutcome::for_sequences(stored, event.sequence);
+        if outcome == SaveOutcome::Unchanged {
+            conn.execute(
+                "UPDATE events SET api_id = COALESCE(NULLIF(?1, ''), api_id) WHERE event_uid = ?2",
+                params![event.api_id, event.event_uid],
+            )?;
+            return Ok(outcome);
+        }
+
+        let clean_url = event.url.as_deref().map(Event::clean_string);
+
+        conn.execute(
+            "INSERT INTO events (summary, description, location, start_time, end_time, url, event_uid, api_id, organizer, attendees, sequence, last_modified, categories, ical_uid, original_end, all_day, status, recurrence_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
+             ON CONFLICT (event_uid) DO UPDATE SET
+                 summary = excluded.summary,
+                 description = excluded.description,
+                 location = excluded.location,
+                 start_time = excluded.start_time,
+                 end_time = excluded.end_time,
+                 url = excluded.url,
+                 organizer = excluded.organizer,
+                 attendees = excluded.attendees,
+                 sequence = excluded.sequence,
+                 last_modified = excluded.last_modified,
+                 categories = excluded.categories,
+                 ical_uid = excluded.ical_uid,
+                 original_end = excluded.original_end,
+                 all_day = excluded.all_day,
+                 status = excluded.status,
+                 recurrence_id = excluded.recurrence_id,
+                 api_id = COALESCE(NULLIF(excluded.api_id, ''), events.api_id)",
+            params![
+                event.summary,
+                event.description,
+                event.location,
+                event.start,
+                event.end,
+                clean_url,
+                event.event_uid,
+                event.api_id,
+                event.organizer,
+                list_json(&event.attendees),
+                event.sequence,
+                event.last_modified,
+                list_json(&event.categories),
+                event.ical_uid,
+                event.original_end,
+                event.all_day,
+                event.status.as_ical(),
+                event.recurrence_id,
+            ],
+        )?;
+
+        Ok(outcome)
+    }
+
+    fn query_events(
+        &self,
+        sql: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<Event>, DatabaseError> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let events = stmt
+            .query_map(params, event_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(events)
+    }
+}
+
+impl EventStore for SqliteDatabase {
+    fn init_schema(&self) -> Result<(), DatabaseError> {
+        // Create tables if they don't exist, with the same columns as PostgreSQL
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                summary TEXT NOT NULL,
+                description TEXT,
+                location TEXT,
+                start_time TEXT NOT NULL,
+                end_time TEXT NOT NULL,
+                url TEXT,
+                event_uid TEXT NOT NULL UNIQUE,
+                api_id TEXT,
+                organizer TEXT,
+                attendees TEXT,
+                sequence INTEGER,
+                last_modified TEXT,
+                categories TEXT,
+                ical_uid TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        // Bring databases created by older versions up to date
+        for (column, column_type) in ADDED_COLUMNS {
+            let column_exists: bool = self.conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info('events') WHERE name = ?1)",
+                [column],
+                |row| row.get(0),
+            )?;
+            if !column_exists {
+                info!("Adding {} column to events table...", column);
+                self.conn.execute(&format!("ALTER TABLE events ADD COLUMN {} {}", column, column_type), [])?;
+                info!("Migration complete: {} column added.", column);
+            }
+        }
+
+        // Index the columns every read query filters and orders on
+        self.conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_events_start_time ON events(start_time);
+             CREATE INDEX IF NOT EXISTS idx_events_end_time ON events(end_time);",
+        )?;
+
+        // Key/value store for facts about the database itself, such as the last sync
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS metadata (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    fn save_event(&self, event: &Event) -> Result<(), DatabaseError> {
+        Self::insert_event(&self.conn, event).map(|_| ())
+    }
+
+    fn save_events(&self, events: &[Event]) -> Result<SaveSummary, DatabaseError> {
+        // Dropping the transaction on error rolls back everything written so far
+        let transaction = self.conn.unchecked_transaction()?;
+        let mut summary = SaveSummary::default();
+        for event in events {
+            summary.record(Self::insert_event(&transaction, event)?);
+        }
+        transaction.commit()?;
+
+        Ok(summary)
+    }
+
+    fn get_all_events(&self) -> Result<Vec<Event>, DatabaseError> {
+        let cutoff = self.retention_cutoff();
+
+        self.query_events(
+            &format!(
+                "SELECT {} FROM events WHERE end_time >= ?1 ORDER BY start_time",
+                EVENT_COLUMNS
+            ),
+            &[&cutoff],
+        )
+    }
+
+    fn get_all_events_unfiltered(&self) -> Result<Vec<Event>, DatabaseError> {
+        self.query_events(
+            &format!("SELECT {} FROM events ORDER BY start_time", EVENT_COLUMNS),
+            &[],
+        )
+    }
+
+    fn get_events_page(&self, limit: i64, offset: i64) -> Result<Vec<Event>, DatabaseError> {
+        let cutoff = self.retention_cutoff();
+        // SQLite reads a negative LIMIT as no limit
+        let limit = if limit > 0 { limit } else { -1 };
+
+        self.query_events(
+            &format!(
+                "SELECT {} FROM events WHERE end_time >= ?1 ORDER BY start_time LIMIT ?2 OFFSET ?3",
+                EVENT_COLUMNS
+            ),
+            &[&cutoff, &limit, &offset.max(0)],
+        )
+    }
+
+    fn get_events_added_since(&self, since: DateTime<Utc>) -> Result<Vec<Event>, DatabaseError> {
+        let cutoff = self.retention_cutoff();
+
+        // created_at holds CURRENT_TIMESTAMP text without an offset, so compare
+        // both sides through datetime() rather than as raw strings
+        self.query_events(
+            &format!(
+                "SELECT {} FROM events
+                 WHERE datetime(created_at) >= datetime(?1) AND end_time >= ?2
+                 ORDER BY start_time",
+                EVENT_COLUMNS
+            ),
+            &[&since, &cutoff],
+        )
+    }
+
+    fn get_events_in_range(
+        &self,
+        start_date: &DateTime<Utc>,
+        end_date: &DateTime<Utc>,
+    ) -> Result<Vec<Event>, DatabaseError> {
+        let cutoff = self.retention_cutoff();
+
+        // Use the later of start_date or the retention cutoff as the effective start date
+        let effective_start_date = if start_date < &cutoff {
+            &cutoff
+        } else {
+            start_date
+        };
+
+        self.query_events(
+            &format!(
+                "SELECT {} FROM events
+                 WHERE start_time >= ?1 AND start_time <= ?2 AND end_time >= ?3
+                 ORDER BY start_time",
+                EVENT_COLUMNS
+            ),
+            &[effective_start_date, end_date, &cutoff],
+        )
+    }
+
+    fn get_events_missing_api_id(&self) -> Result<Vec<Event>, DatabaseError> {
+        let cutoff = self.retention_cutoff();
+
+        self.query_events(
+            &format!(
+                "SELECT {} FROM events
+                 WHERE (api_id IS NULL OR api_id = '') AND end_time >= ?1
+                 ORDER BY start_time",
+                EVENT_COLUMNS
+            ),
+            &[&cutoff],
+        )
+    }
+
+    fn get_event_count(&self) -> Result<i64, DatabaseError> {
+        let cutoff = self.retention_cutoff();
+
+        let count = self.conn.query_row(
+            "SELECT COUNT(*) FROM events WHERE end_time >= ?1",
+            [&cutoff],
+            |row| row.get(0),
+        )?;
+
+        Ok(count)
+    }
+
+    fn clear_all_events(&self) -> Result<u64, DatabaseError> {
+        let deleted = self.conn.execute("DELETE FROM events", [])?;
+        Ok(deleted as u64)
+    }
+
+    fn clear_past_events(&self, before: DateTime<Utc>) -> Result<u64, DatabaseError> {
+        let deleted = self.conn.execute("DELETE FROM events WHERE end_time < ?1", [before])?;
+        Ok(deleted as u64)
+    }
+
+    fn get_metadata(&self, key: &str) -> Result<Option<String>, DatabaseError> {
+        let value = self
+            .conn
+            .query_row("SELECT value FROM metadata WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?;
+        Ok(value)
+    }
+
+    fn set_metadata(&self, key: &str, value: &str) -> Result<(), DatabaseError> {
+        self.conn.execute(
+            "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            [key, value],
+        )?;
+        Ok(())
+    }
+
+    fn delete_event(&self, event_uid: &str) -> Result<u64, DatabaseError> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM events WHERE event_uid = ?1", [event_uid])?;
+        Ok(deleted as u64)
+    }
+}
+
+/// Builds an event from a row selected with `EVENT_COLUMNS`
+fn event_from_row(row: &Row) -> rusqlite::Result<Event> {
+    let url: Option<String> = row.get("url")?;
+    let attendees: Option<String> = row.get("attendees")?;
+    let categories: Option<String> = row.get("categories")?;
+
+    let mut event = Event::with_uid_and_api_id(
+        row.get("summary")?,
+        row.get("description")?,
+        row.get("location")?,
+        row.get("start_time")?,
+        row.get("end_time")?,
+        url.as_deref().map(Event::clean_string),
+        row.get("event_uid")?,
+        row.get("api_id")?,
+    );
+    event.organizer = row.get("organizer")?;
+    event.attendees = list_from_json(attendees);
+    event.sequence = row.get("sequence")?;
+    event.last_modified = row.get("last_modified")?;
+    event.categories = list_from_json(categories);
+    event.created_at = row.get("created_at")?;
+    event.ical_uid = row.get("ical_uid")?;
+    event.recurrence_id = row.get("recurrence_id")?;
+    event.original_end = row.get("original_end")?;
+    event.all_day = row.get::<_, Option<bool>>("all_day")?.unwrap_or(false);
+    event.status = row.get::<_, Option<String>>("status")?.map(|status| EventStatus::from_ical(&status)).unwrap_or_default();
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::{parse_calendar_from_reader, ParseOptions, DEFAULT_RETENTION_DAYS};
+    use crate::database::{ensure_schema, last_sync, record_last_sync, SchemaInit};
+
+    /// An in-memory database with the current schema
+    fn db() -> SqliteDatabase {
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        db
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    /// A one-hour event starting at `start`
+    fn event(summary: &str, start: &str) -> Event {
+        let start = utc(start);
+        Event::new(summary.to_string(), None, None, start, start + chrono::Duration::hours(1), None)
+    }
+
+    #[test]
+    fn saved_events_read_back() {
+        let db = db();
+        let mut meetup = event("Rust Meetup", "2030-01-15T18:00:00Z");
+        meetup.location = Some("Community Hall".to_string());
+        meetup.url = Some("https://lu.ma/rust-meetup".to_string());
+        meetup.api_id = Some("evt-123".to_string());
+        meetup.categories = vec!["Tech".to_string(), "Social".to_string()];
+
+        db.save_event(&meetup).unwrap();
+        let stored = db.get_all_events().unwrap();
+
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].summary, "Rust Meetup");
+        assert_eq!(stored[0].start, meetup.start);
+        assert_eq!(stored[0].end, meetup.end);
+        assert_eq!(stored[0].location, meetup.location);
+        assert_eq!(stored[0].url, meetup.url);
+        assert_eq!(stored[0].event_uid, meetup.event_uid);
+        assert_eq!(stored[0].api_id.as_deref(), Some("evt-123"));
+        assert_eq!(stored[0].categories, meetup.categories);
+        assert!(stored[0].created_at.is_some());
+    }
+
+    #[test]
+    fn saving_an_event_twice_keeps_one_row() {
+        let db = db();
+        let meetup = event("Rust Meetup", "2030-01-15T18:00:00Z");
+
+        let first = db.save_events(std::slice::from_ref(&meetup)).unwrap();
+        let second = db.save_events(&[meetup]).unwrap();
+
+        assert_eq!(first.inserted, 1);
+        assert_eq!(second.inserted, 0);
+        assert_eq!(db.get_event_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn init_schema_can_run_again() {
+        let db = db();
+        db.save_event(&event("Rust Meetup", "2030-01-15T18:00:00Z")).unwrap();
+
+        db.init_schema().unwrap();
+
+        assert_eq!(db.get_event_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn delete_event_removes_only_the_matching_uid() {
+        let db = db();
+        let meetup = event("Rust Meetup", "2030-01-15T18:00:00Z");
+        let workshop = event("Embedded Workshop", "2030-01-16T18:00:00Z");
+        db.save_events(&[meetup.clone(), workshop.clone()]).unwrap();
+
+        assert_eq!(db.delete_event(&meetup.event_uid).unwrap(), 1);
+        assert_eq!(db.delete_event(&meetup.event_uid).unwrap(), 0);
+
+        assert_eq!(db.get_event_count().unwrap(), 1);
+        assert_eq!(db.get_all_events().unwrap()[0].event_uid, workshop.event_uid);
+    }
+
+    #[test]
+    fn a_failure_mid_batch_commits_nothing() {
+        let db = db();
+        db.conn
+            .execute_batch(
+                "CREATE TRIGGER reject_broken BEFORE INSERT ON events WHEN NEW.summary = 'Broken'
+                 BEGIN SELECT RAISE(ABORT, 'rejected'); END;",
+            )
+            .unwrap();
+        let events = [
+            event("Rust Meetup", "2030-01-15T18:00:00Z"),
+            event("Broken", "2030-01-16T18:00:00Z"),
+            event("Embedded Workshop", "2030-01-17T18:00:00Z"),
+        ];
+
+        assert!(db.save_events(&events).is_err());
+        assert_eq!(db.get_event_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn saving_a_rescheduled_event_updates_its_row_and_keeps_the_api_id() {
+        let db = db();
+        let mut meetup = event("Rust Meetup", "2030-01-15T18:00:00Z");
+        meetup.set_ical_uid("meetup@lu.ma", None);
+        meetup.api_id = Some("evt-123".to_string());
+        db.save_event(&meetup).unwrap();
+
+        let mut rescheduled = event("Rust Meetup", "2030-01-22T18:00:00Z");
+        rescheduled.set_ical_uid("meetup@lu.ma", None);
+        rescheduled.location = Some("New Venue".to_string());
+        let summary = db.save_events(&[rescheduled]).unwrap();
+
+        assert_eq!(summary.updated, 1);
+        let stored = db.get_all_events().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].start, utc("2030-01-22T18:00:00Z"));
+        assert_eq!(stored[0].location.as_deref(), Some("New Venue"));
+        assert_eq!(stored[0].api_id.as_deref(), Some("evt-123"));
+    }
+
+    #[test]
+    fn init_schema_indexes_start_and_end_times() {
+        let db = db();
+        let mut stmt = db
+            .conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'events'")
+            .unwrap();
+        let indexes: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert!(indexes.iter().any(|name| name == "idx_events_start_time"));
+        assert!(indexes.iter().any(|name| name == "idx_events_end_time"));
+    }
+
+    /// The meetup at revision `sequence`, moved to a start time that shows which revision is stored
+    fn meetup_revision(sequence: i32, start: &str) -> Event {
+        let mut meetup = event("Rust Meetup", start);
+        meetup.set_ical_uid("meetup@lu.ma", None);
+        meetup.sequence = Some(sequence);
+        meetup
+    }
+
+    #[test]
+    fn only_a_higher_sequence_overwrites_the_stored_event() {
+        let db = db();
+        db.save_event(&meetup_revision(1, "2030-01-15T18:00:00Z")).unwrap();
+
+        let equal = db.save_events(&[meetup_revision(1, "2030-01-16T18:00:00Z")]).unwrap();
+        assert_eq!((equal.inserted, equal.updated, equal.unchanged), (0, 0, 1));
+        assert_eq!(db.get_all_events().unwrap()[0].start, utc("2030-01-15T18:00:00Z"));
+
+        let higher = db.save_events(&[meetup_revision(2, "2030-01-17T18:00:00Z")]).unwrap();
+        assert_eq!((higher.inserted, higher.updated, higher.unchanged), (0, 1, 0));
+        let stored = db.get_all_events().unwrap();
+        assert_eq!(stored[0].start, utc("2030-01-17T18:00:00Z"));
+        assert_eq!(stored[0].sequence, Some(2));
+    }
+
+    #[test]
+    fn events_missing_an_api_id_are_listed_alone() {
+        let db = db();
+        let mut enriched = event("Rust Meetup", "2030-01-15T18:00:00Z");
+        enriched.api_id = Some("evt-123".to_string());
+        let mut pending = event("Embedded Workshop", "2030-01-16T18:00:00Z");
+        pending.url = Some("https://lu.ma/embedded".to_string());
+        let also_pending = event("Go Night", "2030-01-17T18:00:00Z");
+        db.save_events(&[enriched, pending, also_pending]).unwrap();
+
+        let missing = db.get_events_missing_api_id().unwrap();
+
+        let summaries: Vec<&str> = missing.iter().map(|event| event.summary.as_str()).collect();
+        assert_eq!(summaries, ["Embedded Workshop", "Go Night"]);
+        assert_eq!(missing[0].url.as_deref(), Some("https://lu.ma/embedded"));
+    }
+
+    #[test]
+    fn pages_are_disjoint_and_ordered_by_start() {
+        let db = db();
+        db.save_events(&[
+            event("Third", "2030-01-17T18:00:00Z"),
+            event("First", "2030-01-15T18:00:00Z"),
+            event("Fourth", "2030-01-18T18:00:00Z"),
+            event("Second", "2030-01-16T18:00:00Z"),
+        ])
+        .unwrap();
+        let page = |offset| {
+            db.get_events_page(2, offset)
+                .unwrap()
+                .into_iter()
+                .map(|event| event.summary)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(page(0), ["First", "Second"]);
+        assert_eq!(page(2), ["Third", "Fourth"]);
+        assert!(page(4).is_empty());
+    }
+
+    #[test]
+    fn rows_stored_under_a_legacy_uid_are_adopted() {
+        let calendar = [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "BEGIN:VEVENT",
+            r"SUMMARY:Rust Meetup\, talks & demos",
+            r"DESCRIPTION:Talks\nDrinks after",
+            r"LOCATION:Hall\; Room 2",
+            "DTSTART:20300115T180000Z",
+            "DTEND:20300115T200000Z",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let meetup = parse_calendar_from_reader(calendar.as_bytes(), ParseOptions::default()).unwrap().remove(0);
+
+        // The ID older releases derived from the escaped SUMMARY, DESCRIPTION and LOCATION
+        let db = db();
+        let mut legacy_row = meetup.clone();
+        legacy_row.event_uid = r"Rust_Meetup\,_talks_&_demos-1894730400-d844fb1a077dcd2a".to_string();
+        legacy_row.api_id = Some("evt-123".to_string());
+        db.save_event(&legacy_row).unwrap();
+
+        db.save_event(&meetup).unwrap();
+
+        let stored = db.get_all_events().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].event_uid, meetup.event_uid);
+        assert_eq!(stored[0].api_id.as_deref(), Some("evt-123"));
+    }
+
+    fn has_index(db: &SqliteDatabase, name: &str) -> bool {
+        db.conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = ?1",
+                [name],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn existing_schema_is_not_initialized_again() {
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        ensure_schema(&db, SchemaInit::IfNeeded).unwrap();
+        assert!(has_index(&db, "idx_events_start_time"));
+
+        // Dropping an index shows whether the DDL runs again
+        db.conn.execute("DROP INDEX idx_events_start_time", []).unwrap();
+        ensure_schema(&db, SchemaInit::IfNeeded).unwrap();
+        assert!(!has_index(&db, "idx_events_start_time"));
+
+        ensure_schema(&db, SchemaInit::Always).unwrap();
+        assert!(has_index(&db, "idx_events_start_time"));
+    }
+
+    #[test]
+    fn clear_past_events_deletes_only_events_that_ended() {
+        let db = db();
+        db.save_events(&[
+            event("January Meetup", "2030-01-15T18:00:00Z"),
+            event("Late Night Hack", "2030-01-31T23:30:00Z"),
+            event("March Meetup", "2030-03-01T18:00:00Z"),
+        ])
+        .unwrap();
+
+        let deleted = db.clear_past_events(utc("2030-02-01T00:00:00Z")).unwrap();
+
+        assert_eq!(deleted, 1);
+        let remaining: Vec<String> = db.get_all_events().unwrap().into_iter().map(|event| event.summary).collect();
+        assert_eq!(remaining, ["Late Night Hack", "March Meetup"]);
+    }
+
+    #[test]
+    fn events_added_since_are_selected_by_creation_time() {
+        let db = db();
+        db.save_events(&[
+            event("Imported Long Ago", "2030-01-15T18:00:00Z"),
+            event("Just Imported", "2030-01-16T18:00:00Z"),
+        ])
+        .unwrap();
+        db.conn
+            .execute(
+                "UPDATE events SET created_at = '2020-01-01 00:00:00' WHERE summary = 'Imported Long Ago'",
+                [],
+            )
+            .unwrap();
+
+        let recent = db.get_events_added_since(Utc::now() - chrono::Duration::hours(1)).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].summary, "Just Imported");
+        assert!(recent[0].created_at.is_some_and(|created_at| created_at > utc("2020-01-01T00:00:00Z")));
+
+        let since_2019 = db.get_events_added_since(utc("2019-12-31T00:00:00Z")).unwrap();
+        assert_eq!(since_2019.len(), 2);
+    }
+
+    #[test]
+    fn last_sync_time_round_trips() {
+        let db = db();
+        assert_eq!(last_sync(&db).unwrap(), None);
+
+        let before = Utc::now();
+        record_last_sync(&db).unwrap();
+        let synced = last_sync(&db).unwrap().unwrap();
+        assert!(synced >= before && synced <= Utc::now());
+
+        db.set_metadata("last_sync", "yesterday").unwrap();
+        assert!(last_sync(&db).is_err());
+    }
+
+    #[test]
+    fn all_day_and_status_read_back() {
+        let db = db();
+        let mut holiday = event("Holiday", "2030-01-15T00:00:00Z");
+        holiday.all_day = true;
+        holiday.status = EventStatus::Tentative;
+
+        db.save_events(&[holiday, event("Rust Meetup", "2030-01-16T18:00:00Z")]).unwrap();
+        let stored = db.get_all_events().unwrap();
+
+        assert!(stored[0].all_day);
+        assert_eq!(stored[0].status, EventStatus::Tentative);
+        assert!(!stored[1].all_day);
+        assert_eq!(stored[1].status, EventStatus::Confirmed);
+    }
+
+    #[test]
+    fn recurrence_ids_read_back() {
+        let db = db();
+        let mut instance = event("Office Hours (rescheduled)", "2030-01-23T18:00:00Z");
+        instance.set_ical_uid("office-hours@lu.ma", Some("20300122T180000Z"));
+
+        db.save_event(&instance).unwrap();
+        let stored = db.get_all_events().unwrap();
+
+        assert_eq!(stored[0].event_uid, "office-hours@lu.ma#20300122T180000Z");
+        assert_eq!(stored[0].ical_uid.as_deref(), Some("office-hours@lu.ma"));
+        assert_eq!(stored[0].recurrence_id.as_deref(), Some("20300122T180000Z"));
+    }
+}
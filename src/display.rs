@@ -1,66 +1,439 @@
 use crate::models::Event;
-use chrono::{Datelike, Duration, Local, NaiveDate, Utc};
-use colored::Colorize;
-use std::collections::HashMap;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use unicode_segmentation::UnicodeSegmentation;
 
-/// Displays a list of events with a limit
-pub fn display_events(events: &[Event], limit: usize, verbose: bool) {
-    println!("{}", "Upcoming Events".bright_blue().bold());
-    println!("{}", "═".repeat(80).bright_blue());
-    
-    let limited_events = if limit > 0 && limit < events.len() {
-        &events[0..limit]
+/// Output format for event listings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored, human-readable listing
+    #[default]
+    Pretty,
+    /// A JSON array of events with RFC 3339 timestamps
+    Json,
+    /// One `START<TAB>SUMMARY` line per event, with no headers or colors
+    Plain,
+}
+
+/// Ordering applied to event listings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    /// Start time, earliest first
+    #[default]
+    Start,
+    /// Summary, alphabetically (case-insensitive)
+    Title,
+    /// Duration, shortest first
+    Duration,
+}
+
+/// Options controlling how event listings are rendered
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisplayOptions {
+    /// Show location, URL, description and duration for each event
+    pub verbose: bool,
+    /// The output format
+    pub format: OutputFormat,
+    /// Show how far each event is from now ("in 2 days") after its time range
+    pub relative: bool,
+    /// Zone used for displayed times and day boundaries (`Local` when unset)
+    pub timezone: Option<Tz>,
+    /// Keep the paragraphs of verbose descriptions instead of joining them into one line
+    pub full_description: bool,
+    /// Truncate summaries longer than this many characters
+    pub summary_width: Option<usize>,
+    /// Width of the terminal, used to fit each event on one line when `summary_width` is unset
+    pub terminal_width: Option<usize>,
+    /// Split listings into sections by day, week or location
+    pub group_by: Option<GroupBy>,
+    /// Only include these fields in the JSON and plain formats
+    pub fields: Option<FieldSelection>,
+    /// Print how many events a listing holds instead of the events
+    pub count_only: bool,
+}
+
+/// A subset of `Event::FIELDS`, selected for machine-readable output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSelection(u32);
+
+impl FieldSelection {
+    /// Parses a comma-separated list of field names such as `summary,start,url`
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut mask = 0;
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let index = Event::FIELDS.iter().position(|field| *field == name).ok_or_else(|| {
+                format!("unknown field '{}' (valid fields: {})", name, Event::FIELDS.join(", "))
+            })?;
+            mask |= 1 << index;
+        }
+
+        if mask == 0 {
+            return Err(format!("expected at least one field name (valid fields: {})", Event::FIELDS.join(", ")));
+        }
+        Ok(Self(mask))
+    }
+
+    /// The selected field names, in `Event::FIELDS` order
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Event::FIELDS
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| self.0 & (1 << index) != 0)
+            .map(|(_, name)| *name)
+    }
+
+    /// The selected fields of `event`, keyed by name
+    pub fn select(self, event: &Event) -> serde_json::Map<String, serde_json::Value> {
+        let serde_json::Value::Object(mut fields) = serde_json::to_value(event).unwrap_or_default() else {
+            return serde_json::Map::new();
+        };
+        fields.retain(|name, _| self.names().any(|selected| selected == name));
+        fields
+    }
+}
+
+/// Truncates a listing to its first `limit` entries, where a limit of 0 means no limit
+pub fn limit_events<T>(events: &[T], limit: usize) -> &[T] {
+    if limit > 0 && limit < events.len() {
+        &events[..limit]
     } else {
         events
-    };
+    }
+}
+
+/// Displays a list of events with a limit (0 for no limit)
+pub fn display_events(out: &mut dyn Write, events: &[Event], limit: usize, options: DisplayOptions) -> io::Result<()> {
+    let limited_events = limit_events(events, limit);
     
     // Convert &[Event] to Vec<&Event> for display_event_list
     let event_refs: Vec<&Event> = limited_events.iter().collect();
-    display_event_list(&event_refs, verbose);
     
-    if limit > 0 && limit < events.len() {
-        println!("\n{}", format!("Showing {}/{} events. Use --limit to see more.", limit, events.len()).yellow());
+    if print_machine_readable(out, &event_refs, options)? {
+        return Ok(());
+    }
+    
+    writeln!(out, "{}", "Upcoming Events".bright_blue().bold())?;
+    writeln!(out, "{}", "═".repeat(80).bright_blue())?;
+    
+    display_listing(out, &event_refs, options)?;
+    
+    if limited_events.len() < events.len() {
+        writeln!(
+            out,
+            "\n{}",
+            format!("Showing {}/{} events. Use --limit or --all to see more.", limited_events.len(), events.len()).yellow()
+        )?;
     }
+    
+    Ok(())
 }
 
-/// Displays today's events
-pub fn display_today_events(events: &[Event], verbose: bool) {
-    let today = Local::now().date_naive();
-    let today_events: Vec<&Event> = events
+/// Keeps events matching every filter term (case-insensitive) in their summary,
+/// description or location, and tagged with every one of `categories`
+pub fn filter_events(events: &[Event], terms: &[String], categories: &[String]) -> Vec<Event> {
+    let terms: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
+    
+    events
+        .iter()
+        .filter(|event| categories.iter().all(|category| event.has_category(category)))
+        .filter(|event| {
+            let haystack = [
+                Some(event.summary.as_str()),
+                event.description.as_deref(),
+                event.location.as_deref(),
+            ]
+            .iter()
+            .flatten()
+            .map(|field| field.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("\n");
+            
+            terms.iter().all(|term| haystack.contains(term.as_str()))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Keeps events lasting at least `min` and at most `max` minutes, where a bound
+/// of 0 means no limit on that side
+pub fn filter_by_duration(events: Vec<Event>, min: u32, max: u32) -> Vec<Event> {
+    events
+        .into_iter()
+        .filter(|event| {
+            let minutes = event.duration_minutes();
+            (min == 0 || minutes >= min as i64) && (max == 0 || minutes <= max as i64)
+        })
+        .collect()
+}
+
+/// Drops near-duplicates: events whose summaries match after normalization
+/// (lowercase, trimmed, punctuation removed) and whose starts are no more than
+/// `window` apart. The first occurrence of each is kept, in the original order.
+pub fn dedupe_events(events: Vec<Event>, window: Duration) -> Vec<Event> {
+    let normalize = |summary: &str| {
+        summary
+            .chars()
+            .filter(|c| !c.is_ascii_punctuation())
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+
+    // Start times already kept, per normalized summary
+    let mut kept: HashMap<String, Vec<DateTime<Utc>>> = HashMap::new();
+    events
+        .into_iter()
+        .filter(|event| {
+            let starts = kept.entry(normalize(&event.summary)).or_default();
+            if starts.iter().any(|start| (event.start - *start).abs() <= window) {
+                return false;
+            }
+            starts.push(event.start);
+            true
+        })
+        .collect()
+}
+
+/// Sorts events by `key` (ties broken by start time), optionally reversing the order
+pub fn sort_events(events: &mut [Event], key: SortKey, reverse: bool) {
+    let compare = match key {
+        SortKey::Start => compare_by_start,
+        SortKey::Title => compare_by_title,
+        SortKey::Duration => compare_by_duration,
+    };
+    
+    events.sort_by(|a, b| {
+        let ordering = compare(a, b).then_with(|| compare_by_start(a, b));
+        if reverse { ordering.reverse() } else { ordering }
+    });
+}
+
+fn compare_by_start(a: &Event, b: &Event) -> Ordering {
+    a.start.cmp(&b.start)
+}
+
+fn compare_by_title(a: &Event, b: &Event) -> Ordering {
+    a.summary.to_lowercase().cmp(&b.summary.to_lowercase())
+}
+
+fn compare_by_duration(a: &Event, b: &Event) -> Ordering {
+    a.duration_minutes().cmp(&b.duration_minutes())
+}
+
+/// Converts a timestamp to `timezone`, or to the local zone when none is given
+pub fn in_zone(dt: DateTime<Utc>, timezone: Option<Tz>) -> DateTime<FixedOffset> {
+    match timezone {
+        Some(tz) => dt.with_timezone(&tz).fixed_offset(),
+        None => dt.with_timezone(&Local).fixed_offset(),
+    }
+}
+
+/// Bucket size for event counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum StatsPeriod {
+    /// One bucket per calendar day
+    #[default]
+    Day,
+    /// One bucket per Monday-Sunday week
+    Week,
+}
+
+/// Key for splitting a listing into sections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// One section per day of the start
+    Day,
+    /// One section per Monday-Sunday week of the start
+    Week,
+    /// One section per location, with events without one under "Unspecified"
+    Location,
+}
+
+/// Returns the Monday of the week containing `date`
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Splits events into titled sections, keeping their order within each section
+///
+/// Day and week sections come in date order, with "(Today)" or "(This Week)"
+/// marking the one containing `now`; location sections come alphabetically,
+/// with "Unspecified" last.
+pub fn group_events<'a>(
+    events: &[&'a Event],
+    group_by: GroupBy,
+    now: DateTime<Utc>,
+    timezone: Option<Tz>,
+) -> Vec<(String, Vec<&'a Event>)> {
+    let today = in_zone(now, timezone).date_naive();
+
+    match group_by {
+        GroupBy::Day | GroupBy::Week => {
+            let mut groups: BTreeMap<NaiveDate, Vec<&Event>> = BTreeMap::new();
+            for event in events {
+                let date = in_zone(event.start, timezone).date_naive();
+                let key = if group_by == GroupBy::Week { week_start(date) } else { date };
+                groups.entry(key).or_default().push(*event);
+            }
+
+            groups
+                .into_iter()
+                .map(|(date, events)| {
+                    let title = match group_by {
+                        GroupBy::Week if date == week_start(today) => {
+                            format!("Week of {} (This Week)", date.format("%A, %B %d, %Y"))
+                        }
+                        GroupBy::Week => format!("Week of {}", date.format("%A, %B %d, %Y")),
+                        _ if date == today => format!("{} (Today)", date.format("%A, %B %d, %Y")),
+                        _ => date.format("%A, %B %d, %Y").to_string(),
+                    };
+                    (title, events)
+                })
+                .collect()
+        }
+        GroupBy::Location => {
+            // Sorting on (missing, name) puts the unspecified bucket last
+            let mut groups: BTreeMap<(bool, String), Vec<&Event>> = BTreeMap::new();
+            for event in events {
+                let location = event.location.as_deref().map(str::trim).filter(|l| !l.is_empty());
+                let key = (location.is_none(), location.unwrap_or("Unspecified").to_string());
+                groups.entry(key).or_default().push(*event);
+            }
+
+            groups.into_iter().map(|((_, title), events)| (title, events)).collect()
+        }
+    }
+}
+
+/// Counts events per day or week of their start in the given zone, in date order
+///
+/// Weeks are keyed by their Monday. Days or weeks without events are left out.
+pub fn event_counts(events: &[Event], period: StatsPeriod, timezone: Option<Tz>) -> Vec<(NaiveDate, usize)> {
+    let mut counts: HashMap<NaiveDate, usize> = HashMap::new();
+    for event in events {
+        let date = in_zone(event.start, timezone).date_naive();
+        let bucket = match period {
+            StatsPeriod::Day => date,
+            StatsPeriod::Week => week_start(date),
+        };
+        *counts.entry(bucket).or_default() += 1;
+    }
+    
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort();
+    counts
+}
+
+/// Returns the events starting on the same day as `now` in the given zone
+///
+/// The day runs from midnight to midnight in `timezone` (the local zone when
+/// unset), not in the zone the event was published in.
+pub fn today_events(events: &[Event], now: DateTime<Utc>, timezone: Option<Tz>) -> Vec<&Event> {
+    let today = in_zone(now, timezone).date_naive();
+    events
         .iter()
         .filter(|e| {
-            let event_date = e.start.with_timezone(&Local).date_naive();
+            let event_date = in_zone(e.start, timezone).date_naive();
             event_date == today
         })
-        .collect();
+        .collect()
+}
+
+/// Returns the Monday and Sunday of the week containing `now` in the given zone
+fn current_week(now: DateTime<Utc>, timezone: Option<Tz>) -> (NaiveDate, NaiveDate) {
+    let monday = week_start(in_zone(now, timezone).date_naive());
+    (monday, monday + Duration::days(6))
+}
+
+/// Returns the events starting during the Monday-Sunday week containing `now`
+/// in the given zone
+pub fn week_events(events: &[Event], now: DateTime<Utc>, timezone: Option<Tz>) -> Vec<&Event> {
+    let (monday, sunday) = current_week(now, timezone);
+    events
+        .iter()
+        .filter(|e| {
+            let event_date = in_zone(e.start, timezone).date_naive();
+            event_date >= monday && event_date <= sunday
+        })
+        .collect()
+}
+
+/// Returns the events starting between now and N days from now
+pub fn upcoming_events(events: &[Event], days: u32) -> Vec<&Event> {
+    let today = Utc::now();
+    let end_date = today + Duration::days(days as i64);
+    events
+        .iter()
+        .filter(|e| e.start >= today && e.start <= end_date)
+        .collect()
+}
+
+/// Returns the events starting on a day from `from` through `to` in the given
+/// zone; a missing bound leaves that side open
+pub fn range_events(
+    events: &[Event],
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    timezone: Option<Tz>,
+) -> Vec<&Event> {
+    events
+        .iter()
+        .filter(|e| {
+            let event_date = in_zone(e.start, timezone).date_naive();
+            from.is_none_or(|from| event_date >= from) && to.is_none_or(|to| event_date <= to)
+        })
+        .collect()
+}
+
+/// Returns the event starting soonest after `now`, if any
+pub fn next_event(events: &[Event], now: DateTime<Utc>) -> Option<&Event> {
+    events
+        .iter()
+        .filter(|e| e.start > now)
+        .min_by_key(|e| e.start)
+}
+
+/// Displays today's events
+pub fn display_today_events(out: &mut dyn Write, events: &[Event], options: DisplayOptions) -> io::Result<()> {
+    // Read the clock once so the heading and the selection agree around midnight
+    let now = Utc::now();
+    let today = in_zone(now, options.timezone).date_naive();
+    let today_events = today_events(events, now, options.timezone);
     
-    println!("{}", format!("Events for Today ({})", today.format("%A, %B %d, %Y")).bright_blue().bold());
-    println!("{}", "═".repeat(80).bright_blue());
+    if print_machine_readable(out, &today_events, options)? {
+        return Ok(());
+    }
+    
+    writeln!(out, "{}", format!("Events for Today ({})", today.format("%A, %B %d, %Y")).bright_blue().bold())?;
+    writeln!(out, "{}", "═".repeat(80).bright_blue())?;
     
     if today_events.is_empty() {
-        println!("{}", "No events scheduled for today.".yellow());
-        return;
+        writeln!(out, "{}", "No events scheduled for today.".yellow())?;
+        return Ok(());
     }
     
-    display_event_list(&today_events, verbose);
+    display_listing(out, &today_events, options)
 }
 
 /// Displays events for the current week
-pub fn display_week_events(events: &[Event], verbose: bool) {
-    let today = Local::now().date_naive();
-    let days_since_monday = today.weekday().num_days_from_monday();
-    let monday = today - Duration::days(days_since_monday as i64);
-    let sunday = monday + Duration::days(6);
+pub fn display_week_events(out: &mut dyn Write, events: &[Event], options: DisplayOptions) -> io::Result<()> {
+    let now = Utc::now();
+    let (monday, sunday) = current_week(now, options.timezone);
+    let week_events = week_events(events, now, options.timezone);
     
-    let week_events: Vec<&Event> = events
-        .iter()
-        .filter(|e| {
-            let event_date = e.start.with_timezone(&Local).date_naive();
-            event_date >= monday && event_date <= sunday
-        })
-        .collect();
+    if print_machine_readable(out, &week_events, options)? {
+        return Ok(());
+    }
     
-    println!(
+    writeln!(
+        out,
         "{}",
         format!(
             "Events for This Week ({} - {})",
@@ -69,56 +442,36 @@ pub fn display_week_events(events: &[Event], verbose: bool) {
         )
         .bright_blue()
         .bold()
-    );
-    println!("{}", "═".repeat(80).bright_blue());
+    )?;
+    writeln!(out, "{}", "═".repeat(80).bright_blue())?;
     
     if week_events.is_empty() {
-        println!("{}", "No events scheduled for this week.".yellow());
-        return;
+        writeln!(out, "{}", "No events scheduled for this week.".yellow())?;
+        return Ok(());
     }
     
-    // Group events by day
-    let mut events_by_day: HashMap<NaiveDate, Vec<&Event>> = HashMap::new();
-    
-    for event in week_events {
-        let date = event.start.with_timezone(&Local).date_naive();
-        events_by_day.entry(date).or_default().push(event);
-    }
-    
-    // Display events by day
-    let mut dates: Vec<NaiveDate> = events_by_day.keys().cloned().collect();
-    dates.sort();
-    
-    for date in dates {
-        let day_events = events_by_day.get(&date).unwrap();
-        
-        // Format day header
-        let day_str = if date == today {
-            format!("{} (Today)", date.format("%A, %B %d"))
-        } else {
-            date.format("%A, %B %d").to_string()
-        };
-        
-        println!("\n{}", day_str.bright_green().bold());
-        println!("{}", "-".repeat(day_str.len()).bright_green());
-        
-        // Use the reference to the Vec directly, as it's already a Vec<&Event>
-        display_event_list(&day_events, verbose);
-    }
+    // The week reads best day by day unless another grouping was asked for
+    let group_by = options.group_by.unwrap_or(GroupBy::Day);
+    display_grouped(out, &week_events, group_by, now, options)
 }
 
-/// Displays upcoming events limited by days and count
-pub fn display_upcoming_events(events: &[Event], days: u32, limit: usize, verbose: bool) {
-    let today = Utc::now();
-    let end_date = today + Duration::days(days as i64);
+/// Displays upcoming events limited by days and count (0 for no limit)
+pub fn display_upcoming_events(
+    out: &mut dyn Write,
+    events: &[Event],
+    days: u32,
+    limit: usize,
+    options: DisplayOptions,
+) -> io::Result<()> {
+    let total_in_range = upcoming_events(events, days);
+    let filtered_events = limit_events(&total_in_range, limit);
     
-    let filtered_events: Vec<&Event> = events
-        .iter()
-        .filter(|e| e.start >= today && e.start <= end_date)
-        .take(if limit > 0 { limit } else { events.len() })
-        .collect();
+    if print_machine_readable(out, filtered_events, options)? {
+        return Ok(());
+    }
     
-    println!(
+    writeln!(
+        out,
         "{}",
         format!(
             "Upcoming Events (Next {} Days)",
@@ -126,83 +479,1093 @@ pub fn display_upcoming_events(events: &[Event], days: u32, limit: usize, verbos
         )
         .bright_blue()
         .bold()
-    );
-    println!("{}", "═".repeat(80).bright_blue());
+    )?;
+    writeln!(out, "{}", "═".repeat(80).bright_blue())?;
     
     if filtered_events.is_empty() {
-        println!("{}", "No upcoming events found in the specified time period.".yellow());
-        return;
+        writeln!(out, "{}", "No upcoming events found in the specified time period.".yellow())?;
+        return Ok(());
     }
     
-    display_event_list(&filtered_events, verbose);
+    display_listing(out, filtered_events, options)?;
     
-    if filtered_events.len() < events.len() {
-        let total_in_range: usize = events
-            .iter()
-            .filter(|e| e.start >= today && e.start <= end_date)
-            .count();
-            
-        if limit > 0 && limit < total_in_range {
-            println!(
-                "\n{}",
-                format!(
-                    "Showing {}/{} events in the next {} days. Use --limit to see more.",
-                    filtered_events.len(),
-                    total_in_range,
-                    days
-                )
-                .yellow()
-            );
+    if filtered_events.len() < total_in_range.len() {
+        writeln!(
+            out,
+            "\n{}",
+            format!(
+                "Showing {}/{} events in the next {} days. Use --limit or --all to see more.",
+                filtered_events.len(),
+                total_in_range.len(),
+                days
+            )
+            .yellow()
+        )?;
+    }
+    
+    Ok(())
+}
+
+/// Displays events starting within a date range, limited by count (0 for no limit)
+pub fn display_range_events(
+    out: &mut dyn Write,
+    events: &[Event],
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    limit: usize,
+    options: DisplayOptions,
+) -> io::Result<()> {
+    let total_in_range = range_events(events, from, to, options.timezone);
+    let filtered_events = limit_events(&total_in_range, limit);
+    
+    if print_machine_readable(out, filtered_events, options)? {
+        return Ok(());
+    }
+    
+    let range = match (from, to) {
+        (Some(from), Some(to)) => format!("{} - {}", from.format("%b %d, %Y"), to.format("%b %d, %Y")),
+        (Some(from), None) => format!("from {}", from.format("%b %d, %Y")),
+        (None, Some(to)) => format!("until {}", to.format("%b %d, %Y")),
+        (None, None) => "all dates".to_string(),
+    };
+    writeln!(out, "{}", format!("Events ({})", range).bright_blue().bold())?;
+    writeln!(out, "{}", "═".repeat(80).bright_blue())?;
+    
+    if filtered_events.is_empty() {
+        writeln!(out, "{}", "No events found in the specified date range.".yellow())?;
+        return Ok(());
+    }
+    
+    display_listing(out, filtered_events, options)?;
+    
+    if filtered_events.len() < total_in_range.len() {
+        writeln!(
+            out,
+            "\n{}",
+            format!(
+                "Showing {}/{} events in this date range. Use --limit or --all to see more.",
+                filtered_events.len(),
+                total_in_range.len()
+            )
+            .yellow()
+        )?;
+    }
+    
+    Ok(())
+}
+
+/// Widest bar drawn by `display_stats`, in characters
+const MAX_BAR_WIDTH: usize = 50;
+
+/// Displays how many events start on each day or week, as a bar chart
+///
+/// `limit` caps the number of buckets shown (0 for no limit).
+pub fn display_stats(
+    out: &mut dyn Write,
+    events: &[Event],
+    period: StatsPeriod,
+    limit: usize,
+    options: DisplayOptions,
+) -> io::Result<()> {
+    let all_counts = event_counts(events, period, options.timezone);
+    let counts = limit_events(&all_counts, limit);
+    
+    match options.format {
+        OutputFormat::Json => {
+            let buckets: Vec<_> = counts
+                .iter()
+                .map(|(date, count)| serde_json::json!({ "date": date.to_string(), "count": count }))
+                .collect();
+            serde_json::to_writer_pretty(&mut *out, &buckets)?;
+            return writeln!(out);
         }
+        OutputFormat::Plain => {
+            for (date, count) in counts {
+                writeln!(out, "{}\t{}", date, count)?;
+            }
+            return Ok(());
+        }
+        OutputFormat::Pretty => {}
+    }
+    
+    let heading = match period {
+        StatsPeriod::Day => "Events per Day",
+        StatsPeriod::Week => "Events per Week",
+    };
+    writeln!(out, "{}", heading.bright_blue().bold())?;
+    writeln!(out, "{}", "═".repeat(80).bright_blue())?;
+    
+    if counts.is_empty() {
+        writeln!(out, "{}", "No events to count.".yellow())?;
+        return Ok(());
+    }
+    
+    // Scale the bars so the busiest bucket fills the full width
+    let max = counts.iter().map(|(_, count)| *count).max().unwrap_or(1);
+    for (date, count) in counts {
+        let label = match period {
+            StatsPeriod::Day => date.format("%a, %b %d, %Y").to_string(),
+            StatsPeriod::Week => format!("Week of {}", date.format("%b %d, %Y")),
+        };
+        let width = (count * MAX_BAR_WIDTH).div_ceil(max);
+        writeln!(out, "{:<20} {:>4} {}", label, count, "#".repeat(width).green())?;
+    }
+    
+    if counts.len() < all_counts.len() {
+        writeln!(
+            out,
+            "\n{}",
+            format!(
+                "Showing {}/{} buckets. Use --limit or --all to see more.",
+                counts.len(),
+                all_counts.len()
+            )
+            .yellow()
+        )?;
+    }
+    
+    Ok(())
+}
+
+/// Prints a single event on one line, e.g. for a status bar
+///
+/// The pretty format shows the start, summary and location; JSON and plain use
+/// their usual shape.
+pub fn display_one_line(out: &mut dyn Write, event: &Event, options: DisplayOptions) -> io::Result<()> {
+    if print_machine_readable(out, &[event], options)? {
+        return Ok(());
+    }
+    
+    let start = in_zone(event.start, options.timezone);
+    let time = if event.all_day {
+        start.format("%a %b %d %Y").to_string()
+    } else {
+        start.format("%a %b %d %Y %I:%M %p").to_string()
+    };
+    let summary = event.summary.replace(['\t', '\n', '\r'], " ");
+    match &event.location {
+        Some(location) => writeln!(out, "{} {} @ {}", time, summary, location.replace(['\n', '\r'], " ")),
+        None => writeln!(out, "{} {}", time, summary),
+    }
+}
+
+/// Writes events in the JSON or plain format (or just their count with
+/// `options.count_only`), returning false for the pretty format
+fn print_machine_readable(out: &mut dyn Write, events: &[&Event], options: DisplayOptions) -> io::Result<bool> {
+    if options.count_only {
+        writeln!(out, "{}", events.len())?;
+        return Ok(true);
+    }
+    
+    match options.format {
+        OutputFormat::Pretty => Ok(false),
+        OutputFormat::Json => match options.fields {
+            Some(fields) => print_json_fields(out, events, fields).map(|_| true),
+            None => print_json(out, events).map(|_| true),
+        },
+        OutputFormat::Plain => match options.fields {
+            Some(fields) => print_plain_fields(out, events, fields).map(|_| true),
+            None => print_plain(out, events, options.timezone).map(|_| true),
+        },
     }
 }
 
+/// Writes one tab-separated `START SUMMARY` line per event, with an RFC 3339 start time
+pub fn print_plain(out: &mut dyn Write, events: &[&Event], timezone: Option<Tz>) -> io::Result<()> {
+    for event in events {
+        // Keep the line shape intact for tools like awk and cut
+        let summary = event.summary.replace(['\t', '\n', '\r'], " ");
+        writeln!(out, "{}\t{}", in_zone(event.start, timezone).to_rfc3339(), summary)?;
+    }
+    
+    Ok(())
+}
+
+/// Writes events as a JSON array
+pub fn print_json(out: &mut dyn Write, events: &[&Event]) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, events)?;
+    writeln!(out)
+}
+
+/// Writes events as a JSON array of objects holding only the selected fields
+pub fn print_json_fields(out: &mut dyn Write, events: &[&Event], fields: FieldSelection) -> io::Result<()> {
+    let selected: Vec<_> = events.iter().map(|event| fields.select(event)).collect();
+    serde_json::to_writer_pretty(&mut *out, &selected)?;
+    writeln!(out)
+}
+
+/// Writes the selected fields of each event as one tab-separated line, in
+/// `Event::FIELDS` order
+///
+/// Values are written as in the JSON format, with lists joined by commas and
+/// missing values left empty.
+pub fn print_plain_fields(out: &mut dyn Write, events: &[&Event], fields: FieldSelection) -> io::Result<()> {
+    for event in events {
+        let selected = fields.select(event);
+        let columns: Vec<String> = fields
+            .names()
+            .map(|name| plain_value(selected.get(name).unwrap_or(&serde_json::Value::Null)))
+            .collect();
+        writeln!(out, "{}", columns.join("\t"))?;
+    }
+
+    Ok(())
+}
+
+/// Renders a JSON value as a single plain column
+fn plain_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text.replace(['\t', '\n', '\r'], " "),
+        serde_json::Value::Array(items) => items.iter().map(plain_value).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+/// Displays a list of events, in sections when `options.group_by` is set
+fn display_listing(out: &mut dyn Write, events: &[&Event], options: DisplayOptions) -> io::Result<()> {
+    match options.group_by {
+        Some(group_by) if !events.is_empty() => display_grouped(out, events, group_by, Utc::now(), options),
+        _ => display_event_list(out, events, options),
+    }
+}
+
+/// Displays events under a header for each section of `group_by`
+fn display_grouped(
+    out: &mut dyn Write,
+    events: &[&Event],
+    group_by: GroupBy,
+    now: DateTime<Utc>,
+    options: DisplayOptions,
+) -> io::Result<()> {
+    for (title, group) in group_events(events, group_by, now, options.timezone) {
+        writeln!(out, "\n{}", title.bright_green().bold())?;
+        writeln!(out, "{}", "-".repeat(title.chars().count()).bright_green())?;
+        display_event_list(out, &group, options)?;
+    }
+    
+    Ok(())
+}
+
 /// Helper function to display a list of events
-fn display_event_list(events: &[&Event], verbose: bool) {
+fn display_event_list(out: &mut dyn Write, events: &[&Event], options: DisplayOptions) -> io::Result<()> {
     if events.is_empty() {
-        println!("{}", "No events to display.".yellow());
-        return;
+        writeln!(out, "{}", "No events to display.".yellow())?;
+        return Ok(());
     }
     
+    let now = Utc::now();
+    
     for event in events {
-        let local_start = event.start.with_timezone(&Local);
-        let local_end = event.end.with_timezone(&Local);
+        let local_start = in_zone(event.start, options.timezone);
+        let local_end = in_zone(event.end, options.timezone);
         
         // Format date and time
-        let date_format = local_start.format("%a, %b %d").to_string();
-        let time_format = format!(
-            "{} - {}",
-            local_start.format("%I:%M %p"),
-            local_end.format("%I:%M %p")
-        );
+        let (date_format, time_format) = if event.all_day {
+            // DTEND is exclusive for all-day events, so the last day is the day before
+            let first_day = local_start.date_naive();
+            let last_day = (local_end - Duration::days(1)).date_naive().max(first_day);
+            let date_format = if last_day > first_day {
+                format!("{} - {}", first_day.format("%a, %b %d, %Y"), last_day.format("%a, %b %d, %Y"))
+            } else {
+                first_day.format("%a, %b %d, %Y").to_string()
+            };
+            (date_format, "All day".to_string())
+        } else {
+            (
+                local_start.format("%a, %b %d, %Y").to_string(),
+                format!(
+                    "{} - {}",
+                    local_start.format("%I:%M %p"),
+                    local_end.format("%I:%M %p")
+                ),
+            )
+        };
+        
+        let relative = options.relative.then(|| format!("({})", relative_time(event.start, now)));
         
-        println!(
+        // Whatever the terminal has left after the date and time columns
+        let summary_width = options.summary_width.or_else(|| {
+            let relative_width = relative.as_ref().map_or(0, |relative| relative.chars().count() + 1);
+            let used = date_format.chars().count() + time_format.chars().count() + relative_width + " |  | ".len();
+            options.terminal_width.map(|width| width.saturating_sub(used))
+        });
+        let summary = match summary_width {
+            Some(width) => truncate_summary(&event.summary, width),
+            None => event.summary.clone(),
+        };
+        
+        let time_format = match relative {
+            Some(relative) => format!("{} {}", time_format.bright_cyan(), relative.dimmed()),
+            None => time_format.bright_cyan().to_string(),
+        };
+        
+        writeln!(
+            out,
             "{} | {} | {}",
             date_format.bright_yellow(),
-            time_format.bright_cyan(),
-            event.summary.white().bold()
-        );
+            time_format,
+            summary_by_urgency(&summary, event.start, now)
+        )?;
+        
+        // Invitations are addressed to the user, so say who sent them even without --verbose
+        if event.is_invitation() {
+            let from = event.organizer.as_deref().unwrap_or("unknown organizer");
+            writeln!(out, "  {} from {}", "Invitation".bright_magenta().bold(), from.bold())?;
+        }
         
-        if verbose {
+        if options.verbose {
             if let Some(location) = &event.location {
-                println!("  {}: {}", "Location".blue(), location);
+                writeln!(out, "  {}: {}", "Location".blue(), location)?;
+            }
+            
+            if let Some((lat, lon)) = event.geo {
+                writeln!(out, "  {}: {}, {}", "Coordinates".blue(), lat, lon)?;
+            }
+            
+            if let Some(organizer) = event.organizer.as_ref().filter(|_| !event.is_invitation()) {
+                writeln!(out, "  {}: {}", "Organizer".blue(), organizer)?;
+            }
+            
+            if !event.attendees.is_empty() {
+                writeln!(out, "  {}: {}", "Attendees".blue(), event.attendees.join(", "))?;
+            }
+            
+            if !event.categories.is_empty() {
+                writeln!(out, "  {}: {}", "Categories".blue(), event.categories.join(", "))?;
             }
             
             if let Some(url) = &event.url {
                 let clean_url = url.replace("\n", "").replace("\r", "").trim().to_string();
-                println!("  {}: {}", "URL".blue(), clean_url);
+                writeln!(out, "  {}: {}", "URL".blue(), clean_url)?;
+            }
+            
+            for link in &event.links {
+                writeln!(out, "  {}: {}", "Link".blue(), link)?;
             }
             
             if let Some(description) = &event.description {
-                // Trim and format description
-                let desc = description.trim();
+                let desc = format_description(description, options.full_description);
                 if !desc.is_empty() {
-                    println!("  {}: {}", "Description".blue(), desc);
+                    // Indent continuation lines so paragraphs stay under their event
+                    writeln!(out, "  {}: {}", "Description".blue(), desc.replace('\n', "\n    "))?;
                 }
             }
             
-            println!("  {}: {} minutes", "Duration".blue(), event.duration_minutes());
-            println!();
+            if let Some(ical_uid) = &event.ical_uid {
+                writeln!(out, "  {}: {}", "iCal UID".blue(), ical_uid)?;
+            }
+            
+            if let Some(sequence) = event.sequence {
+                writeln!(out, "  {}: {}", "Revision".blue(), sequence)?;
+            }
+            
+            if let Some(last_modified) = event.last_modified {
+                let last_modified = in_zone(last_modified, options.timezone);
+                writeln!(out, "  {}: {}", "Last modified".blue(), last_modified.format("%a, %b %d, %Y %I:%M %p"))?;
+            }
+
+            if let Some(created_at) = event.created_at {
+                let created_at = in_zone(created_at, options.timezone);
+                writeln!(out, "  {}: {}", "Added".blue(), created_at.format("%a, %b %d, %Y %I:%M %p"))?;
+            }
+
+            writeln!(out, "  {}: {}", "Duration".blue(), event.duration_human())?;
+            writeln!(out)?;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Tidies a description for display, decoding any leftover iCal escapes
+///
+/// Runs of whitespace and newlines collapse into single spaces. With `full`,
+/// paragraphs (separated by blank lines) are kept apart by a blank line;
+/// otherwise the whole description becomes one line.
+pub fn format_description(description: &str, full: bool) -> String {
+    let description = Event::unescape_ical(description).replace("\r\n", "\n");
+    let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if !full {
+        return collapse(&description);
+    }
+
+    let mut paragraphs = Vec::new();
+    let mut current = Vec::new();
+    for line in description.lines().map(str::trim) {
+        if line.is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(collapse(&current.join(" ")));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(collapse(&current.join(" ")));
+    }
+
+    paragraphs.join("\n\n")
+}
+
+/// Describes how long before `now` a past moment was, e.g. "12 min ago" or "3 days ago"
+pub fn time_ago(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = now.signed_duration_since(then).num_minutes().max(0);
+    if minutes == 0 {
+        return "just now".to_string();
+    }
+    
+    format!("{} ago", rough_amount(minutes))
+}
+
+/// Rounds a number of minutes down to whole minutes, hours or days, e.g. "2 hrs"
+fn rough_amount(minutes: i64) -> String {
+    if minutes < 60 {
+        format!("{} min", minutes)
+    } else if minutes < 60 * 24 {
+        let hours = minutes / 60;
+        format!("{} {}", hours, if hours == 1 { "hr" } else { "hrs" })
+    } else {
+        let days = minutes / (60 * 24);
+        format!("{} {}", days, if days == 1 { "day" } else { "days" })
+    }
+}
+
+/// Shortens a summary to at most `width` characters, ending it with an ellipsis
+///
+/// Counts grapheme clusters, so accented letters and emoji are never split.
+pub fn truncate_summary(summary: &str, width: usize) -> String {
+    let graphemes: Vec<&str> = summary.graphemes(true).collect();
+    if graphemes.len() <= width {
+        return summary.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    
+    format!("{}…", graphemes[..width - 1].concat())
+}
+
+/// Colors a summary by how soon the event starts: red within the hour, yellow
+/// within a day, and the terminal's default color otherwise
+fn summary_by_urgency(summary: &str, start: DateTime<Utc>, now: DateTime<Utc>) -> ColoredString {
+    let until_start = start.signed_duration_since(now);
+    let summary = summary.bold();
+
+    if until_start < Duration::zero() {
+        summary
+    } else if until_start < Duration::hours(1) {
+        summary.red()
+    } else if until_start < Duration::hours(24) {
+        summary.yellow()
+    } else {
+        summary
+    }
+}
+
+/// Describes when `start` is relative to `now`, e.g. "in 5 min", "in 2 days" or "started 1 hr ago"
+fn relative_time(start: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let delta = start.signed_duration_since(now);
+    let minutes = delta.num_minutes().abs();
+    
+    if minutes == 0 {
+        return "now".to_string();
+    }
+    
+    let amount = rough_amount(minutes);
+    if delta > Duration::zero() {
+        format!("in {}", amount)
+    } else {
+        format!("started {} ago", amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    fn event(summary: &str, start: &str, end: &str) -> Event {
+        Event::new(summary.to_string(), None, None, utc(start), utc(end), None)
+    }
+
+    /// Display options with times shown in UTC, so output doesn't depend on the host zone
+    fn utc_options() -> DisplayOptions {
+        DisplayOptions { timezone: Some(chrono_tz::UTC), ..DisplayOptions::default() }
+    }
+
+    /// Captures what `write` prints, without color escape codes
+    fn render(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut out = Vec::new();
+        write(&mut out).unwrap();
+
+        let mut text = String::new();
+        let mut chars = String::from_utf8(out).unwrap().chars().collect::<Vec<_>>().into_iter();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                text.push(c);
+            }
         }
+        text
+    }
+
+    #[test]
+    fn all_day_events_show_dates_instead_of_times() {
+        let mut conference = event("Conference", "2030-01-15T00:00:00Z", "2030-01-17T00:00:00Z");
+        conference.all_day = true;
+        let mut holiday = event("Holiday", "2030-01-18T00:00:00Z", "2030-01-19T00:00:00Z");
+        holiday.all_day = true;
+
+        let output = render(|out| display_events(out, &[conference, holiday], 0, utc_options()));
+
+        let expected = format!(
+            "Upcoming Events\n{}\n\
+             Tue, Jan 15, 2030 - Wed, Jan 16, 2030 | All day | Conference\n\
+             Fri, Jan 18, 2030 | All day | Holiday\n",
+            "═".repeat(80)
+        );
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn filter_keeps_events_matching_every_term_in_any_field() {
+        let mut rust = event("Rust Meetup", "2030-01-15T18:00:00Z", "2030-01-15T20:00:00Z");
+        rust.description = Some("Talks about async and embedded".to_string());
+        let mut go = event("Go Night", "2030-01-16T18:00:00Z", "2030-01-16T20:00:00Z");
+        go.location = Some("Rust Belt Brewery".to_string());
+        let mut embedded = event("Embedded Workshop", "2030-01-17T18:00:00Z", "2030-01-17T20:00:00Z");
+        embedded.description = Some("Bring a microcontroller".to_string());
+        let events = [rust, go, embedded];
+
+        let summaries = |terms: &[&str]| {
+            let terms: Vec<String> = terms.iter().map(|term| term.to_string()).collect();
+            filter_events(&events, &terms, &[])
+                .into_iter()
+                .map(|event| event.summary)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(summaries(&["rust"]), ["Rust Meetup", "Go Night"]);
+        assert_eq!(summaries(&["EMBEDDED"]), ["Rust Meetup", "Embedded Workshop"]);
+        assert_eq!(summaries(&["rust", "embedded"]), ["Rust Meetup"]);
+        assert_eq!(summaries(&["python"]), Vec::<String>::new());
+        assert_eq!(summaries(&[]).len(), 3);
+    }
+
+    #[test]
+    fn relative_time_rounds_down_to_minutes_hours_and_days() {
+        let now = utc("2030-01-15T12:00:00Z");
+        let at = |offset: Duration| relative_time(now + offset, now);
+
+        assert_eq!(at(Duration::seconds(30)), "now");
+        assert_eq!(at(Duration::minutes(5)), "in 5 min");
+        assert_eq!(at(Duration::minutes(59)), "in 59 min");
+        assert_eq!(at(Duration::minutes(60)), "in 1 hr");
+        assert_eq!(at(Duration::minutes(150)), "in 2 hrs");
+        assert_eq!(at(Duration::hours(23) + Duration::minutes(59)), "in 23 hrs");
+        assert_eq!(at(Duration::hours(24)), "in 1 day");
+        assert_eq!(at(Duration::days(3)), "in 3 days");
+    }
+
+    #[test]
+    fn relative_time_describes_past_starts() {
+        let now = utc("2030-01-15T12:00:00Z");
+
+        assert_eq!(relative_time(now - Duration::minutes(10), now), "started 10 min ago");
+        assert_eq!(relative_time(now - Duration::hours(1), now), "started 1 hr ago");
+        assert_eq!(relative_time(now - Duration::days(2), now), "started 2 days ago");
+    }
+
+    fn sorted(events: &[Event], key: SortKey, reverse: bool) -> Vec<String> {
+        let mut events = events.to_vec();
+        sort_events(&mut events, key, reverse);
+        events.into_iter().map(|event| event.summary).collect()
+    }
+
+    /// Three events whose start, title and duration orders all differ
+    fn sortable_events() -> Vec<Event> {
+        vec![
+            event("beta", "2030-01-16T18:00:00Z", "2030-01-16T21:00:00Z"),
+            event("Gamma", "2030-01-15T18:00:00Z", "2030-01-15T20:00:00Z"),
+            event("alpha", "2030-01-17T18:00:00Z", "2030-01-17T18:30:00Z"),
+        ]
+    }
+
+    #[test]
+    fn sort_by_start() {
+        let events = sortable_events();
+        assert_eq!(sorted(&events, SortKey::Start, false), ["Gamma", "beta", "alpha"]);
+        assert_eq!(sorted(&events, SortKey::Start, true), ["alpha", "beta", "Gamma"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sort_by_title_ignores_case() {
+        let events = sortable_events();
+        assert_eq!(sorted(&events, SortKey::Title, false), ["alpha", "beta", "Gamma"]);
+        assert_eq!(sorted(&events, SortKey::Title, true), ["Gamma", "beta", "alpha"]);
+    }
+
+    #[test]
+    fn sort_by_duration() {
+        let events = sortable_events();
+        assert_eq!(sorted(&events, SortKey::Duration, false), ["alpha", "Gamma", "beta"]);
+        assert_eq!(sorted(&events, SortKey::Duration, true), ["beta", "Gamma", "alpha"]);
+    }
+
+    #[test]
+    fn sort_ties_fall_back_to_start_time() {
+        let events = [
+            event("Same", "2030-01-16T18:00:00Z", "2030-01-16T19:00:00Z"),
+            event("same", "2030-01-15T18:00:00Z", "2030-01-15T19:00:00Z"),
+        ];
+        assert_eq!(sorted(&events, SortKey::Title, false), ["same", "Same"]);
+    }
+
+    #[test]
+    fn times_are_rendered_in_the_chosen_timezone() {
+        let meetup = event("Meetup", "2030-01-15T18:00:00Z", "2030-01-15T20:00:00Z");
+        let in_zone_named = |timezone: Tz| {
+            let options = DisplayOptions { timezone: Some(timezone), ..DisplayOptions::default() };
+            render(|out| display_events(out, std::slice::from_ref(&meetup), 0, options))
+        };
+
+        let london = in_zone_named(chrono_tz::Europe::London);
+        let tokyo = in_zone_named(chrono_tz::Asia::Tokyo);
+
+        assert!(london.contains("Tue, Jan 15, 2030 | 06:00 PM - 08:00 PM | Meetup"), "{}", london);
+        assert!(tokyo.contains("Wed, Jan 16, 2030 | 03:00 AM - 05:00 AM | Meetup"), "{}", tokyo);
+    }
+
+    #[test]
+    fn plain_format_prints_only_start_and_summary_lines() {
+        let events = [
+            event("Rust Meetup", "2030-01-15T18:00:00Z", "2030-01-15T20:00:00Z"),
+            event("Tabs\tand\nnewlines", "2030-01-16T18:00:00Z", "2030-01-16T20:00:00Z"),
+            event("Embedded Workshop", "2030-01-17T18:00:00Z", "2030-01-17T20:00:00Z"),
+        ];
+        let options = DisplayOptions { format: OutputFormat::Plain, ..utc_options() };
+
+        let output = render(|out| display_events(out, &events, 2, options));
+
+        assert_eq!(
+            output,
+            "2030-01-15T18:00:00+00:00\tRust Meetup\n\
+             2030-01-16T18:00:00+00:00\tTabs and newlines\n"
+        );
+    }
+
+    #[test]
+    fn limit_events_truncates_unless_the_limit_is_zero() {
+        let numbers: Vec<u32> = (1..=8).collect();
+
+        assert_eq!(limit_events(&numbers, 0), numbers.as_slice());
+        assert_eq!(limit_events(&numbers, 5), [1, 2, 3, 4, 5]);
+        assert_eq!(limit_events(&numbers, 20), numbers.as_slice());
+    }
+
+    fn summaries(events: Vec<&Event>) -> Vec<&str> {
+        events.into_iter().map(|event| event.summary.as_str()).collect()
+    }
+
+    #[test]
+    fn today_is_the_day_in_the_display_timezone() {
+        let new_york = Some(chrono_tz::America::New_York);
+        // 07:00 on Jan 15 in New York
+        let now = utc("2030-01-15T12:00:00Z");
+        let events = [
+            // 23:00 on Jan 14 in New York, but already Jan 15 in UTC
+            event("Late Show", "2030-01-15T04:00:00Z", "2030-01-15T05:00:00Z"),
+            // 21:00 on Jan 15 in New York, but already Jan 16 in UTC
+            event("Evening Meetup", "2030-01-16T02:00:00Z", "2030-01-16T03:00:00Z"),
+        ];
+
+        assert_eq!(summaries(today_events(&events, now, new_york)), ["Evening Meetup"]);
+        assert_eq!(summaries(today_events(&events, now, Some(chrono_tz::UTC))), ["Late Show"]);
+    }
+
+    #[test]
+    fn the_week_is_the_week_in_the_display_timezone() {
+        let new_york = Some(chrono_tz::America::New_York);
+        // 23:30 on Sunday Jan 20 in New York, already Monday Jan 21 in UTC
+        let now = utc("2030-01-21T04:30:00Z");
+        let events = [
+            event("Sunday Brunch", "2030-01-20T16:00:00Z", "2030-01-20T18:00:00Z"),
+            event("Monday Standup", "2030-01-21T15:00:00Z", "2030-01-21T15:30:00Z"),
+        ];
+
+        assert_eq!(summaries(week_events(&events, now, new_york)), ["Sunday Brunch"]);
+        assert_eq!(summaries(week_events(&events, now, Some(chrono_tz::UTC))), ["Monday Standup"]);
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn range_includes_events_on_both_boundary_days() {
+        let events = [
+            event("Before", "2030-01-14T23:59:00Z", "2030-01-15T01:00:00Z"),
+            event("First Day", "2030-01-15T00:00:00Z", "2030-01-15T01:00:00Z"),
+            event("Last Day", "2030-01-17T23:59:00Z", "2030-01-18T01:00:00Z"),
+            event("After", "2030-01-18T00:00:00Z", "2030-01-18T01:00:00Z"),
+        ];
+        let utc_zone = Some(chrono_tz::UTC);
+
+        let in_range = range_events(&events, Some(date("2030-01-15")), Some(date("2030-01-17")), utc_zone);
+        assert_eq!(summaries(in_range), ["First Day", "Last Day"]);
+
+        let from_only = range_events(&events, Some(date("2030-01-17")), None, utc_zone);
+        assert_eq!(summaries(from_only), ["Last Day", "After"]);
+
+        let to_only = range_events(&events, None, Some(date("2030-01-14")), utc_zone);
+        assert_eq!(summaries(to_only), ["Before"]);
+    }
+
+    #[test]
+    fn range_days_are_read_in_the_display_timezone() {
+        // 20:00 on Jan 17 in New York, already Jan 18 in UTC
+        let events = [event("Evening", "2030-01-18T01:00:00Z", "2030-01-18T02:00:00Z")];
+        let (from, to) = (Some(date("2030-01-15")), Some(date("2030-01-17")));
+
+        assert_eq!(summaries(range_events(&events, from, to, Some(chrono_tz::America::New_York))), ["Evening"]);
+        assert!(range_events(&events, from, to, Some(chrono_tz::UTC)).is_empty());
+    }
+
+    /// Two events on Monday Jan 14, one on Wednesday Jan 16 and one the next Monday
+    fn stats_events() -> Vec<Event> {
+        vec![
+            event("Breakfast", "2030-01-14T08:00:00Z", "2030-01-14T09:00:00Z"),
+            event("Standup", "2030-01-14T15:00:00Z", "2030-01-14T15:30:00Z"),
+            event("Meetup", "2030-01-16T18:00:00Z", "2030-01-16T20:00:00Z"),
+            event("Retro", "2030-01-21T15:00:00Z", "2030-01-21T16:00:00Z"),
+        ]
+    }
+
+    #[test]
+    fn events_are_counted_per_day_and_per_week() {
+        let events = stats_events();
+        let utc_zone = Some(chrono_tz::UTC);
+
+        assert_eq!(
+            event_counts(&events, StatsPeriod::Day, utc_zone),
+            [(date("2030-01-14"), 2), (date("2030-01-16"), 1), (date("2030-01-21"), 1)]
+        );
+        assert_eq!(
+            event_counts(&events, StatsPeriod::Week, utc_zone),
+            [(date("2030-01-14"), 3), (date("2030-01-21"), 1)]
+        );
+    }
+
+    #[test]
+    fn stats_limit_caps_the_buckets_shown() {
+        let options = DisplayOptions { format: OutputFormat::Plain, ..utc_options() };
+
+        let output = render(|out| display_stats(out, &stats_events(), StatsPeriod::Day, 2, options));
+
+        assert_eq!(output, "2030-01-14\t2\n2030-01-16\t1\n");
+    }
+
+    #[test]
+    fn category_filter_requires_every_tag_ignoring_case() {
+        let mut mixer = event("Mixer", "2030-01-15T18:00:00Z", "2030-01-15T20:00:00Z");
+        mixer.categories = vec!["Tech".to_string(), "Social".to_string()];
+        let mut talk = event("Talk", "2030-01-16T18:00:00Z", "2030-01-16T20:00:00Z");
+        talk.categories = vec!["Tech".to_string()];
+        let untagged = event("Walk", "2030-01-17T18:00:00Z", "2030-01-17T20:00:00Z");
+        let events = [mixer, talk, untagged];
+
+        let with_categories = |categories: &[&str]| {
+            let categories: Vec<String> = categories.iter().map(|category| category.to_string()).collect();
+            filter_events(&events, &[], &categories)
+                .into_iter()
+                .map(|event| event.summary)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(with_categories(&["social"]), ["Mixer"]);
+        assert_eq!(with_categories(&["TECH"]), ["Mixer", "Talk"]);
+        assert_eq!(with_categories(&["tech", "Social"]), ["Mixer"]);
+        assert!(with_categories(&["Social", "Outdoors"]).is_empty());
+    }
+
+    #[test]
+    fn next_event_is_the_soonest_strictly_after_now() {
+        let now = utc("2030-01-15T12:00:00Z");
+        let events = [
+            event("Later", "2030-01-17T18:00:00Z", "2030-01-17T20:00:00Z"),
+            event("Past", "2030-01-14T18:00:00Z", "2030-01-14T20:00:00Z"),
+            event("Starting Now", "2030-01-15T12:00:00Z", "2030-01-15T13:00:00Z"),
+            event("Soonest", "2030-01-15T12:30:00Z", "2030-01-15T13:30:00Z"),
+        ];
+
+        assert_eq!(next_event(&events, now).map(|event| event.summary.as_str()), Some("Soonest"));
+        assert!(next_event(&events[1..3], now).is_none());
+    }
+
+    #[test]
+    fn summaries_are_colored_by_how_soon_they_start() {
+        colored::control::set_override(true);
+        let now = utc("2030-01-15T12:00:00Z");
+        let colored_at = |start: &str| summary_by_urgency("Meetup", utc(start), now);
+
+        let within_the_hour = colored_at("2030-01-15T12:30:00Z");
+        assert_eq!(within_the_hour.fgcolor, Some(colored::Color::Red));
+        assert_eq!(within_the_hour.to_string(), "\x1b[1;31mMeetup\x1b[0m");
+
+        let within_the_day = colored_at("2030-01-15T20:00:00Z");
+        assert_eq!(within_the_day.fgcolor, Some(colored::Color::Yellow));
+        assert_eq!(within_the_day.to_string(), "\x1b[1;33mMeetup\x1b[0m");
+
+        for later_or_started in ["2030-01-17T12:00:00Z", "2030-01-15T11:00:00Z"] {
+            let summary = colored_at(later_or_started);
+            assert_eq!(summary.fgcolor, None);
+            assert_eq!(summary.to_string(), "\x1b[1mMeetup\x1b[0m");
+        }
+    }
+
+    fn at(summary: &str, start: &str) -> Event {
+        let start = utc(start);
+        Event::new(summary.to_string(), None, None, start, start + Duration::hours(2), None)
+    }
+
+    #[test]
+    fn near_duplicates_within_the_window_are_collapsed() {
+        let events = vec![
+            at("Rust Meetup", "2030-01-15T18:00:00Z"),
+            at("rust meetup!", "2030-01-15T18:10:00Z"),
+            at("  Rust   Meetup ", "2030-01-15T18:15:00Z"),
+            at("Go Meetup", "2030-01-15T18:05:00Z"),
+        ];
+
+        let deduped = dedupe_events(events, Duration::minutes(15));
+
+        let kept: Vec<(&str, DateTime<Utc>)> =
+            deduped.iter().map(|event| (event.summary.as_str(), event.start)).collect();
+        assert_eq!(
+            kept,
+            [("Rust Meetup", utc("2030-01-15T18:00:00Z")), ("Go Meetup", utc("2030-01-15T18:05:00Z"))]
+        );
+    }
+
+    #[test]
+    fn events_just_outside_the_window_are_kept() {
+        let events = vec![
+            at("Rust Meetup", "2030-01-15T18:00:00Z"),
+            at("Rust Meetup", "2030-01-15T18:16:00Z"),
+            at("Rust Meetup", "2030-01-15T17:44:00Z"),
+        ];
+
+        assert_eq!(dedupe_events(events, Duration::minutes(15)).len(), 3);
+    }
+
+    const MULTI_PARAGRAPH: &str = "Join us for talks\nand   pizza.\r\n\r\n\r\n  Doors open at 6pm,\n\tfirst talk at 6:30.  \n\nBring a laptop!\n";
+
+    #[test]
+    fn descriptions_collapse_to_one_line_by_default() {
+        assert_eq!(
+            format_description(MULTI_PARAGRAPH, false),
+            "Join us for talks and pizza. Doors open at 6pm, first talk at 6:30. Bring a laptop!"
+        );
+        assert_eq!(format_description(r"Drinks\, snacks\nand talks", false), "Drinks, snacks and talks");
+    }
+
+    #[test]
+    fn full_descriptions_keep_their_paragraphs() {
+        assert_eq!(
+            format_description(MULTI_PARAGRAPH, true),
+            "Join us for talks and pizza.\n\nDoors open at 6pm, first talk at 6:30.\n\nBring a laptop!"
+        );
+        assert_eq!(format_description(r"First\n\nSecond\, still", true), "First\n\nSecond, still");
+    }
+
+    fn lasting(summary: &str, minutes: i64) -> Event {
+        let start = utc("2030-01-15T18:00:00Z");
+        Event::new(summary.to_string(), None, None, start, start + Duration::minutes(minutes), None)
+    }
+
+    #[test]
+    fn duration_bounds_are_inclusive_and_zero_means_unbounded() {
+        let events = || {
+            vec![
+                lasting("Lightning Talk", 15),
+                lasting("Meetup", 60),
+                lasting("Workshop", 180),
+                lasting("Hackathon", 24 * 60),
+            ]
+        };
+        let kept = |min, max| -> Vec<String> {
+            filter_by_duration(events(), min, max).into_iter().map(|event| event.summary).collect()
+        };
+
+        assert_eq!(kept(0, 0), ["Lightning Talk", "Meetup", "Workshop", "Hackathon"]);
+        assert_eq!(kept(60, 0), ["Meetup", "Workshop", "Hackathon"]);
+        assert_eq!(kept(0, 180), ["Lightning Talk", "Meetup", "Workshop"]);
+        assert_eq!(kept(16, 179), ["Meetup"]);
+        assert!(kept(200, 1000).is_empty());
+    }
+
+    #[test]
+    fn ascii_summaries_are_cut_to_the_width() {
+        assert_eq!(truncate_summary("Rust Meetup", 20), "Rust Meetup");
+        assert_eq!(truncate_summary("Rust Meetup", 11), "Rust Meetup");
+        assert_eq!(truncate_summary("Rust Meetup", 8), "Rust Me…");
+        assert_eq!(truncate_summary("Rust Meetup", 1), "…");
+        assert_eq!(truncate_summary("Rust Meetup", 0), "");
+    }
+
+    #[test]
+    fn emoji_summaries_are_cut_between_graphemes() {
+        let summary = "🦀 Rust 👩‍💻 Night 🎉";
+        assert_eq!(truncate_summary(summary, 18), summary);
+        assert_eq!(truncate_summary(summary, 9), "🦀 Rust 👩‍💻…");
+        assert_eq!(truncate_summary(summary, 8), "🦀 Rust …");
+        assert_eq!(truncate_summary("Café Crème", 4), "Caf…");
+        assert_eq!(truncate_summary("Cafe\u{301} Cre\u{300}me", 5), "Cafe\u{301}…");
+    }
+
+    /// A reader that goes away (like `head`) after taking `remaining` bytes
+    struct ClosingReader {
+        remaining: usize,
+    }
+
+    impl Write for ClosingReader {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.remaining == 0 {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+            }
+            let taken = buf.len().min(self.remaining);
+            self.remaining -= taken;
+            Ok(taken)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_closed_reader_is_reported_as_a_broken_pipe() {
+        let events: Vec<Event> = (0..20)
+            .map(|day| {
+                let start = utc("2030-01-01T18:00:00Z") + Duration::days(day);
+                Event::new(format!("Meetup {}", day), None, None, start, start + Duration::hours(2), None)
+            })
+            .collect();
+
+        for format in [OutputFormat::Pretty, OutputFormat::Json, OutputFormat::Plain] {
+            let options = DisplayOptions { format, ..utc_options() };
+            let mut out = ClosingReader { remaining: 100 };
+            let error = display_events(&mut out, &events, 0, options).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::BrokenPipe, "{:?}", format);
+
+            let mut out = ClosingReader { remaining: 100 };
+            let error = display_stats(&mut out, &events, StatsPeriod::Day, 0, options).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::BrokenPipe, "{:?}", format);
+        }
+    }
+
+    fn groupable_events() -> Vec<Event> {
+        let mut hall = event("Rust Meetup", "2030-01-15T18:00:00Z", "2030-01-15T20:00:00Z");
+        hall.location = Some("Community Hall".to_string());
+        let nowhere = event("Hack Night", "2030-01-15T20:00:00Z", "2030-01-15T23:00:00Z");
+        let mut blank = event("Book Club", "2030-01-20T18:00:00Z", "2030-01-20T19:00:00Z");
+        blank.location = Some("  ".to_string());
+        let mut brewery = event("Go Meetup", "2030-01-21T18:00:00Z", "2030-01-21T20:00:00Z");
+        brewery.location = Some("Brewery".to_string());
+        vec![hall, nowhere, blank, brewery]
+    }
+
+    fn grouped(events: &[Event], group_by: GroupBy) -> Vec<(String, Vec<&str>)> {
+        let refs: Vec<&Event> = events.iter().collect();
+        group_events(&refs, group_by, utc("2030-01-15T12:00:00Z"), Some(chrono_tz::UTC))
+            .into_iter()
+            .map(|(title, events)| (title, summaries(events)))
+            .collect()
+    }
+
+    #[test]
+    fn groups_by_day_mark_today() {
+        let events = groupable_events();
+        assert_eq!(
+            grouped(&events, GroupBy::Day),
+            [
+                ("Tuesday, January 15, 2030 (Today)".to_string(), vec!["Rust Meetup", "Hack Night"]),
+                ("Sunday, January 20, 2030".to_string(), vec!["Book Club"]),
+                ("Monday, January 21, 2030".to_string(), vec!["Go Meetup"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn groups_by_week_start_on_monday() {
+        let events = groupable_events();
+        assert_eq!(
+            grouped(&events, GroupBy::Week),
+            [
+                (
+                    "Week of Monday, January 14, 2030 (This Week)".to_string(),
+                    vec!["Rust Meetup", "Hack Night", "Book Club"]
+                ),
+                ("Week of Monday, January 21, 2030".to_string(), vec!["Go Meetup"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn groups_by_location_put_unspecified_last() {
+        let events = groupable_events();
+        assert_eq!(
+            grouped(&events, GroupBy::Location),
+            [
+                ("Brewery".to_string(), vec!["Go Meetup"]),
+                ("Community Hall".to_string(), vec!["Rust Meetup"]),
+                ("Unspecified".to_string(), vec!["Hack Night", "Book Club"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn selected_fields_are_the_only_json_keys() {
+        let mut meetup = event("Rust Meetup", "2030-01-15T18:00:00Z", "2030-01-15T20:00:00Z");
+        meetup.url = Some("https://lu.ma/rust-meetup".to_string());
+        let fields = FieldSelection::parse("url, summary,start").unwrap();
+        let options = DisplayOptions { format: OutputFormat::Json, fields: Some(fields), ..utc_options() };
+
+        let mut out = Vec::new();
+        display_events(&mut out, &[meetup], 0, options).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let keys: Vec<&String> = json[0].as_object().unwrap().keys().collect();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(json[0]["summary"], "Rust Meetup");
+        assert_eq!(json[0]["start"], "2030-01-15T18:00:00Z");
+        assert_eq!(json[0]["url"], "https://lu.ma/rust-meetup");
+    }
+
+    #[test]
+    fn selected_fields_are_the_only_plain_columns() {
+        let mut meetup = event("Rust\tMeetup", "2030-01-15T18:00:00Z", "2030-01-15T20:00:00Z");
+        meetup.categories = vec!["Tech".to_string(), "Social".to_string()];
+        let fields = FieldSelection::parse("categories,summary,location").unwrap();
+        let options = DisplayOptions { format: OutputFormat::Plain, fields: Some(fields), ..utc_options() };
+
+        let mut out = Vec::new();
+        display_events(&mut out, &[meetup], 0, options).unwrap();
+
+        // Columns follow Event::FIELDS order, not the order they were asked for
+        assert_eq!(String::from_utf8(out).unwrap(), "Rust Meetup\t\tTech,Social\n");
+    }
+
+    #[test]
+    fn field_selections_reject_unknown_or_missing_names() {
+        assert!(FieldSelection::parse("summary,colour").unwrap_err().contains("unknown field 'colour'"));
+        assert!(FieldSelection::parse(" , ").unwrap_err().contains("at least one field"));
+    }
+}
@@ -1,10 +1,20 @@
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum CalendarError {
     #[error("Failed to fetch calendar: {0}")]
     FetchError(#[from] reqwest::Error),
     
+    #[error("Failed to fetch calendar: HTTP {status}{}", body_suffix(.body))]
+    HttpStatus { status: u16, body: String },
+    
+    #[error("Invalid calendar URL {0}")]
+    InvalidUrl(String),
+    
+    #[error("Not a calendar: {0}")]
+    NotCalendar(String),
+    
     #[error("Failed to parse calendar: {0}")]
     ParseError(String),
     
@@ -14,15 +24,81 @@ pub enum CalendarError {
     #[error("Database error: {0}")]
     DatabaseError(#[from] tokio_postgres::Error),
     
-    #[error("Error loading environment variable: {0}")]
-    #[allow(dead_code)]
+    #[error("Database error: {0}")]
+    StorageError(#[from] DatabaseError),
+    
+    #[error("Configuration error: {0}")]
     EnvError(String),
     
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
+    
+    #[error("No upcoming events")]
+    NoUpcomingEvents,
+    
+    #[error("Interrupted")]
+    Interrupted,
+}
+
+/// Maps an error to the process exit code, so scripts can tell failures apart
+///
+/// - 1: other errors (e.g. I/O)
+/// - 2: fetching the calendar failed
+/// - 3: the calendar, a time or an API response could not be parsed (or the URL
+///   served something other than a calendar)
+/// - 4: database error
+/// - 5: missing or invalid environment/configuration (e.g. no API key or a bad URL)
+/// - 6: `next-one` found no upcoming event
+/// - 130: interrupted with Ctrl-C (128 + SIGINT, as shells report it)
+pub fn exit_code(error: &CalendarError) -> i32 {
+    match error {
+        CalendarError::FetchError(_) | CalendarError::HttpStatus { .. } => 2,
+        CalendarError::ParseError(_)
+        | CalendarError::TimeConversionError(_)
+        | CalendarError::NotCalendar(_) => 3,
+        CalendarError::DatabaseError(_) => 4,
+        CalendarError::StorageError(DatabaseError::EnvError(_)) => 5,
+        CalendarError::StorageError(_) => 4,
+        CalendarError::EnvError(_) | CalendarError::InvalidUrl(_) => 5,
+        CalendarError::IoError(_) => 1,
+        CalendarError::NoUpcomingEvents => 6,
+        CalendarError::Interrupted => 130,
+    }
+}
+
+/// Names the kind of error for machine-readable output, e.g. `fetch` or `database`
+///
+/// Kinds are coarser than the variants and line up with the exit codes, so
+/// scripts can branch on them without knowing the error types.
+pub fn error_kind(error: &CalendarError) -> &'static str {
+    match error {
+        CalendarError::FetchError(_) => "fetch",
+        CalendarError::HttpStatus { .. } => "http_status",
+        CalendarError::InvalidUrl(_) => "invalid_url",
+        CalendarError::NotCalendar(_) => "not_calendar",
+        CalendarError::ParseError(_) => "parse",
+        CalendarError::TimeConversionError(_) => "time_conversion",
+        CalendarError::DatabaseError(_) => "database",
+        CalendarError::StorageError(DatabaseError::EnvError(_)) => "config",
+        CalendarError::StorageError(_) => "database",
+        CalendarError::EnvError(_) => "config",
+        CalendarError::IoError(_) => "io",
+        CalendarError::NoUpcomingEvents => "no_upcoming_events",
+        CalendarError::Interrupted => "interrupted",
+    }
+}
+
+/// Appends the server's explanation to an HTTP status, when it sent one
+fn body_suffix(body: &str) -> String {
+    if body.is_empty() {
+        String::new()
+    } else {
+        format!(" - {}", body)
+    }
 }
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum DatabaseError {
     #[error("Connection error: {0}")]
     ConnectionError(String),
@@ -30,11 +106,53 @@ pub enum DatabaseError {
     #[error("Query error: {0}")]
     QueryError(#[from] tokio_postgres::Error),
     
+    #[error("SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+    
     #[error("Error loading environment variable: {0}")]
     #[allow(dead_code)]
     EnvError(String),
     
     #[error("Data conversion error: {0}")]
-    #[allow(dead_code)]
     DataConversionError(String),
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch_error() -> reqwest::Error {
+        reqwest::Client::new().get("not a url").build().unwrap_err()
+    }
+
+    fn postgres_error() -> tokio_postgres::Error {
+        "port=not-a-number".parse::<tokio_postgres::Config>().unwrap_err()
+    }
+
+    fn text() -> String {
+        "details".to_string()
+    }
+
+    #[test]
+    fn each_error_maps_to_its_exit_code() {
+        let cases = [
+            (CalendarError::FetchError(fetch_error()), 2),
+            (CalendarError::HttpStatus { status: 404, body: text() }, 2),
+            (CalendarError::ParseError(text()), 3),
+            (CalendarError::TimeConversionError(text()), 3),
+            (CalendarError::NotCalendar(text()), 3),
+            (CalendarError::DatabaseError(postgres_error()), 4),
+            (CalendarError::StorageError(DatabaseError::ConnectionError(text())), 4),
+            (CalendarError::StorageError(DatabaseError::DataConversionError(text())), 4),
+            (CalendarError::StorageError(DatabaseError::EnvError(text())), 5),
+            (CalendarError::EnvError(text()), 5),
+            (CalendarError::InvalidUrl(text()), 5),
+            (CalendarError::IoError(std::io::Error::other(text())), 1),
+            (CalendarError::NoUpcomingEvents, 6),
+            (CalendarError::Interrupted, 130),
+        ];
+
+        for (error, code) in cases {
+            assert_eq!(exit_code(&error), code, "{:?}", error);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::models::Event;
+use chrono::{DateTime, Local, Utc};
+use std::io::{self, Write};
+
+/// Maximum length of a content line in octets, excluding the line break (RFC 5545 §3.1)
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Writes events as a pretty-printed JSON array of [`Event`]s, which reads back
+/// with `serde_json::from_reader::<_, Vec<Event>>`
+pub fn write_json<W: Write>(events: &[&Event], out: &mut W) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, events).map_err(io::Error::other)?;
+    writeln!(out)
+}
+
+/// Writes events as a VCALENDAR with one VEVENT each
+pub fn write_ics<W: Write>(events: &[&Event], out: &mut W) -> io::Result<()> {
+    let stamp = format_utc(&Utc::now());
+
+    write_line(out, "BEGIN:VCALENDAR")?;
+    write_line(out, "VERSION:2.0")?;
+    write_line(out, &format!("PRODID:-//lumabot//Luma Calendar CLI {}//EN", env!("CARGO_PKG_VERSION")))?;
+    write_line(out, "CALSCALE:GREGORIAN")?;
+    write_line(out, "METHOD:PUBLISH")?;
+
+    for event in events {
+        write_line(out, "BEGIN:VEVENT")?;
+        // A modified instance shares the UID of its series and is told apart by
+        // its RECURRENCE-ID, as in the feed it came from
+        let uid = event.ical_uid.as_deref().unwrap_or(&event.event_uid);
+        write_line(out, &format!("UID:{}", escape_text(uid)))?;
+        if let Some(recurrence_id) = &event.recurrence_id {
+            write_line(out, &format!("RECURRENCE-ID:{}", recurrence_id))?;
+        }
+        write_line(out, &format!("DTSTAMP:{}", stamp))?;
+
+        if event.all_day {
+            // All-day events keep their local calendar dates
+            write_line(out, &format!("DTSTART;VALUE=DATE:{}", format_date(&event.start)))?;
+            write_line(out, &format!("DTEND;VALUE=DATE:{}", format_date(&event.end)))?;
+        } else {
+            write_line(out, &format!("DTSTART:{}", format_utc(&event.start)))?;
+            // Export the feed's own end rather than one padded for display
+            let end = event.original_end.unwrap_or(event.end);
+            write_line(out, &format!("DTEND:{}", format_utc(&end)))?;
+        }
+
+        write_line(out, &format!("SUMMARY:{}", escape_text(&event.summary)))?;
+
+        if let Some(location) = &event.location {
+            write_line(out, &format!("LOCATION:{}", escape_text(location)))?;
+        }
+
+        if let Some((lat, lon)) = event.geo {
+            write_line(out, &format!("GEO:{};{}", lat, lon))?;
+        }
+
+        if let Some(description) = &event.description {
+            write_line(out, &format!("DESCRIPTION:{}", escape_text(description)))?;
+        }
+
+        if let Some(url) = &event.url {
+            write_line(out, &format!("URL:{}", Event::clean_string(url)))?;
+        }
+
+        if !event.categories.is_empty() {
+            let categories: Vec<String> = event.categories.iter().map(|c| escape_text(c)).collect();
+            write_line(out, &format!("CATEGORIES:{}", categories.join(",")))?;
+        }
+
+        write_line(out, &format!("STATUS:{}", event.status.as_ical()))?;
+
+        if let Some(sequence) = event.sequence {
+            write_line(out, &format!("SEQUENCE:{}", sequence))?;
+        }
+
+        if let Some(last_modified) = &event.last_modified {
+            write_line(out, &format!("LAST-MODIFIED:{}", format_utc(last_modified)))?;
+        }
+
+        write_line(out, "END:VEVENT")?;
+    }
+
+    write_line(out, "END:VCALENDAR")?;
+    out.flush()
+}
+
+/// Formats a timestamp as an iCal UTC datetime (YYYYMMDDTHHMMSSZ)
+fn format_utc(dt: &DateTime<Utc>) -> String {
+    dt.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Formats a timestamp as an iCal date (YYYYMMDD) in the local zone
+fn format_date(dt: &DateTime<Utc>) -> String {
+    dt.with_timezone(&Local).format("%Y%m%d").to_string()
+}
+
+/// Escapes an iCal TEXT value (the inverse of `Event::unescape_ical`)
+fn escape_text(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Writes a content line, folding it at 75 octets with CRLF line breaks
+fn write_line<W: Write>(out: &mut W, line: &str) -> io::Result<()> {
+    let mut remaining = line;
+    let mut limit = MAX_LINE_OCTETS;
+
+    while remaining.len() > limit {
+        // Never split inside a multi-byte character
+        let mut split = limit;
+        while !remaining.is_char_boundary(split) {
+            split -= 1;
+        }
+
+        out.write_all(&remaining.as_bytes()[..split])?;
+        out.write_all(b"\r\n ")?;
+        remaining = &remaining[split..];
+
+        // Continuation lines start with a space, which counts toward the limit
+        limit = MAX_LINE_OCTETS - 1;
+    }
+
+    out.write_all(remaining.as_bytes())?;
+    out.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::{parse_calendar_from_reader, ParseOptions};
+    use chrono::TimeZone;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    fn round_trip(events: &[&Event]) -> Vec<Event> {
+        let mut ics = Vec::new();
+        write_ics(events, &mut ics).unwrap();
+        parse_calendar_from_reader(ics.as_slice(), ParseOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn exported_events_parse_back() {
+        let mut meetup = Event::new(
+            "Rust Meetup; talks, demos & drinks".to_string(),
+            Some("First line\nSecond line with a \\ backslash and a very long tail that needs folding across lines".to_string()),
+            Some("Community Hall, Room 2".to_string()),
+            utc("2030-01-15T18:00:00Z"),
+            utc("2030-01-15T20:30:00Z"),
+            Some("https://lu.ma/rust-meetup".to_string()),
+        );
+        meetup.categories = vec!["Tech".to_string(), "Social, Drinks".to_string()];
+        meetup.geo = Some((52.52, 13.405));
+        meetup.sequence = Some(3);
+        meetup.last_modified = Some(utc("2030-01-01T12:00:00Z"));
+
+        let parsed = round_trip(&[&meetup]);
+
+        assert_eq!(parsed.len(), 1);
+        let parsed = &parsed[0];
+        assert_eq!(parsed.summary, meetup.summary);
+        assert_eq!(parsed.description, meetup.description);
+        assert_eq!(parsed.location, meetup.location);
+        assert_eq!(parsed.start, meetup.start);
+        assert_eq!(parsed.end, meetup.end);
+        assert_eq!(parsed.url, meetup.url);
+        assert_eq!(parsed.categories, meetup.categories);
+        assert_eq!(parsed.geo, meetup.geo);
+        assert_eq!(parsed.sequence, meetup.sequence);
+        assert_eq!(parsed.last_modified, meetup.last_modified);
+        // The exported UID becomes the feed UID of the re-parsed event
+        assert_eq!(parsed.event_uid, meetup.event_uid);
+    }
+
+    #[test]
+    fn exported_all_day_events_stay_all_day() {
+        let start = Local.with_ymd_and_hms(2030, 1, 15, 0, 0, 0).unwrap().with_timezone(&Utc);
+        let mut conference = Event::new(
+            "Conference".to_string(),
+            None,
+            None,
+            start,
+            start + chrono::Duration::days(2),
+            None,
+        );
+        conference.all_day = true;
+
+        let parsed = round_trip(&[&conference]);
+
+        assert!(parsed[0].all_day);
+        assert_eq!(parsed[0].start, conference.start);
+        assert_eq!(parsed[0].end, conference.end);
+    }
+
+    #[test]
+    fn modified_instances_export_the_series_uid_and_recurrence_id() {
+        let mut instance = Event::new(
+            "Office Hours (rescheduled)".to_string(),
+            None,
+            None,
+            utc("2030-01-23T18:00:00Z"),
+            utc("2030-01-23T19:00:00Z"),
+            None,
+        );
+        instance.set_ical_uid("office-hours@lu.ma", Some("20300122T180000Z"));
+
+        let mut ics = Vec::new();
+        write_ics(&[&instance], &mut ics).unwrap();
+        let ics = String::from_utf8(ics).unwrap();
+        assert!(ics.contains("UID:office-hours@lu.ma\r\nRECURRENCE-ID:20300122T180000Z\r\n"), "{}", ics);
+
+        let parsed = round_trip(&[&instance]);
+        assert_eq!(parsed[0].event_uid, instance.event_uid);
+        assert_eq!(parsed[0].recurrence_id, instance.recurrence_id);
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        let mut out = Vec::new();
+        write_line(&mut out, &format!("DESCRIPTION:{}", "é".repeat(100))).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert_eq!(text.replace("\r\n ", ""), format!("DESCRIPTION:{}\r\n", "é".repeat(100)));
+    }
+}
@@ -0,0 +1,41 @@
+//! Fetch, parse and store Luma calendar events
+//!
+//! The `luma-calendar-cli` binary is a thin wrapper around this crate. The
+//! main entry points are re-exported at the top level:
+//!
+//! ```no_run
+//! use luma_calendar_cli::{fetch_and_parse_calendar, FetchOptions, ParseOptions};
+//!
+//! let events = fetch_and_parse_calendar(
+//!     "https://api.lu.ma/ics/get?entity=calendar&id=cal-4dWxlBFjW9Cd6ou",
+//!     ParseOptions::default(),
+//!     &FetchOptions::default(),
+//! )?;
+//! for event in &events {
+//!     println!("{} {}", event.start, event.summary);
+//! }
+//! # Ok::<(), luma_calendar_cli::CalendarError>(())
+//! ```
+
+pub mod api;
+mod cache;
+pub mod calendar;
+pub mod config;
+pub mod database;
+pub mod display;
+pub mod errors;
+pub mod export;
+pub mod models;
+pub mod progress;
+pub mod remind;
+pub mod sync;
+#[cfg(feature = "tui")]
+pub mod tui;
+
+pub use api::{AddEventOptions, AddEventResponse, EventDetails, LumaApi, RegistrationStatus};
+pub use calendar::{
+    fetch_and_parse_calendar, normalize_calendar_url, parse_calendar_file, parse_calendar_from_reader, FetchOptions,
+    ParseOptions,
+};
+pub use errors::{CalendarError, DatabaseError};
+pub use models::{CalendarMethod, Event, EventStatus};
@@ -1,17 +1,32 @@
-mod api;
-mod calendar;
-mod database;
-mod display;
-mod errors;
-mod models;
-
-use clap::{Parser, Subcommand};
+use luma_calendar_cli::{api, calendar, config, database, display, errors, export, models, remind, sync};
+#[cfg(feature = "tui")]
+use luma_calendar_cli::tui;
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use calendar::{FetchOptions, ParseOptions};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use config::Config;
 use colored::Colorize;
+use display::{DisplayOptions, FieldSelection, GroupBy, OutputFormat, SortKey, StatsPeriod};
 use errors::CalendarError;
-use tokio::runtime::Runtime;
+use tokio::runtime::{Handle, Runtime};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
 use api::LumaApi;
 
-use std::{process, time::Instant};
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Shortest allowed --watch interval, to avoid hammering the calendar server
+const MIN_WATCH_SECS: u64 = 10;
 
 // Define the CLI arguments
 #[derive(Parser, Debug)]
@@ -21,16 +36,76 @@ struct Cli {
     command: Option<Commands>,
 
     /// URL of the calendar to fetch
-    #[clap(short, long, default_value = "https://api.lu.ma/ics/get?entity=calendar&id=cal-4dWxlBFjW9Cd6ou")]
+    #[clap(short, long, visible_alias = "ics-url", default_value = "https://api.lu.ma/ics/get?entity=calendar&id=cal-4dWxlBFjW9Cd6ou")]
     url: String,
 
-    /// Limit the number of events displayed
+    /// Fetch the Luma calendar with this ID (e.g. cal-4dWxlBFjW9Cd6ou) instead of --url
+    #[clap(long, value_name = "ID", value_parser = parse_calendar_id)]
+    calendar: Option<String>,
+
+    /// Read the calendar from a local .ics file instead of the URL (`-` for stdin)
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["url", "calendar"])]
+    file: Option<PathBuf>,
+
+    /// List events stored in the database instead of fetching the calendar
+    #[clap(long, conflicts_with_all = ["file", "store"])]
+    offline: bool,
+
+    /// Always download the calendar instead of revalidating the cached copy
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Keep cached calendars in this directory instead of the user cache directory
+    #[clap(long, value_name = "PATH", conflicts_with = "no_cache")]
+    cache_dir: Option<PathBuf>,
+
+    /// Seconds before a calendar fetch times out
+    #[clap(long, value_name = "SECONDS", default_value_t = calendar::DEFAULT_FETCH_TIMEOUT.as_secs())]
+    fetch_timeout: u64,
+
+    /// Times to retry a calendar fetch after a connection error or timeout
+    #[clap(long, value_name = "N", default_value_t = calendar::DEFAULT_FETCH_RETRIES)]
+    fetch_retries: u32,
+
+    /// User-Agent sent when fetching the calendar
+    #[clap(long, env = "LUMABOT_USER_AGENT", value_name = "STRING")]
+    user_agent: Option<String>,
+
+    /// Token for private calendar feeds, sent as 'Authorization: Bearer <TOKEN>'
+    #[clap(long, env = "LUMABOT_FEED_TOKEN", hide_env_values = true, value_name = "TOKEN")]
+    auth_token: Option<String>,
+
+    /// Extra request header for fetching the calendar, as 'Name: Value' (repeatable)
+    #[clap(long = "header", value_name = "HEADER", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Limit the number of events displayed (0 for no limit)
     #[clap(short, long, default_value_t = 10)]
     limit: usize,
 
-    /// Show detailed information about events
+    /// Show every event, ignoring --limit
+    #[clap(long, conflicts_with = "limit")]
+    all: bool,
+
+    /// Print only the number of events the listing would show (ignoring --limit)
+    #[clap(long)]
+    count_only: bool,
+
+    /// Show detailed information about events; repeat (-vv) to also log each API request
+    #[clap(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Truncate event titles to this many characters (defaults to fitting the terminal width)
+    #[clap(long, value_name = "N")]
+    summary_width: Option<usize>,
+
+    /// Keep the paragraph breaks of descriptions in verbose listings
+    #[clap(long)]
+    full_description: bool,
+
+    /// Only log warnings and errors (overrides RUST_LOG)
     #[clap(short, long)]
-    verbose: bool,
+    quiet: bool,
 
     /// Store events in the database
     #[clap(short, long)]
@@ -39,6 +114,110 @@ struct Cli {
     /// Auto-enrich events with API IDs while storing
     #[clap(short = 'e', long)]
     enrich: bool,
+
+    /// Include events whose STATUS is CANCELLED
+    #[clap(long)]
+    include_cancelled: bool,
+
+    /// Fail on the first malformed event instead of skipping it with a warning
+    #[clap(long)]
+    strict: bool,
+
+    /// Length in minutes given to timed events without a duration (0 keeps them zero-length)
+    #[clap(long, value_name = "MIN", default_value_t = calendar::DEFAULT_EVENT_DURATION_MINUTES)]
+    default_duration: u32,
+
+    /// Drop events that ended more than this many days ago
+    #[clap(long, value_name = "DAYS", default_value_t = calendar::DEFAULT_RETENTION_DAYS)]
+    retention_days: u32,
+
+    /// Read defaults from this TOML file instead of ~/.config/lumabot/config.toml
+    #[clap(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Never create or migrate the database schema on connect, not even on a first run (use `db-init`)
+    #[clap(long, env = "LUMABOT_NO_DB_INIT")]
+    no_db_init: bool,
+
+    /// Skip TLS certificate verification for the database connection (also LUMABOT_INSECURE_TLS=1)
+    #[clap(long)]
+    insecure: bool,
+
+    /// Delay between Luma API requests in milliseconds
+    #[clap(long, env = "LUMA_RATE_LIMIT_MS", default_value_t = api::DEFAULT_RATE_LIMIT_MS)]
+    rate_limit: u64,
+
+    /// Number of times to retry Luma API requests that fail with 429 or 5xx
+    #[clap(long, default_value_t = api::DEFAULT_MAX_RETRIES)]
+    max_retries: u32,
+
+    /// Only list events whose summary, description or location contains TEXT (repeat to AND)
+    #[clap(long = "filter", value_name = "TEXT")]
+    filters: Vec<String>,
+
+    /// Only list events tagged with this CATEGORIES value, case-insensitive (repeat to AND)
+    #[clap(long = "category", value_name = "TAG")]
+    categories: Vec<String>,
+
+    /// Only list events lasting at least this many minutes (0 for no minimum)
+    #[clap(long, value_name = "MIN", default_value_t = 0)]
+    min_duration: u32,
+
+    /// Only list events lasting at most this many minutes, e.g. to hide multi-day events (0 for no maximum)
+    #[clap(long, value_name = "MIN", default_value_t = 0)]
+    max_duration: u32,
+
+    /// Collapse events with the same title starting within this many minutes of each other
+    #[clap(long, value_name = "MIN")]
+    dedupe_window: Option<u32>,
+
+    /// Output format for event listings
+    #[clap(long, value_enum, default_value_t = OutputFormat::Pretty)]
+    format: OutputFormat,
+
+    /// Disable colored output (also set by the NO_COLOR environment variable)
+    #[clap(long)]
+    no_color: bool,
+
+    /// Write listings and exports to this file (truncating it) instead of stdout, without colors
+    #[clap(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Split listings into sections by day, week or location
+    #[clap(long, value_enum, value_name = "KEY")]
+    group_by: Option<GroupBy>,
+
+    /// Only include these comma-separated fields in json and plain output, e.g. summary,start,url
+    #[clap(long, value_name = "LIST", value_parser = FieldSelection::parse)]
+    fields: Option<FieldSelection>,
+
+    /// Order listings by start time, title or duration
+    #[clap(long, value_enum, default_value_t = SortKey::Start)]
+    sort: SortKey,
+
+    /// Reverse the listing order (e.g. newest first)
+    #[clap(long)]
+    reverse: bool,
+
+    /// Show times, and decide what "today" and "this week" mean, in this IANA zone (e.g. Europe/Berlin) instead of the local zone
+    #[clap(long, value_name = "TZ")]
+    timezone: Option<Tz>,
+
+    /// Re-fetch and re-render every SECONDS (minimum 10) until interrupted
+    #[clap(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(MIN_WATCH_SECS..))]
+    watch: Option<u64>,
+
+    /// Show how far away each event is ("in 2 days") next to its time
+    #[clap(long)]
+    relative: bool,
+
+    /// Only list events starting on or after this day (YYYY-MM-DD, in the display zone)
+    #[clap(long, value_name = "DATE")]
+    from: Option<NaiveDate>,
+
+    /// Only list events starting on or before this day (YYYY-MM-DD, in the display zone)
+    #[clap(long, value_name = "DATE")]
+    to: Option<NaiveDate>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -57,6 +236,18 @@ enum Commands {
         days: u32,
     },
 
+    /// Print the next upcoming event on one line (exits with 6 when there is none)
+    #[clap(name = "next-one")]
+    NextOne,
+
+    /// Show how many events start on each day (or week), as a bar chart
+    #[clap(name = "stats")]
+    Stats {
+        /// Count per day or per week
+        #[clap(long, value_enum, default_value_t = StatsPeriod::Day)]
+        by: StatsPeriod,
+    },
+
     /// Show events from the database
     #[clap(name = "db")]
     Database {
@@ -64,19 +255,78 @@ enum Commands {
         #[clap(long)]
         all: bool,
         
-        /// Limit the number of events displayed
+        /// Limit the number of events displayed (0 for no limit)
         #[clap(short, long, default_value_t = 10)]
         limit: usize,
         
+        /// Skip this many events before the first one displayed, to page with --limit
+        #[clap(long, default_value_t = 0)]
+        offset: usize,
+        
+        /// Show the number of stored events and when they were last synced
+        #[clap(long, conflicts_with_all = ["all", "added_since"])]
+        status: bool,
+        
+        /// Only list events first stored within this window, e.g. 30m, 12h, 7d or 2w
+        #[clap(long, value_name = "DURATION", value_parser = parse_age)]
+        added_since: Option<chrono::Duration>,
+        
         /// Show detailed information about events
         #[clap(short, long)]
         verbose: bool,
     },
     
+    /// Check the environment, database, API key and calendar URL without changing anything
+    Doctor,
+    
+    /// Create the database table, or migrate it to the current schema
+    #[clap(name = "db-init")]
+    DbInit,
+    
     /// Clear all events from the database
     #[clap(name = "clear")]
     ClearDb,
     
+    /// Delete stored events that have already ended, keeping upcoming ones
+    #[clap(name = "prune")]
+    Prune {
+        /// Only delete events that ended more than this many days ago
+        #[clap(long, value_name = "DAYS", default_value_t = 0)]
+        before_days: u32,
+    },
+    
+    /// Delete a single event from the database by its UID
+    #[clap(name = "db-delete")]
+    DeleteEvent {
+        /// The event UID to delete
+        #[clap(long)]
+        uid: String,
+    },
+    
+    /// Dump every stored event, including past ones, for backup or migration
+    #[clap(name = "db-export")]
+    DbExport {
+        /// Snapshot format: JSON (round-trips into events) or an .ics calendar
+        #[clap(long, value_enum, default_value_t = DumpFormat::Json)]
+        format: DumpFormat,
+        
+        /// File to write (default: stdout, or --output)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Save the events from a db-export JSON file or an .ics file into the database
+    #[clap(name = "db-import")]
+    DbImport {
+        /// File to import; JSON or iCalendar, detected from the extension or content
+        #[clap(long, value_name = "PATH")]
+        file: PathBuf,
+    },
+    
+    /// Show stored events whose Luma API ID has not been resolved yet
+    #[clap(name = "db-missing")]
+    MissingApiId,
+    
     /// Enrich database events with API data
     #[clap(name = "api")]
     EnrichApi {
@@ -87,6 +337,10 @@ enum Commands {
         /// The slug to lookup (optional, if not provided, the command will attempt to enrich all events)
         #[clap(short, long)]
         slug: Option<String>,
+        
+        /// Look up events that already have an API ID again, replacing IDs that changed
+        #[clap(long)]
+        force: bool,
     },
     
     /// Test API lookup without database operations
@@ -97,12 +351,60 @@ enum Commands {
         slug: String,
     },
     
+    /// Look up the API ID of every slug read from stdin (or a file), one per line
+    #[clap(name = "lookup-batch")]
+    LookupBatch {
+        /// Read slugs from this file instead of stdin
+        #[clap(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+    },
+    
+    /// List a calendar's events through the Luma API instead of its ICS feed
+    #[clap(name = "api-list")]
+    ApiList {
+        /// ID of the Luma calendar, e.g. cal-4dWxlBFjW9Cd6ou
+        #[clap(long, value_name = "ID", value_parser = parse_calendar_id)]
+        calendar: String,
+    },
+    
+    /// Show your registration status for every stored event with an API ID
+    #[clap(name = "rsvp-status")]
+    RsvpStatus,
+    
     /// Add an event to your Luma calendar using its API ID
     #[clap(name = "add")]
     AddEvent {
         /// The event API ID to add to your calendar
         #[clap(short, long)]
         event_id: String,
+        
+        /// Platform hosting the event, e.g. zoom
+        #[clap(long, default_value = "luma")]
+        platform: String,
+        
+        /// Venue address to attach to the calendar entry
+        #[clap(long, value_name = "TEXT")]
+        address: Option<String>,
+        
+        /// Visibility of the calendar entry, e.g. public
+        #[clap(long)]
+        visibility: Option<String>,
+    },
+    
+    /// Show guest count, capacity and waitlist status for an event
+    #[clap(name = "details")]
+    EventDetails {
+        /// The event API ID to look up
+        #[clap(short, long)]
+        event_id: String,
+    },
+    
+    /// Remove an event from your Luma calendar using its calendar event ID
+    #[clap(name = "remove")]
+    RemoveEvent {
+        /// The calendar event ID returned when the event was added
+        #[clap(short, long)]
+        event_id: String,
     },
     
     /// Full sync: fetch events, store in database, enrich with API data, and add to your calendar
@@ -119,72 +421,298 @@ enum Commands {
         /// Skip adding events to your calendar (only store and enrich)
         #[clap(long)]
         skip_add: bool,
+        
+        /// Only look up and add events that are new or whose LAST-MODIFIED advanced since the last sync
+        #[clap(long)]
+        incremental: bool,
+        
+        /// Look up events that already have an API ID again, replacing IDs that changed
+        #[clap(long, conflicts_with = "incremental")]
+        force: bool,
+        
+        /// Delay between calendar additions in milliseconds (defaults to --rate-limit)
+        #[clap(long, value_name = "MS")]
+        add_delay_ms: Option<u64>,
+    },
+    
+    /// Send a desktop notification for events starting soon (each event only once)
+    #[clap(name = "remind")]
+    Remind {
+        /// Notify about events starting within this many minutes
+        #[clap(short, long, value_name = "MINUTES", default_value_t = 15)]
+        within: u32,
+    },
+    
+    /// Export the fetched events to an .ics file
+    #[clap(name = "export")]
+    Export {
+        /// File to write (defaults to stdout)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+        
+        /// Only export today's events
+        #[clap(long, conflicts_with_all = ["week", "next"])]
+        today: bool,
+        
+        /// Only export this week's events
+        #[clap(long, conflicts_with = "next")]
+        week: bool,
+        
+        /// Only export events in the next N days
+        #[clap(long)]
+        next: Option<u32>,
+    },
+    
+    /// Browse the fetched events in an interactive terminal UI
+    #[cfg(feature = "tui")]
+    #[clap(name = "tui")]
+    Tui,
+    
+    /// Print a shell completion script to stdout
+    #[clap(name = "completions")]
+    Completions {
+        /// Shell to generate completions for
+        #[clap(value_enum)]
+        shell: Shell,
     },
 }
 
+impl Commands {
+    /// False for commands that only work on the database, which then run
+    /// without fetching the calendar
+    fn needs_calendar(&self) -> bool {
+        !matches!(
+            self,
+            Commands::Database { .. }
+                | Commands::DbInit
+                | Commands::ClearDb
+                | Commands::DeleteEvent { .. }
+                | Commands::MissingApiId
+                | Commands::Doctor
+                | Commands::ApiList { .. }
+                | Commands::DbExport { .. }
+                | Commands::DbImport { .. }
+                | Commands::Prune { .. }
+                | Commands::LookupBatch { .. }
+                | Commands::RsvpStatus
+        )
+    }
+}
+
+/// Format of a `db-export` snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DumpFormat {
+    /// A JSON array of events with every stored field
+    Json,
+    /// An iCalendar file
+    Ics,
+}
+
+impl Cli {
+    /// Fills in options that were not given on the command line (or via the
+    /// environment) from the config file
+    fn apply_config(&mut self, config: Config, matches: &ArgMatches) -> Result<(), CalendarError> {
+        let from_default = |id: &str| {
+            matches!(matches.value_source(id), None | Some(ValueSource::DefaultValue))
+        };
+
+        if let (true, Some(url)) = (from_default("url"), config.url) {
+            self.url = url;
+        }
+        if let (true, Some(limit)) = (from_default("limit"), config.limit) {
+            self.limit = limit;
+        }
+        if let (true, Some(rate_limit)) = (from_default("rate_limit"), config.rate_limit_ms) {
+            self.rate_limit = rate_limit;
+        }
+        if let (true, Some(retention_days)) = (from_default("retention_days"), config.retention_days) {
+            self.retention_days = retention_days;
+        }
+        if let (true, Some(timezone)) = (from_default("timezone"), config.timezone) {
+            let timezone = timezone.parse::<Tz>().map_err(|e| {
+                CalendarError::EnvError(format!("Invalid timezone in config file: {}", e))
+            })?;
+            self.timezone = Some(timezone);
+        }
+
+        Ok(())
+    }
+
+    /// The number of events listings are truncated to, where 0 means no limit
+    fn limit(&self) -> usize {
+        if self.all || self.count_only { 0 } else { self.limit }
+    }
+
+    /// Options for parsing fetched calendars
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            include_cancelled: self.include_cancelled,
+            retention_days: self.retention_days,
+            strict: self.strict,
+            default_duration_minutes: self.default_duration,
+        }
+    }
+
+    /// The calendar to fetch: the one named by --calendar, otherwise --url
+    fn calendar_url(&self) -> String {
+        match &self.calendar {
+            Some(id) => calendar::luma_calendar_url(id),
+            None => self.url.clone(),
+        }
+    }
+
+    /// Options for fetching calendars from a URL
+    fn fetch_options(&self) -> FetchOptions {
+        FetchOptions {
+            use_cache: !self.no_cache,
+            cache_dir: self.cache_dir.clone(),
+            headers: self.headers.clone(),
+            user_agent: self.user_agent.clone(),
+            auth_token: self.auth_token.clone(),
+            timeout: Duration::from_secs(self.fetch_timeout),
+            retries: self.fetch_retries,
+        }
+    }
+
+    /// How connecting to the database treats the schema
+    fn schema_init(&self) -> database::SchemaInit {
+        if self.no_db_init {
+            database::SchemaInit::Never
+        } else {
+            database::SchemaInit::IfNeeded
+        }
+    }
+
+    /// Returns true when stdout carries machine-readable data that must not be decorated
+    fn machine_output(&self) -> bool {
+        self.output.is_none()
+            && (self.format != OutputFormat::Pretty
+                || self.count_only
+                || matches!(
+                    self.command,
+                    Some(Commands::Export { output: None, .. }) | Some(Commands::NextOne) | Some(Commands::LookupBatch { .. })
+                ))
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    
+    // Needs neither the config nor the calendar
+    if let Some(Commands::Completions { shell }) = &cli.command {
+        clap_complete::generate(*shell, &mut Cli::command(), env!("CARGO_BIN_NAME"), &mut io::stdout());
+        return Ok(());
+    }
+    
+    init_logging(cli.quiet, cli.verbose);
+
+    // Config file values sit between the built-in defaults and explicit flags
+    if let Err(e) = config::load(cli.config.as_deref()).and_then(|config| cli.apply_config(config, &matches)) {
+        exit_with_error(&e, cli.format);
+    }
+    let machine_output = cli.machine_output();
+
+    // Escape codes would end up as garbage in the file
+    if cli.output.is_some() || cli.no_color {
+        colored::control::set_override(false);
+    }
 
     // Measure execution time
     let start_time = Instant::now();
 
-    match run(cli) {
+    // A single runtime drives all async database and API work for the process
+    let runtime = Runtime::new()?;
+
+    if let Some(interval) = cli.watch {
+        watch(&cli, runtime.handle(), Duration::from_secs(interval));
+    }
+
+    match run(&cli, runtime.handle()) {
         Ok(_) => {
-            let duration = start_time.elapsed();
-            println!("\n{}", format!("Execution time: {:.2?}", duration).dimmed());
+            // Keep machine-readable output free of the footer
+            if !machine_output {
+                let duration = start_time.elapsed();
+                // println! would panic if the reader (e.g. `head`) has already gone
+                let _ = writeln!(io::stdout(), "\n{}", format!("Execution time: {:.2?}", duration).dimmed());
+            }
             Ok(())
         }
-        Err(e) => {
-            eprintln!("{}: {}", "Error".bright_red().bold(), e);
-            process::exit(1);
+        // The reader stopped early, which is not a failure of ours
+        Err(CalendarError::IoError(e)) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        Err(e) => exit_with_error(&e, cli.format),
+    }
+}
+
+/// Reports `error` on stderr and exits with its exit code
+fn exit_with_error(error: &CalendarError, format: OutputFormat) -> ! {
+    eprintln!("{}", error_report(error, format));
+    process::exit(errors::exit_code(error));
+}
+
+/// The report printed for `error`
+///
+/// With `--format json` the report is a single `{"error": {"kind", "message"}}`
+/// object, so scripts reading JSON never have to parse the colored message.
+fn error_report(error: &CalendarError, format: OutputFormat) -> String {
+    if format == OutputFormat::Json {
+        serde_json::json!({
+            "error": {
+                "kind": errors::error_kind(error),
+                "message": error.to_string(),
+            }
+        })
+        .to_string()
+    } else {
+        format!("{}: {}", "Error".bright_red().bold(), error)
+    }
+}
+
+/// Clears the screen and re-runs the command every `interval`, forever
+///
+/// Failures (e.g. a dropped connection) are logged and retried on the next tick.
+fn watch(cli: &Cli, rt: &Handle, interval: Duration) -> ! {
+    loop {
+        // Clear the screen and move the cursor home
+        print!("\x1B[2J\x1B[H");
+
+        if let Err(e) = run(cli, rt) {
+            warn!("Refresh failed, retrying in {}s: {}", interval.as_secs(), e);
         }
+
+        std::thread::sleep(interval);
     }
 }
 
-fn run(cli: Cli) -> Result<(), CalendarError> {
-    let events = calendar::fetch_and_parse_calendar(&cli.url)?;
+fn run(cli: &Cli, rt: &Handle) -> Result<(), CalendarError> {
+    let events = if !cli.command.as_ref().is_none_or(Commands::needs_calendar) {
+        Vec::new()
+    } else if cli.offline {
+        let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+        load_stored_events(db.as_ref())?
+    } else {
+        load_events(cli, &cli.calendar_url())?
+    };
     
     // Handle database operations if --store is set
-    if cli.store {
-        match database::connect_db() {
+    if cli.store && !events.is_empty() {
+        match database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init()) {
             Ok(db) => {
-                println!("{}", "Storing events in database...".blue());
+                info!("Storing events in database...");
                 
                 // Debug: Count events with URLs
                 let events_with_urls = events.iter().filter(|e| e.url.is_some()).count();
-                println!("{}", format!("Found {} events with URLs out of {}", events_with_urls, events.len()).yellow());
+                debug!("Found {} events with URLs out of {}", events_with_urls, events.len());
                 
                 // Add default URL to events that don't have one - Luma base URL and clean existing URLs
-                let events_with_clean_urls: Vec<_> = events.iter().map(|e| {
-                    let mut new_event = e.clone();
-                    // Clean the URL if it exists or add a default one
-                    if let Some(url) = &e.url {
-                        // Thoroughly clean existing URL
-                        let clean_url = models::Event::clean_string(url);
-                        new_event.url = Some(clean_url);
-                    } else {
-                        // Add a default URL pattern: https://lu.ma/e/{event_uid}
-                        let default_url = format!("https://lu.ma/e/{}", new_event.event_uid);
-                        new_event.url = Some(default_url);
-                    }
-                    new_event
-                }).collect();
+                let events_with_clean_urls = sync::with_clean_urls(&events);
                 
                 // Auto-enrich events with API IDs if --enrich is set
                 if cli.enrich {
-                    println!("{}", "Auto-enriching events with API IDs...".blue());
-                    
-                    // Set up Tokio runtime for async operations
-                    let rt = match Runtime::new() {
-                        Ok(runtime) => runtime,
-                        Err(e) => {
-                            println!("{}", format!("Failed to create async runtime: {}", e).red());
-                            return Err(CalendarError::ParseError(format!("Failed to create runtime: {}", e)));
-                        }
-                    };
+                    info!("Auto-enriching events with API IDs...");
                     
                     // Create API client
-                    let api_client = LumaApi::new();
+                    let api_client = api_client(cli)?;
                     
                     // Create a vector to hold enriched events
                     let mut enriched_events = Vec::new();
@@ -196,7 +724,7 @@ fn run(cli: Cli) -> Result<(), CalendarError> {
                         
                         // Skip events that already have an API ID
                         if enriched_event.api_id.is_some() {
-                            println!("{}", format!("Event already has API ID: {}", enriched_event.summary).yellow());
+                            debug!("Event already has API ID: {}", enriched_event.summary);
                             enriched_events.push(enriched_event);
                             continue;
                         }
@@ -204,7 +732,7 @@ fn run(cli: Cli) -> Result<(), CalendarError> {
                         // Extract slug from URL
                         if let Some(slug) = enriched_event.extract_slug() {
                             // The slug is already clean from extract_slug
-                            println!("{}", format!("Looking up API ID for event: {} (slug: '{}')", enriched_event.summary, slug).blue());
+                            debug!("Looking up API ID for event: {} (slug: '{}')", enriched_event.summary, slug);
                             
                             let api_id = rt.block_on(async {
                                 api_client.lookup_event_id(&slug).await
@@ -212,113 +740,297 @@ fn run(cli: Cli) -> Result<(), CalendarError> {
                             
                             match api_id {
                                 Ok(id) => {
-                                    println!("{}", format!("Found API ID: {}", id).green());
+                                    debug!("Found API ID: {}", id);
                                     enriched_event.api_id = Some(id);
                                     success_count += 1;
                                 },
                                 Err(e) => {
                                     // Slug is already clean
-                                    println!("{}", format!("API lookup failed for '{}': {}", slug, e).red());
+                                    error!("API lookup failed for '{}': {}", slug, e);
                                     error_count += 1;
                                 }
                             }
                             
                             // Add a small delay to respect rate limits
-                            std::thread::sleep(std::time::Duration::from_millis(500));
+                            std::thread::sleep(api_client.rate_limit());
                         } else {
-                            println!("{}", format!("Could not extract slug from URL for event: {}", enriched_event.summary).yellow());
+                            warn!("Could not extract slug from URL for event: {}", enriched_event.summary);
                         }
                         
                         enriched_events.push(enriched_event);
                     }
                     
-                    println!("{}", format!("API enrichment complete. Success: {}, Errors: {}", success_count, error_count).blue());
+                    info!("API enrichment complete. Success: {}, Errors: {}", success_count, error_count);
                     
                     // Save enriched events with API IDs
                     match db.save_events(&enriched_events) {
-                        Ok(count) => println!("{}", format!("Stored {} new or updated events", count).green()),
-                        Err(e) => println!("{}", format!("Failed to store events: {}", e).red()),
+                        Ok(summary) => {
+                            info!("Stored events: {}", summary);
+                            record_last_sync(db.as_ref());
+                        }
+                        Err(e) => error!("Failed to store events: {}", e),
                     }
                 } else {
                     // Save events with clean URLs without enrichment
                     match db.save_events(&events_with_clean_urls) {
-                        Ok(count) => println!("{}", format!("Stored {} new events", count).green()),
-                        Err(e) => println!("{}", format!("Failed to store events: {}", e).red()),
+                        Ok(summary) => {
+                            info!("Stored events: {}", summary);
+                            record_last_sync(db.as_ref());
+                        }
+                        Err(e) => error!("Failed to store events: {}", e),
                     }
                 }
             }
-            Err(e) => println!("{}", format!("Database connection failed: {}", e).red()),
+            Err(e) => error!("Database connection failed: {}", e),
         }
     }
 
+    let display_options = DisplayOptions {
+        verbose: cli.verbose >= 1,
+        format: cli.format,
+        relative: cli.relative,
+        timezone: cli.timezone,
+        full_description: cli.full_description,
+        summary_width: cli.summary_width,
+        terminal_width: terminal_width(cli),
+        group_by: cli.group_by,
+        fields: cli.fields,
+        count_only: cli.count_only,
+    };
+
+    // An empty calendar gets one clear message rather than an empty listing; the
+    // per-command messages are for calendars with nothing in the requested range
+    let lists_calendar = matches!(
+        cli.command,
+        None | Some(Commands::Today | Commands::Week | Commands::Next { .. })
+    );
+    let calendar_empty = events.is_empty();
+
+    // Keyword filters and sorting apply to the listings, ahead of any --limit truncation
+    let mut events = display::filter_events(&events, &cli.filters, &cli.categories);
+    events = display::filter_by_duration(events, cli.min_duration, cli.max_duration);
+    if let Some(minutes) = cli.dedupe_window {
+        let before = events.len();
+        events = display::dedupe_events(events, chrono::Duration::minutes(minutes as i64));
+        debug!("Dropped {} near-duplicate events", before - events.len());
+    }
+    display::sort_events(&mut events, cli.sort, cli.reverse);
+
+    let mut out = open_output(cli)?;
+
+    if calendar_empty && lists_calendar && cli.format == OutputFormat::Pretty && !cli.count_only {
+        writeln!(out, "{}", "No events found in this calendar.".yellow())?;
+        out.flush()?;
+        return Ok(());
+    }
+
     // Handle subcommands or default display
     match &cli.command {
         Some(Commands::Today) => {
-            display::display_today_events(&events, cli.verbose);
+            display::display_today_events(&mut out, &events, display_options)?;
         }
         Some(Commands::Week) => {
-            display::display_week_events(&events, cli.verbose);
+            display::display_week_events(&mut out, &events, display_options)?;
         }
         Some(Commands::Next { days }) => {
-            display::display_upcoming_events(&events, *days, cli.limit, cli.verbose);
+            display::display_upcoming_events(&mut out, &events, *days, cli.limit(), display_options)?;
         }
-        Some(Commands::Database { all, limit, verbose }) => {
-            match database::connect_db() {
-                Ok(db) => {
-                    if *all {
-                        match db.get_all_events() {
-                            Ok(db_events) => {
-                                println!(
-                                    "{}",
-                                    format!("Displaying all {} events from database", db_events.len())
-                                        .blue()
-                                );
-                                display::display_events(&db_events, *limit, *verbose);
-                            }
-                            Err(e) => println!("{}", format!("Failed to fetch events: {}", e).red()),
-                        }
+        Some(Commands::NextOne) => {
+            let event = display::next_event(&events, chrono::Utc::now()).ok_or(CalendarError::NoUpcomingEvents)?;
+            display::display_one_line(&mut out, event, display_options)?;
+        }
+        Some(Commands::Stats { by }) => {
+            display::display_stats(&mut out, &events, *by, cli.limit(), display_options)?;
+        }
+        Some(Commands::Database { all, limit, offset, status, added_since, verbose }) => {
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            if cli.count_only {
+                write_stored_count(&mut out, cli, db.as_ref())?;
+            } else if *all || added_since.is_some() {
+                let filter_and_page = |db_events: Vec<models::Event>| {
+                    let db_events = stored_listing(cli, &db_events);
+                    let total = db_events.len();
+                    let rest = db_events.get(*offset..).unwrap_or_default();
+                    (display::limit_events(rest, *limit).to_vec(), total)
+                };
+
+                // Page in SQL when the listing is in the table's own order; filters
+                // and other orderings have to see every event before paging
+                let (db_events, total) = if let Some(window) = added_since {
+                    filter_and_page(db.get_events_added_since(chrono::Utc::now() - *window)?)
+                } else if cli.filters.is_empty()
+                    && cli.categories.is_empty()
+                    && cli.min_duration == 0
+                    && cli.max_duration == 0
+                    && cli.sort == SortKey::Start
+                    && !cli.reverse
+                {
+                    (db.get_events_page(*limit as i64, *offset as i64)?, db.get_event_count()? as usize)
+                } else {
+                    filter_and_page(db.get_all_events()?)
+                };
+
+                if cli.format == OutputFormat::Pretty {
+                    let heading = if db_events.len() == total {
+                        format!("Displaying all {} events from database", total)
+                    } else if db_events.is_empty() {
+                        format!("No events past offset {} of {} in database", offset, total)
                     } else {
-                        match db.get_event_count() {
-                            Ok(count) => {
-                                println!(
-                                    "{}",
-                                    format!("Database contains {} events", count).blue()
-                                );
-                            }
-                            Err(e) => {
-                                println!("{}", format!("Failed to count events: {}", e).red())
-                            }
-                        }
+                        format!(
+                            "Displaying events {}-{} of {} from database",
+                            offset + 1,
+                            offset + db_events.len(),
+                            total
+                        )
+                    };
+                    writeln!(out, "{}", heading.blue())?;
+                }
+                let db_options = DisplayOptions {
+                    verbose: *verbose,
+                    ..display_options
+                };
+                // Already paged, so show the page as is
+                display::display_events(&mut out, &db_events, 0, db_options)?;
+            } else if *status {
+                let count = db.get_event_count()?;
+                let last_sync = database::last_sync(db.as_ref())?;
+                match cli.format {
+                    OutputFormat::Json => {
+                        let last_sync = last_sync.map(|dt| dt.to_rfc3339());
+                        writeln!(out, "{}", serde_json::json!({ "count": count, "last_sync": last_sync }))?;
+                    }
+                    OutputFormat::Plain => {
+                        let last_sync = last_sync.map_or_else(|| "never".to_string(), |dt| dt.to_rfc3339());
+                        writeln!(out, "{}\t{}", count, last_sync)?;
+                    }
+                    OutputFormat::Pretty => {
+                        let synced = match last_sync {
+                            Some(dt) => format!("last synced {}", display::time_ago(dt, chrono::Utc::now())),
+                            None => "never synced".to_string(),
+                        };
+                        writeln!(out, "{}", format!("Database contains {} events, {}", count, synced).blue())?;
+                    }
+                }
+            } else {
+                let count = db.get_event_count()?;
+                match cli.format {
+                    OutputFormat::Json => writeln!(out, "{}", serde_json::json!({ "count": count }))?,
+                    OutputFormat::Plain => writeln!(out, "{}", count)?,
+                    OutputFormat::Pretty => {
+                        writeln!(out, "{}", format!("Database contains {} events", count).blue())?;
                     }
                 }
-                Err(e) => println!("{}", format!("Database connection failed: {}", e).red()),
             }
         }
+        Some(Commands::DbInit) => {
+            database::connect_db(rt, cli.insecure, cli.retention_days, database::SchemaInit::Always)?;
+            println!("{}", "Database schema is up to date".green());
+        }
+        Some(Commands::Doctor) => {
+            let checks = [
+                ("Database environment", check_database_env()),
+                ("Database connection", check_database(cli, rt)),
+                ("Luma API", check_api(cli, rt)),
+                ("Calendar URL", check_calendar(cli)),
+            ];
+
+            for (name, result) in &checks {
+                match result {
+                    Ok(detail) => println!("{}", format!("✅ {}: {}", name, detail).green()),
+                    Err(reason) => println!("{}", format!("❌ {}: {}", name, reason).red()),
+                }
+            }
+
+            let failed = checks.iter().filter(|(_, result)| result.is_err()).count();
+            if failed > 0 {
+                return Err(CalendarError::EnvError(format!("{} of {} checks failed", failed, checks.len())));
+            }
+            println!("\n{}", format!("All {} checks passed", checks.len()).green().bold());
+        }
         Some(Commands::ClearDb) => {
-            match database::connect_db() {
-                Ok(db) => {
-                    match db.clear_all_events() {
-                        Ok(count) => {
-                            println!("{}", format!("Successfully cleared {} events from database", count).green());
-                        }
-                        Err(e) => {
-                            println!("{}", format!("Failed to clear database: {}", e).red());
-                        }
-                    }
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            let count = db.clear_all_events()?;
+            println!("{}", format!("Successfully cleared {} events from database", count).green());
+        }
+        Some(Commands::Prune { before_days }) => {
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            let before = chrono::Utc::now() - chrono::Duration::days(*before_days as i64);
+            let count = db.clear_past_events(before)?;
+            let before = display::in_zone(before, cli.timezone);
+            println!(
+                "{}",
+                format!("Pruned {} events that ended before {}", count, before.format("%a, %b %d, %Y %I:%M %p")).green()
+            );
+        }
+        Some(Commands::DeleteEvent { uid }) => {
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            match db.delete_event(uid)? {
+                0 => {
+                    println!("{}", format!("No matching event with UID {} in the database", uid).yellow());
+                }
+                count => {
+                    println!("{}", format!("Successfully deleted {} event(s) with UID {}", count, uid).green());
                 }
-                Err(e) => println!("{}", format!("Database connection failed: {}", e).red()),
             }
         }
-        Some(Commands::TestLookup { slug }) => {
-            // Set up Tokio runtime for async operations
-            let rt = Runtime::new().map_err(|e| {
-                CalendarError::ParseError(format!("Failed to create runtime: {}", e))
-            })?;
+        Some(Commands::DbExport { format, output }) => {
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            let stored = db.get_all_events_unfiltered()?;
+            let stored: Vec<&models::Event> = stored.iter().collect();
+            
+            let mut file;
+            let mut writer: &mut dyn Write = match output {
+                Some(path) => {
+                    file = BufWriter::new(File::create(path)?);
+                    &mut file
+                }
+                None => &mut out,
+            };
+            match format {
+                DumpFormat::Json => export::write_json(&stored, &mut writer)?,
+                DumpFormat::Ics => export::write_ics(&stored, &mut writer)?,
+            }
+            writer.flush()?;
             
+            if let Some(path) = output {
+                info!("Exported {} stored events to {}", stored.len(), path.display());
+            }
+        }
+        Some(Commands::DbImport { file }) => {
+            let imported = read_import_file(file, cli.parse_options())?;
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            let summary = db.save_events(&imported)?;
+            println!(
+                "{}",
+                format!("Imported {} events from {}: {}", imported.len(), file.display(), summary).green()
+            );
+        }
+        Some(Commands::MissingApiId) => {
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            if cli.count_only {
+                write_stored_count(&mut out, cli, db.as_ref())?;
+            } else {
+                let db_events = stored_listing(cli, &db.get_events_missing_api_id()?);
+                if cli.format == OutputFormat::Pretty {
+                    writeln!(
+                        out,
+                        "{}",
+                        format!("{} events without an API ID", db_events.len()).blue()
+                    )?;
+                }
+                // The URL is what the slug lookup used, so always show it
+                let missing_options = DisplayOptions {
+                    verbose: true,
+                    ..display_options
+                };
+                display::display_events(&mut out, &db_events, cli.limit(), missing_options)?;
+            }
+        }
+        Some(Commands::TestLookup { slug }) => {
             // Create API client
-            let api_client = LumaApi::new();
+            let api_client = api_client(cli)?;
             
-            println!("{}", format!("Looking up API ID for slug: {}", slug).blue());
+            info!("Looking up API ID for slug: {}", slug);
             let api_id = rt.block_on(async {
                 api_client.lookup_event_id(slug).await
             });
@@ -333,29 +1045,106 @@ fn run(cli: Cli) -> Result<(), CalendarError> {
                 },
             }
         }
-        Some(Commands::AddEvent { event_id }) => {
-            // Set up Tokio runtime for async operations
-            let rt = Runtime::new().map_err(|e| {
-                CalendarError::ParseError(format!("Failed to create runtime: {}", e))
-            })?;
+        Some(Commands::LookupBatch { file }) => {
+            let api_client = api_client(cli)?;
+            let slugs = read_slugs(file.as_deref())?;
+            info!("Looking up API IDs for {} slugs", slugs.len());
+
+            // A failed slug is reported on its line; the rest are still looked up
+            let results = rt.block_on(api_client.lookup_event_ids(&slugs));
+            let (mut found, mut failed) = (0, 0);
+            for (slug, result) in slugs.iter().zip(results) {
+                match result {
+                    Ok(id) => {
+                        writeln!(out, "{}\t{}", slug, id)?;
+                        found += 1;
+                    }
+                    Err(e) => {
+                        writeln!(out, "{}\tERROR: {}", slug, e)?;
+                        failed += 1;
+                    }
+                }
+            }
+
+            info!("Batch lookup complete. Success: {}, Errors: {}", found, failed);
+        }
+        Some(Commands::ApiList { calendar }) => {
+            let api_client = api_client(cli)?;
+            
+            info!("Listing events of calendar {} through the API...", calendar);
+            let api_events = rt.block_on(api_client.list_calendar_events(calendar))?;
+            info!("Found {} events", api_events.len());
+            
+            let mut api_events = display::filter_events(&api_events, &cli.filters, &cli.categories);
+            display::sort_events(&mut api_events, cli.sort, cli.reverse);
+            display::display_events(&mut out, &api_events, cli.limit(), display_options)?;
+        }
+        Some(Commands::RsvpStatus) => {
+            let api_client = api_client(cli)?;
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            let events: Vec<_> = db.get_all_events()?.into_iter().filter(|event| event.api_id.is_some()).collect();
+            info!("Checking registration status for {} events", events.len());
+            
+            // A failed lookup is reported on its event's line, the rest still get checked
+            let mut statuses = Vec::with_capacity(events.len());
+            for (index, event) in events.iter().enumerate() {
+                if index > 0 {
+                    std::thread::sleep(api_client.rate_limit());
+                }
+                let Some(api_id) = event.api_id.as_deref() else {
+                    continue;
+                };
+                statuses.push((event, rt.block_on(api_client.get_registration_status(api_id))));
+            }
             
+            if cli.format == OutputFormat::Json {
+                let entries: Vec<_> = statuses
+                    .iter()
+                    .map(|(event, status)| match status {
+                        Ok(status) => serde_json::json!({"api_id": event.api_id, "summary": event.summary, "status": status}),
+                        Err(e) => serde_json::json!({"api_id": event.api_id, "summary": event.summary, "error": e.to_string()}),
+                    })
+                    .collect();
+                serde_json::to_writer_pretty(&mut out, &entries).map_err(io::Error::from)?;
+                writeln!(out)?;
+            } else if statuses.is_empty() {
+                writeln!(out, "{}", "No stored events have an API ID yet; run sync first.".yellow())?;
+            } else {
+                for (event, status) in &statuses {
+                    let status = match status {
+                        Ok(Some(status @ api::RegistrationStatus::Going)) => status.to_string().green(),
+                        Ok(Some(status @ api::RegistrationStatus::NotGoing)) => status.to_string().red(),
+                        Ok(Some(status)) => status.to_string().yellow(),
+                        Ok(None) => "not registered".dimmed(),
+                        Err(e) => format!("ERROR: {}", e).red(),
+                    };
+                    let start = display::in_zone(event.start, display_options.timezone);
+                    writeln!(out, "{}  {}  {}", start.format("%a %b %d %Y"), event.summary.bold(), status)?;
+                }
+            }
+        }
+        Some(Commands::AddEvent { event_id, platform, address, visibility }) => {
             // Create API client
-            let api_client = LumaApi::new();
+            let api_client = api_client(cli)?;
+            
+            let mut options = api::AddEventOptions {
+                platform: platform.clone(),
+                visibility: visibility.clone(),
+                ..api::AddEventOptions::default()
+            };
+            if let Some(address) = address {
+                options = options.with_address(address);
+            }
             
-            println!("{}", format!("Adding event with API ID: {} to your calendar...", event_id).blue());
+            info!("Adding event with API ID: {} to your calendar...", event_id);
             let result = rt.block_on(async {
-                api_client.add_event(&event_id).await
+                api_client.add_event(event_id, &options).await
             });
             
             match result {
                 Ok(response) => {
-                    // Extract calendar_event_id from the response if available
-                    let calendar_event_id = response.get("calendar_event_id")
-                        .and_then(|id| id.as_str())
-                        .unwrap_or("unknown");
-                    
-                    println!("{}", format!("✅ Successfully added event to your calendar").green());
-                    println!("{}", format!("Calendar Event ID: {}", calendar_event_id).green());
+                    println!("{}", "✅ Successfully added event to your calendar".green());
+                    println!("{}", format!("Calendar Event ID: {}", response.calendar_event_id).green());
                     println!("{}", "The event has been added to your Luma calendar.".yellow());
                 },
                 Err(e) => {
@@ -363,202 +1152,125 @@ fn run(cli: Cli) -> Result<(), CalendarError> {
                 },
             }
         }
-        Some(Commands::FullSync { url, days, skip_add }) => {
-            println!("{}", "Starting full sync process...".blue().bold());
+        Some(Commands::EventDetails { event_id }) => {
+            // Create API client
+            let api_client = api_client(cli)?;
             
-            // 1. Fetch events from calendar URL
-            let calendar_url = url.clone().unwrap_or_else(|| cli.url.clone());
-            println!("{}", format!("Fetching events from calendar: {}", calendar_url).blue());
-            let events = calendar::fetch_and_parse_calendar(&calendar_url)?;
-            println!("{}", format!("Fetched {} events", events.len()).green());
+            info!("Fetching details for event with API ID: {}", event_id);
+            let details = rt.block_on(async {
+                api_client.get_event_details(event_id).await
+            })?;
             
-            // 2. Clean URLs and prepare events for storage
-            let events_with_clean_urls: Vec<_> = events.iter().map(|e| {
-                let mut new_event = e.clone();
-                // Clean the URL if it exists or add a default one
-                if let Some(url) = &e.url {
-                    // Thoroughly clean existing URL
-                    let clean_url = models::Event::clean_string(url);
-                    new_event.url = Some(clean_url);
-                } else {
-                    // Add a default URL pattern: https://lu.ma/e/{event_uid}
-                    let default_url = format!("https://lu.ma/e/{}", new_event.event_uid);
-                    new_event.url = Some(default_url);
+            if cli.format == OutputFormat::Json {
+                serde_json::to_writer_pretty(&mut out, &details).map_err(io::Error::from)?;
+                writeln!(out)?;
+            } else {
+                let guests = details.guest_count.map_or("unknown".to_string(), |count| count.to_string());
+                let capacity = details.capacity.map_or("unlimited".to_string(), |capacity| capacity.to_string());
+                
+                if let Some(name) = &details.name {
+                    writeln!(out, "{}", name.bright_blue().bold())?;
+                }
+                writeln!(out, "{}: {}", "Guests".blue(), guests)?;
+                writeln!(out, "{}: {}", "Capacity".blue(), capacity)?;
+                if let Some(spots_left) = details.spots_left() {
+                    writeln!(out, "{}: {}", "Spots left".blue(), spots_left)?;
                 }
-                new_event
-            }).collect();
+                writeln!(out, "{}: {}", "Waitlist".blue(), if details.waitlist_active { "active" } else { "inactive" })?;
+            }
+        }
+        Some(Commands::RemoveEvent { event_id }) => {
+            // Create API client
+            let api_client = api_client(cli)?;
             
-            // 3. Store events in database
-            match database::connect_db() {
-                Ok(db) => {
-                    println!("{}", "Storing events in database...".blue());
-                    
-                    match db.save_events(&events_with_clean_urls) {
-                        Ok(count) => println!("{}", format!("Stored {} new or updated events", count).green()),
-                        Err(e) => {
-                            println!("{}", format!("Failed to store events: {}", e).red());
-                            return Err(CalendarError::ParseError(format!("Failed to store events: {}", e)));
-                        }
+            info!("Removing event with calendar event ID: {} from your calendar...", event_id);
+            let result = rt.block_on(async {
+                api_client.remove_event(event_id).await
+            });
+            
+            match result {
+                Ok(response) => {
+                    println!("{}", "✅ Successfully removed event from your calendar".green());
+                    if cli.verbose >= 1 && !response.is_null() {
+                        println!("{}", format!("Response: {}", response).blue());
                     }
-                    
-                    // 4. Enrich events with API data
-                    println!("{}", "Enriching events with API data...".blue());
-                    
-                    // Set up Tokio runtime for async operations
-                    let rt = match Runtime::new() {
-                        Ok(runtime) => runtime,
-                        Err(e) => {
-                            println!("{}", format!("Failed to create async runtime: {}", e).red());
-                            return Err(CalendarError::ParseError(format!("Failed to create runtime: {}", e)));
-                        }
-                    };
-                    
-                    // Create API client
-                    let api_client = LumaApi::new();
-                    
-                    // Fetch all events from the database
-                    let mut db_events = match db.get_all_events() {
-                        Ok(events) => events,
-                        Err(e) => {
-                            println!("{}", format!("Failed to fetch events from database: {}", e).red());
-                            return Err(CalendarError::ParseError(format!("Failed to fetch events: {}", e)));
-                        }
-                    };
-                    
-                    println!("{}", format!("Found {} events in database", db_events.len()).blue());
-                    
-                    // Process and enrich events
-                    let mut success_count = 0;
-                    let mut error_count = 0;
-                    let mut added_to_calendar_count = 0;
-                    let mut add_error_count = 0;
-                    
-                    // Filter events based on the days parameter
-                    let now = chrono::Utc::now();
-                    let future_cutoff = now + chrono::Duration::days(*days as i64);
-                    
-                    // Track future events for possible addition to calendar
-                    let mut events_to_add = Vec::new();
-                    
-                    for event in db_events.iter_mut() {
-                        // Skip events that already have an API ID
-                        if event.api_id.is_some() {
-                            println!("{}", format!("Event already has API ID: {}", event.summary).yellow());
-                            
-                            // If event is in the future and has API ID, add it to the list of events to potentially add to calendar
-                            if event.start > now && event.start < future_cutoff {
-                                events_to_add.push(event.clone());
-                            }
-                            
-                            continue;
-                        }
-                        
-                        // Extract slug from URL
-                        if let Some(slug) = event.extract_slug() {
-                            println!("{}", format!("Looking up API ID for event: {} (slug: '{}')", event.summary, slug).blue());
-                            
-                            let api_id = rt.block_on(async {
-                                api_client.lookup_event_id(&slug).await
-                            });
-                            
-                            match api_id {
-                                Ok(id) => {
-                                    println!("{}", format!("Found API ID: {}", id).green());
-                                    event.api_id = Some(id.clone());
-                                    
-                                    // Save the updated event
-                                    if let Err(e) = db.save_event(event) {
-                                        println!("{}", format!("Failed to save event: {}", e).red());
-                                        error_count += 1;
-                                    } else {
-                                        println!("{}", "Event updated successfully".green());
-                                        success_count += 1;
-                                        
-                                        // If event is in the future, add it to the list of events to potentially add to calendar
-                                        if event.start > now && event.start < future_cutoff {
-                                            events_to_add.push(event.clone());
-                                        }
-                                    }
-                                },
-                                Err(e) => {
-                                    println!("{}", format!("API lookup failed for '{}': {}", slug, e).red());
-                                    error_count += 1;
-                                }
-                            }
-                            
-                            // Add a small delay to respect rate limits
-                            std::thread::sleep(std::time::Duration::from_millis(500));
-                        } else {
-                            println!("{}", format!("Could not extract slug from URL for event: {}", event.summary).yellow());
-                        }
-                    }
-                    
-                    println!("{}", format!("API enrichment complete. Success: {}, Errors: {}", success_count, error_count).blue());
-                    
-                    // 5. Add future events to calendar if not skipped
-                    if !*skip_add && !events_to_add.is_empty() {
-                        println!("{}", format!("Found {} future events to add to your calendar", events_to_add.len()).blue());
-                        
-                        for event in events_to_add {
-                            if let Some(api_id) = &event.api_id {
-                                println!("{}", format!("Adding event to calendar: {} (API ID: {})", event.summary, api_id).blue());
-                                
-                                let result = rt.block_on(async {
-                                    api_client.add_event(api_id).await
-                                });
-                                
-                                match result {
-                                    Ok(_) => {
-                                        println!("{}", format!("✅ Successfully added event to calendar: {}", event.summary).green());
-                                        added_to_calendar_count += 1;
-                                    },
-                                    Err(e) => {
-                                        println!("{}", format!("❌ Failed to add event to calendar: {}", e).red());
-                                        add_error_count += 1;
-                                    }
-                                }
-                                
-                                // Add a small delay to respect rate limits
-                                std::thread::sleep(std::time::Duration::from_millis(1000));
-                            }
-                        }
-                        
-                        println!("{}", format!("Calendar addition complete. Success: {}, Errors: {}", added_to_calendar_count, add_error_count).blue());
-                    } else if *skip_add {
-                        println!("{}", "Skipping adding events to calendar as requested".yellow());
-                    } else {
-                        println!("{}", "No future events found to add to your calendar".yellow());
-                    }
-                    
-                    println!("{}", "Full sync process completed successfully".green().bold());
-                }
+                },
                 Err(e) => {
-                    println!("{}", format!("Database connection failed: {}", e).red());
-                    return Err(CalendarError::ParseError(format!("Database connection failed: {}", e)));
-                }
+                    println!("{}", format!("❌ Failed to remove event: {}", e).red());
+                },
             }
         }
-        Some(Commands::EnrichApi { limit, slug }) => {
-            // Set up Tokio runtime for async operations
-            let rt = Runtime::new().map_err(|e| {
-                CalendarError::ParseError(format!("Failed to create runtime: {}", e))
-            })?;
+        Some(Commands::FullSync { url, days, skip_add, incremental, force, add_delay_ms }) => {
+            // Check the API key before doing any work
+            let api_client = api_client(cli)?;
+            
+            info!("Starting full sync process...");
+            
+            // 1. Fetch events from calendar URL
+            let calendar_url = url.clone().unwrap_or_else(|| cli.calendar_url());
+            match &cli.file {
+                Some(path) => info!("Reading events from file: {}", path.display()),
+                None => info!("Fetching events from calendar: {}", calendar_url),
+            }
+            let events = load_events(cli, &calendar_url)?;
+            if events.is_empty() {
+                info!("No events found in this calendar, nothing to sync");
+                return Ok(());
+            }
+            info!("Fetched {} events", events.len());
             
+            // 2. Store, enrich and add upcoming events to the Luma calendar
+            let db = database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init())?;
+            let options = sync::SyncOptions {
+                days: *days,
+                skip_add: *skip_add,
+                incremental: *incremental,
+                force: *force,
+                progress: show_progress(cli),
+                add_delay: add_delay_ms.map(Duration::from_millis),
+            };
+            let cancel = cancel_on_ctrl_c(rt);
+            let report = sync::run_full_sync(&events, db.as_ref(), &api_client, rt, options, &cancel)?;
+            
+            if report.interrupted {
+                println!("{}", "Full sync interrupted, partial results:".yellow().bold());
+            } else {
+                println!("{}", "Full sync complete".green().bold());
+            }
+            println!("  Fetched {} events; {}", report.fetched, report.stored);
+            println!("  Resolved {} API IDs ({} failed)", report.enriched, report.enrich_errors);
+            if *force {
+                println!("  Replaced {} API IDs that had changed", report.changed_ids);
+            }
+            if *incremental {
+                println!("  Skipped {} events unchanged since the last sync", report.unchanged);
+            }
+            if !*skip_add {
+                println!("  Added {} events to your calendar ({} failed)", report.added.len(), report.add_errors);
+                for event in &report.added {
+                    println!("    {} {}", display::in_zone(event.start, cli.timezone).format("%a, %b %d, %Y"), event.summary);
+                }
+            }
+            if report.interrupted {
+                return Err(CalendarError::Interrupted);
+            }
+        }
+        Some(Commands::EnrichApi { limit, slug, force }) => {
             // Create API client
-            let api_client = LumaApi::new();
+            let api_client = api_client(cli)?;
             
             // Connect to database
-            match database::connect_db() {
+            match database::connect_db(rt, cli.insecure, cli.retention_days, cli.schema_init()) {
                 Ok(db) => {
                     // Fetch events from database
                     match db.get_all_events() {
                         Ok(mut db_events) => {
-                            println!("{}", format!("Found {} events in database", db_events.len()).blue());
+                            info!("Found {} events in database", db_events.len());
                             
                             // Limit events if specified
                             let events_to_process = match limit {
                                 Some(lim) => {
-                                    println!("{}", format!("Processing only the first {} events", lim).yellow());
+                                    info!("Processing only the first {} events", lim);
                                     db_events.truncate(*lim);
                                     &mut db_events
                                 },
@@ -568,28 +1280,28 @@ fn run(cli: Cli) -> Result<(), CalendarError> {
                             // Process events
                             if let Some(specific_slug) = slug {
                                 // Process a single event with the given slug
-                                println!("{}", format!("Looking up API ID for slug: {}", specific_slug).yellow());
+                                info!("Looking up API ID for slug: {}", specific_slug);
                                 let api_id = rt.block_on(async {
-                                    api_client.lookup_event_id(&specific_slug).await
+                                    api_client.lookup_event_id(specific_slug).await
                                 });
                                 
                                 match api_id {
                                     Ok(id) => {
-                                        println!("{}", format!("Found API ID: {}", id).green());
+                                        debug!("Found API ID: {}", id);
                                         // Look for an event with this slug
                                         let mut found = false;
                                         for event in events_to_process.iter_mut() {
                                             if let Some(url) = &event.url {
-                                                if url.contains(&*specific_slug) {
-                                                    println!("{}", format!("Updating event: {}", event.summary).green());
+                                                if url.contains(specific_slug.as_str()) {
+                                                    debug!("Updating event: {}", event.summary);
                                                     event.api_id = Some(id.clone());
                                                     found = true;
                                                     
                                                     // Save the updated event
                                                     if let Err(e) = db.save_event(event) {
-                                                        println!("{}", format!("Failed to save event: {}", e).red());
+                                                        error!("Failed to save event: {}", e);
                                                     } else {
-                                                        println!("{}", "Event updated successfully".green());
+                                                        debug!("Event updated successfully");
                                                     }
                                                     
                                                     break;
@@ -598,79 +1310,738 @@ fn run(cli: Cli) -> Result<(), CalendarError> {
                                         }
                                         
                                         if !found {
-                                            println!("{}", format!("No event found with slug: {}", specific_slug).yellow());
+                                            warn!("No event found with slug: {}", specific_slug);
                                         }
                                     },
                                     Err(e) => {
                                         // specific_slug needs cleaning since it's user input
                                         let clean_slug = models::Event::clean_string(specific_slug);
-                                        println!("{}", format!("API lookup failed for '{}': {}", clean_slug, e).red());
+                                        error!("API lookup failed for '{}': {}", clean_slug, e);
                                     },
                                 }
                             } else {
                                 // Process all events
-                                println!("{}", "Processing all events...".blue());
-                                let mut success_count = 0;
-                                let mut error_count = 0;
-                                
-                                for event in events_to_process.iter_mut() {
-                                    // Skip events that already have an API ID
-                                    if event.api_id.is_some() {
-                                        println!("{}", format!("Event already has API ID: {}", event.summary).yellow());
-                                        continue;
-                                    }
-                                    
-                                    // Extract slug from URL
-                                    if let Some(slug) = event.extract_slug() {
-                                        // Slug is already clean from extract_slug
-                                        println!("{}", format!("Looking up API ID for event: {} (slug: '{}')", event.summary, slug).blue());
-                                        
-                                        let api_id = rt.block_on(async {
-                                            api_client.lookup_event_id(&slug).await
-                                        });
-                                        
-                                        match api_id {
-                                            Ok(id) => {
-                                                println!("{}", format!("Found API ID: {}", id).green());
-                                                event.api_id = Some(id);
-                                                
-                                                // Save the updated event
-                                                if let Err(e) = db.save_event(event) {
-                                                    println!("{}", format!("Failed to save event: {}", e).red());
-                                                    error_count += 1;
-                                                } else {
-                                                    println!("{}", "Event updated successfully".green());
-                                                    success_count += 1;
-                                                }
-                                            },
-                                            Err(e) => {
-                                                // Slug is already clean
-                                                println!("{}", format!("API lookup failed for '{}': {}", slug, e).red());
-                                                error_count += 1;
-                                            }
-                                        }
-                                        
-                                        // Add a small delay to respect rate limits
-                                        std::thread::sleep(std::time::Duration::from_millis(500));
-                                    } else {
-                                        println!("{}", format!("Could not extract slug from URL for event: {}", event.summary).yellow());
-                                    }
+                                info!("Processing all events...");
+                                let counts = sync::enrich_stored_events(
+                                    events_to_process,
+                                    db.as_ref(),
+                                    &api_client,
+                                    rt,
+                                    *force,
+                                    show_progress(cli),
+                                );
+                                info!("API enrichment complete. Success: {}, Errors: {}", counts.enriched, counts.errors);
+                                if *force {
+                                    info!("Replaced {} API IDs that had changed", counts.changed);
                                 }
-                                
-                                println!("{}", format!("API enrichment complete. Success: {}, Errors: {}", success_count, error_count).blue());
                             }
                         }
-                        Err(e) => println!("{}", format!("Failed to fetch events from database: {}", e).red()),
+                        Err(e) => error!("Failed to fetch events from database: {}", e),
                     }
                 }
-                Err(e) => println!("{}", format!("Database connection failed: {}", e).red()),
+                Err(e) => error!("Database connection failed: {}", e),
             }
         }
+        Some(Commands::Remind { within }) => {
+            let now = chrono::Utc::now();
+            let due = remind::due_events(&events, now, chrono::Duration::minutes(*within as i64));
+            let mut log = remind::ReminderLog::load();
+            
+            for event in due {
+                if log.was_sent(event) {
+                    continue;
+                }
+                match remind::notify(event, cli.timezone) {
+                    Ok(()) => {
+                        info!("Sent reminder for {}", event.summary);
+                        log.record(event);
+                    }
+                    Err(e) => warn!("Failed to send reminder for {}: {}", event.summary, e),
+                }
+            }
+            
+            if let Err(e) = log.save(now) {
+                warn!("Failed to save the reminder log: {}", e);
+            }
+        }
+        Some(Commands::Export { output, today, week, next }) => {
+            let selected = if *today {
+                display::today_events(&events, chrono::Utc::now(), cli.timezone)
+            } else if *week {
+                display::week_events(&events, chrono::Utc::now(), cli.timezone)
+            } else if let Some(days) = next {
+                display::upcoming_events(&events, *days)
+            } else {
+                events.iter().collect()
+            };
+            
+            // Respect --limit as for the listings
+            let selected = display::limit_events(&selected, cli.limit());
+            
+            match output {
+                Some(path) => {
+                    let mut writer = BufWriter::new(File::create(path)?);
+                    export::write_ics(selected, &mut writer)?;
+                    println!(
+                        "{}",
+                        format!("Exported {} events to {}", selected.len(), path.display()).green()
+                    );
+                }
+                None => export::write_ics(selected, &mut out)?,
+            }
+        }
+        #[cfg(feature = "tui")]
+        Some(Commands::Tui) => {
+            tui::run(&events, cli.timezone)?;
+        }
+        // Handled in main before anything is fetched
+        Some(Commands::Completions { .. }) => {}
+        None if cli.from.is_some() || cli.to.is_some() => {
+            if let (Some(from), Some(to)) = (cli.from, cli.to) {
+                if from > to {
+                    return Err(CalendarError::EnvError(format!("--from {} is after --to {}", from, to)));
+                }
+            }
+            display::display_range_events(&mut out, &events, cli.from, cli.to, cli.limit(), display_options)?;
+        }
         None => {
             // Default behavior: display all events
-            display::display_events(&events, cli.limit, cli.verbose);
+            display::display_events(&mut out, &events, cli.limit(), display_options)?;
         }
     }
 
+    out.flush()?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Opens the `--output` file, truncating it, or stdout when none was given
+fn open_output(cli: &Cli) -> io::Result<Box<dyn Write>> {
+    Ok(match &cli.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+/// Loads events from `--file` when given, otherwise fetches them from `url`
+fn load_events(cli: &Cli, url: &str) -> Result<Vec<models::Event>, CalendarError> {
+    match &cli.file {
+        Some(path) => calendar::parse_calendar_file(path, cli.parse_options()),
+        None => calendar::fetch_and_parse_calendar(url, cli.parse_options(), &cli.fetch_options()),
+    }
+}
+
+/// Returns a flag that is set on the first Ctrl-C, so a long loop can stop
+/// between iterations; a second Ctrl-C exits immediately
+fn cancel_on_ctrl_c(rt: &Handle) -> Arc<AtomicBool> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&cancel);
+    rt.spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        warn!("Interrupted, finishing the current event (press Ctrl-C again to quit now)");
+        flag.store(true, Ordering::Relaxed);
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            process::exit(errors::exit_code(&CalendarError::Interrupted));
+        }
+    });
+    cancel
+}
+
+/// Notes that the stored events were just synced; a failure only costs `db --status` accuracy
+fn record_last_sync(db: &dyn database::EventStore) {
+    if let Err(e) = database::record_last_sync(db) {
+        warn!("Failed to record the sync time: {}", e);
+    }
+}
+
+/// Width of the terminal listings are printed to, or `None` when they go to a
+/// file or pipe and should not be truncated
+fn terminal_width(cli: &Cli) -> Option<usize> {
+    if cli.output.is_some() || !io::stdout().is_terminal() {
+        return None;
+    }
+
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+/// Reads the stored events for `--offline`, in place of fetching the calendar
+fn load_stored_events(db: &dyn database::EventStore) -> Result<Vec<models::Event>, CalendarError> {
+    let events = db.get_all_events()?;
+    info!("Offline: read {} stored events from the database", events.len());
+    Ok(events)
+}
+
+/// Applies the listing filters and order to events read from the database
+fn stored_listing(cli: &Cli, db_events: &[models::Event]) -> Vec<models::Event> {
+    let events = display::filter_events(db_events, &cli.filters, &cli.categories);
+    let mut events = display::filter_by_duration(events, cli.min_duration, cli.max_duration);
+    display::sort_events(&mut events, cli.sort, cli.reverse);
+    events
+}
+
+/// Writes how many events the `db` or `db-missing` listing would show, for
+/// `--count-only`; the whole filtered listing counts, not one page of it
+fn write_stored_count(out: &mut dyn Write, cli: &Cli, db: &dyn database::EventStore) -> Result<(), CalendarError> {
+    let db_events = match &cli.command {
+        Some(Commands::MissingApiId) => db.get_events_missing_api_id()?,
+        Some(Commands::Database { added_since: Some(window), .. }) => {
+            db.get_events_added_since(chrono::Utc::now() - *window)?
+        }
+        _ => db.get_all_events()?,
+    };
+    writeln!(out, "{}", stored_listing(cli, &db_events).len())?;
+    Ok(())
+}
+
+/// Reads events for `db-import`: a JSON array as written by `db-export`, or an
+/// iCalendar file
+///
+/// The format comes from the extension, falling back to the first character.
+fn read_import_file(path: &Path, options: ParseOptions) -> Result<Vec<models::Event>, CalendarError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        io::Error::new(e.kind(), format!("Failed to open {}: {}", path.display(), e))
+    })?;
+    let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase);
+    let is_json = match extension.as_deref() {
+        Some("json") => true,
+        Some("ics" | "ical") => false,
+        _ => contents.trim_start().starts_with('['),
+    };
+
+    if is_json {
+        serde_json::from_str(&contents)
+            .map_err(|e| CalendarError::ParseError(format!("Invalid event JSON in {}: {}", path.display(), e)))
+    } else {
+        calendar::parse_calendar_from_reader(contents.as_bytes(), options)
+    }
+}
+
+/// Reads the slugs for `lookup-batch`, one per line, skipping blank lines
+fn read_slugs(file: Option<&Path>) -> io::Result<Vec<String>> {
+    let contents = match file {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => io::read_to_string(io::stdin())?,
+    };
+
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Checks that a `--calendar` value looks like a Luma calendar ID (`cal-...`)
+fn parse_calendar_id(id: &str) -> Result<String, String> {
+    let id = id.trim();
+    let valid = id
+        .strip_prefix("cal-")
+        .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    if valid {
+        Ok(id.to_string())
+    } else {
+        Err(format!("expected a Luma calendar ID like 'cal-4dWxlBFjW9Cd6ou', got '{}'", id))
+    }
+}
+
+/// Parses an age such as `30m`, `12h`, `7d` or `2w` for `db --added-since`
+fn parse_age(age: &str) -> Result<chrono::Duration, String> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (amount, unit) = age.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("expected a number followed by m, h, d or w, got '{}'", age))?;
+
+    match unit {
+        "m" => Ok(chrono::Duration::minutes(amount)),
+        "h" => Ok(chrono::Duration::hours(amount)),
+        "d" => Ok(chrono::Duration::days(amount)),
+        "w" => Ok(chrono::Duration::weeks(amount)),
+        _ => Err(format!("unknown unit '{}' in '{}', expected m, h, d or w", unit, age)),
+    }
+}
+
+/// Parses a `--header` value of the form `Name: Value`
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("expected 'Name: Value', got '{}'", header))?;
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err(format!("missing header name in '{}'", header));
+    }
+
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+/// Default log filter; PostgreSQL server notices are only interesting when debugging
+const DEFAULT_LOG_FILTER: &str = "info,tokio_postgres=warn";
+
+/// Log filter for -vv, adding this crate's debug logs such as per-event API lookups
+const API_PROGRESS_LOG_FILTER: &str = "info,luma_calendar_cli=debug,tokio_postgres=warn";
+
+/// Sends progress logs to stderr, filtered by RUST_LOG unless `--quiet` or `-vv` is given
+///
+/// Event listings stay on stdout so they can be piped independently.
+fn init_logging(quiet: bool, verbose: u8) {
+    let filter = if quiet {
+        EnvFilter::new("warn")
+    } else if verbose >= 2 {
+        EnvFilter::new(API_PROGRESS_LOG_FILTER)
+    } else {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER))
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
+/// True when enrichment loops should draw a progress bar
+///
+/// At `-vv` each event is logged instead, which would break up the bar.
+fn show_progress(cli: &Cli) -> bool {
+    !cli.quiet && cli.verbose < 2
+}
+
+/// Creates a Luma API client using the configured rate limit and retry count
+///
+/// Fails up front when no usable API key is configured.
+fn api_client(cli: &Cli) -> Result<LumaApi, CalendarError> {
+    let api_client = LumaApi::with_rate_limit(cli.rate_limit).with_max_retries(cli.max_retries);
+    api_client.require_key()?;
+    Ok(api_client)
+}
+
+/// `doctor`: reports whether the database backend has the variables it needs
+fn check_database_env() -> Result<String, String> {
+    let missing = database::missing_env_vars();
+    if missing.is_empty() {
+        Ok("connection settings found".to_string())
+    } else {
+        Err(format!("set DATABASE_URL, or the missing {}", missing.join(", ")))
+    }
+}
+
+/// `doctor`: connects without touching the schema and reads the events table
+fn check_database(cli: &Cli, rt: &Handle) -> Result<String, String> {
+    let db = database::connect_db(rt, cli.insecure, cli.retention_days, database::SchemaInit::Never).map_err(|e| e.to_string())?;
+    check_event_table(db.as_ref())
+}
+
+/// `doctor`: reads the events table of a connected database
+fn check_event_table(db: &dyn database::EventStore) -> Result<String, String> {
+    let count = db
+        .get_event_count()
+        .map_err(|e| format!("{} (run `db-init` if the events table is missing)", e))?;
+    Ok(format!("connected, {} events stored", count))
+}
+
+/// `doctor`: checks that LUMA_API_KEY is set and accepted by the API
+fn check_api(cli: &Cli, rt: &Handle) -> Result<String, String> {
+    let api_client = api_client(cli).map_err(|e| e.to_string())?;
+    rt.block_on(api_client.check_access()).map_err(|e| e.to_string())?;
+    Ok("API key accepted".to_string())
+}
+
+/// `doctor`: fetches the calendar, bypassing the cache so nothing is written
+fn check_calendar(cli: &Cli) -> Result<String, String> {
+    let url = cli.calendar_url();
+    let fetch = FetchOptions { use_cache: false, ..cli.fetch_options() };
+    let events = calendar::fetch_and_parse_calendar(&url, cli.parse_options(), &fetch).map_err(|e| e.to_string())?;
+    Ok(format!("{} returned {} events", url, events.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::EventStore;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Parses a command line (without the program name), keeping the matches for `apply_config`
+    fn parse(args: &[&str]) -> (Cli, ArgMatches) {
+        let matches = Cli::command()
+            .try_get_matches_from(std::iter::once("lumabot").chain(args.iter().copied()))
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        (cli, matches)
+    }
+
+    fn config() -> Config {
+        toml::from_str(
+            r#"
+            limit = 25
+            rate_limit_ms = 200
+            timezone = "Europe/Berlin"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn config_values_fill_in_absent_flags() {
+        let (mut cli, matches) = parse(&[]);
+        cli.apply_config(config(), &matches).unwrap();
+
+        assert_eq!(cli.limit, 25);
+        assert_eq!(cli.rate_limit, 200);
+        assert_eq!(cli.timezone, Some(chrono_tz::Europe::Berlin));
+    }
+
+    #[test]
+    fn flags_override_config_values() {
+        let (mut cli, matches) = parse(&["--limit", "5", "--timezone", "Asia/Tokyo"]);
+        cli.apply_config(config(), &matches).unwrap();
+
+        assert_eq!(cli.limit, 5);
+        assert_eq!(cli.timezone, Some(chrono_tz::Asia::Tokyo));
+        assert_eq!(cli.rate_limit, 200);
+    }
+
+    #[test]
+    fn invalid_config_timezones_are_rejected() {
+        let (mut cli, matches) = parse(&[]);
+        let config = Config { timezone: Some("Mars/Olympus".to_string()), ..Config::default() };
+
+        assert!(cli.apply_config(config, &matches).is_err());
+    }
+
+    #[test]
+    fn all_lifts_the_limit() {
+        assert_eq!(parse(&["--limit", "5"]).0.limit(), 5);
+        assert_eq!(parse(&["--limit", "0"]).0.limit(), 0);
+        assert_eq!(parse(&["--all"]).0.limit(), 0);
+    }
+
+    #[test]
+    fn json_listings_can_be_written_to_an_output_file() {
+        let path = std::env::temp_dir().join(format!("lumabot-output-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "stale contents that must be truncated away, and then some more").unwrap();
+        let path_arg = path.to_str().unwrap().to_string();
+        let (cli, _) = parse(&["--output", &path_arg, "--format", "json"]);
+        let start = chrono::DateTime::parse_from_rfc3339("2030-01-15T18:00:00Z").unwrap().to_utc();
+        let events = [models::Event::new("Rust Meetup".to_string(), None, None, start, start, None)];
+
+        let mut out = open_output(&cli).unwrap();
+        let options = DisplayOptions { format: cli.format, ..DisplayOptions::default() };
+        display::display_events(&mut *out, &events, cli.limit(), options).unwrap();
+        drop(out);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&written).unwrap();
+        assert_eq!(json[0]["summary"], "Rust Meetup");
+        assert_eq!(json.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn empty_calendars_get_one_friendly_message() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/empty.ics");
+        let path = std::env::temp_dir().join(format!("lumabot-empty-{}.txt", uuid::Uuid::new_v4()));
+        let (cli, _) = parse(&["--file", fixture.to_str().unwrap(), "--output", path.to_str().unwrap()]);
+        let runtime = Runtime::new().unwrap();
+
+        run(&cli, runtime.handle()).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(written.contains("No events found in this calendar."), "{}", written);
+        assert!(!written.contains("Upcoming Events"), "{}", written);
+    }
+
+    #[test]
+    fn bash_completions_list_the_subcommands() {
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut Cli::command(), env!("CARGO_BIN_NAME"), &mut script);
+        let script = String::from_utf8(script).unwrap();
+
+        for subcommand in ["today", "week", "sync", "db-delete", "completions"] {
+            assert!(script.contains(subcommand), "missing {}", subcommand);
+        }
+    }
+
+    #[test]
+    fn completions_are_generated_for_every_shell() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), env!("CARGO_BIN_NAME"), &mut script);
+            assert!(!script.is_empty(), "{}", shell);
+        }
+    }
+
+    /// A path in the temp directory that no other test run uses
+    fn temp_path(extension: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("lumabot-test-{}.{}", uuid::Uuid::new_v4(), extension))
+    }
+
+    fn stored_event(summary: &str, start: &str, api_id: Option<&str>) -> models::Event {
+        let start = chrono::DateTime::parse_from_rfc3339(start).unwrap().to_utc();
+        let mut event = models::Event::new(summary.to_string(), None, None, start, start + chrono::Duration::hours(1), None);
+        event.api_id = api_id.map(str::to_string);
+        event
+    }
+
+    fn memory_db() -> database::SqliteDatabase {
+        let db = database::SqliteDatabase::open(":memory:", calendar::DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        db
+    }
+
+    #[test]
+    fn exported_databases_import_back_unchanged() {
+        let source = memory_db();
+        let mut meetup = stored_event("Rust Meetup", "2030-01-15T18:00:00Z", Some("evt-123"));
+        meetup.location = Some("Community Hall".to_string());
+        // Past the retention window, which an export must still include
+        let past = stored_event("Last Year's Meetup", "2020-01-15T18:00:00Z", None);
+        source.save_events(&[meetup, past]).unwrap();
+
+        for (extension, format) in [("json", DumpFormat::Json), ("ics", DumpFormat::Ics)] {
+            let stored = source.get_all_events_unfiltered().unwrap();
+            let stored: Vec<&models::Event> = stored.iter().collect();
+            let path = temp_path(extension);
+            let mut file = File::create(&path).unwrap();
+            match format {
+                DumpFormat::Json => export::write_json(&stored, &mut file).unwrap(),
+                DumpFormat::Ics => export::write_ics(&stored, &mut file).unwrap(),
+            }
+            drop(file);
+
+            let options = ParseOptions { retention_days: 365 * 100, ..ParseOptions::default() };
+            let imported = read_import_file(&path, options).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            let target = memory_db();
+            target.save_events(&imported).unwrap();
+            let restored = target.get_all_events_unfiltered().unwrap();
+            assert_eq!(restored.len(), 2, "{}", extension);
+            for (restored, original) in restored.iter().zip(&stored) {
+                assert_eq!(restored.summary, original.summary, "{}", extension);
+                assert_eq!(restored.start, original.start, "{}", extension);
+                assert_eq!(restored.location, original.location, "{}", extension);
+            }
+            if format == DumpFormat::Json {
+                assert_eq!(restored[1].api_id.as_deref(), Some("evt-123"));
+                assert_eq!(restored[1].event_uid, stored[1].event_uid);
+            }
+        }
+    }
+
+    #[test]
+    fn imported_json_keeps_api_ids() {
+        let events = [
+            stored_event("Rust Meetup", "2030-01-15T18:00:00Z", Some("evt-123")),
+            stored_event("Embedded Workshop", "2030-01-16T18:00:00Z", None),
+        ];
+        let path = temp_path("json");
+        std::fs::write(&path, serde_json::to_string(&events).unwrap()).unwrap();
+
+        let imported = read_import_file(&path, ParseOptions::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let db = memory_db();
+        let first = db.save_events(&imported).unwrap();
+        let again = db.save_events(&imported).unwrap();
+
+        assert_eq!((first.inserted, first.updated), (2, 0));
+        assert_eq!(again.inserted, 0);
+        let stored = db.get_all_events().unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].api_id.as_deref(), Some("evt-123"));
+        assert_eq!(stored[1].api_id, None);
+    }
+
+    #[test]
+    fn import_files_without_an_extension_are_detected_by_content() {
+        let path = temp_path("txt");
+        let events = [stored_event("Rust Meetup", "2030-01-15T18:00:00Z", Some("evt-123"))];
+        std::fs::write(&path, serde_json::to_string(&events).unwrap()).unwrap();
+        let from_json = read_import_file(&path, ParseOptions::default()).unwrap();
+
+        let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Rust Meetup\r\n\
+                        DTSTART:20300115T180000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        std::fs::write(&path, calendar).unwrap();
+        let from_ics = read_import_file(&path, ParseOptions::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(from_json[0].api_id.as_deref(), Some("evt-123"));
+        assert_eq!(from_ics[0].summary, "Rust Meetup");
+    }
+
+    #[test]
+    fn verbosity_counts_repeated_flags() {
+        assert_eq!(parse(&[]).0.verbose, 0);
+        assert_eq!(parse(&["-v"]).0.verbose, 1);
+        assert_eq!(parse(&["-vv"]).0.verbose, 2);
+        assert_eq!(parse(&["-v", "--verbose", "-v"]).0.verbose, 3);
+    }
+
+    #[test]
+    fn the_progress_bar_gives_way_to_per_request_logs_at_vv() {
+        assert!(show_progress(&parse(&[]).0));
+        assert!(show_progress(&parse(&["-v"]).0));
+        assert!(!show_progress(&parse(&["-vv"]).0));
+        assert!(!show_progress(&parse(&["--quiet"]).0));
+    }
+
+    #[test]
+    fn calendar_id_expands_to_its_ics_url() {
+        let (cli, _) = parse(&["--calendar", "cal-4dWxlBFjW9Cd6ou"]);
+        assert_eq!(cli.calendar_url(), "https://api.lu.ma/ics/get?entity=calendar&id=cal-4dWxlBFjW9Cd6ou");
+
+        let (cli, _) = parse(&["--url", "https://example.com/feed.ics", "--calendar", "cal-abc123"]);
+        assert_eq!(cli.calendar_url(), "https://api.lu.ma/ics/get?entity=calendar&id=cal-abc123");
+
+        let (cli, _) = parse(&["--ics-url", "https://example.com/feed.ics"]);
+        assert_eq!(cli.calendar_url(), "https://example.com/feed.ics");
+    }
+
+    #[test]
+    fn calendar_ids_must_look_like_luma_ids() {
+        for id in ["4dWxlBFjW9Cd6ou", "cal-", "evt-4dWxlBFjW9Cd6ou", "cal-abc/def"] {
+            assert!(Cli::try_parse_from(["lumabot", "--calendar", id]).is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn fetch_failures_are_reported_as_json() {
+        // Nothing listens on a port that was just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let url = format!("http://127.0.0.1:{}/calendar.ics", port);
+        let (cli, _) = parse(&["--url", &url, "--fetch-retries", "0", "--format", "json"]);
+        let runtime = Runtime::new().unwrap();
+
+        let error = run(&cli, runtime.handle()).unwrap_err();
+
+        let report: serde_json::Value = serde_json::from_str(&error_report(&error, cli.format)).unwrap();
+        assert_eq!(report["error"]["kind"], "fetch");
+        assert_eq!(report["error"]["message"], error.to_string());
+        assert_eq!(errors::exit_code(&error), 2);
+    }
+
+    #[test]
+    fn doctor_reads_the_event_table() {
+        let db = memory_db();
+        db.save_events(&[stored_event("Rust Meetup", "2030-01-15T18:00:00Z", None)]).unwrap();
+        assert_eq!(check_event_table(&db).unwrap(), "connected, 1 events stored");
+
+        let uninitialized = database::SqliteDatabase::open(":memory:", calendar::DEFAULT_RETENTION_DAYS).unwrap();
+        assert!(check_event_table(&uninitialized).unwrap_err().contains("run `db-init`"));
+    }
+
+    /// Serves `response` to every GET, returning the runtime that keeps the server alive
+    fn serve(response: ResponseTemplate) -> (Runtime, MockServer) {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(response)
+                .mount(&server)
+                .await;
+            server
+        });
+        (runtime, server)
+    }
+
+    #[test]
+    fn doctor_fetches_the_calendar() {
+        let fixture = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/meetups.ics")).unwrap();
+        let (_runtime, server) = serve(ResponseTemplate::new(200).set_body_raw(fixture, "text/calendar"));
+        let (cli, _) = parse(&["--url", &server.uri()]);
+        assert_eq!(check_calendar(&cli).unwrap(), format!("{} returned 2 events", server.uri()));
+
+        let (_runtime, server) = serve(ResponseTemplate::new(500));
+        let (cli, _) = parse(&["--url", &server.uri()]);
+        assert!(check_calendar(&cli).unwrap_err().contains("500"));
+    }
+
+    #[test]
+    fn offline_listings_read_the_stored_events() {
+        let db = memory_db();
+        db.save_events(&[
+            stored_event("Rust Meetup", "2030-01-15T18:00:00Z", None),
+            stored_event("Go Meetup", "2030-01-22T18:00:00Z", None),
+        ])
+        .unwrap();
+
+        let events = load_stored_events(&db).unwrap();
+        let summaries: Vec<&str> = events.iter().map(|e| e.summary.as_str()).collect();
+        assert_eq!(summaries, ["Rust Meetup", "Go Meetup"]);
+    }
+
+    /// Runs the default listing over the given fixture, returning what it wrote
+    fn list_fixture(fixture: &str, extra_args: &[&str]) -> String {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(fixture);
+        let output = temp_path("txt");
+        let mut args = vec!["--file", fixture.to_str().unwrap(), "--output", output.to_str().unwrap()];
+        args.extend_from_slice(extra_args);
+        let (cli, _) = parse(&args);
+
+        run(&cli, Runtime::new().unwrap().handle()).unwrap();
+
+        let written = std::fs::read_to_string(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        written
+    }
+
+    #[test]
+    fn count_only_matches_the_full_listing() {
+        for filters in [&[][..], &["--min-duration", "180"][..]] {
+            let listing = list_fixture("mac_line_endings.ics", &[&["--format", "plain", "--all"], filters].concat());
+            let count = list_fixture("mac_line_endings.ics", &[&["--count-only", "--limit", "1"], filters].concat());
+
+            assert_eq!(count.trim(), listing.lines().count().to_string(), "{:?}", filters);
+        }
+        assert_eq!(list_fixture("mac_line_endings.ics", &["--count-only"]).trim(), "3");
+        assert_eq!(list_fixture("mac_line_endings.ics", &["--count-only", "--min-duration", "180"]).trim(), "1");
+
+        // The database listings count the same events, whatever page was asked for
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mac_line_endings.ics");
+        let mut events = calendar::parse_calendar_file(&fixture, parse(&[]).0.parse_options()).unwrap();
+        events[0].api_id = Some("evt-1".to_string());
+        let db = memory_db();
+        db.save_events(&events).unwrap();
+        let stored_count = |args: &[&str]| {
+            let (cli, _) = parse(args);
+            let mut out = Vec::new();
+            write_stored_count(&mut out, &cli, &db).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        for filters in [&[][..], &["--min-duration", "180"][..]] {
+            let count = list_fixture("mac_line_endings.ics", &[&["--count-only"], filters].concat());
+            assert_eq!(stored_count(&[filters, &["--count-only", "db"]].concat()), count, "{:?}", filters);
+            assert_eq!(
+                stored_count(&[filters, &["--count-only", "db", "--all", "--limit", "1", "--offset", "1"]].concat()),
+                count,
+                "{:?}",
+                filters
+            );
+        }
+        assert_eq!(stored_count(&["--count-only", "db-missing"]).trim(), "2");
+    }
+
+    #[test]
+    fn force_and_incremental_cannot_be_combined() {
+        assert!(Cli::try_parse_from(["lumabot", "sync", "--incremental", "--force"]).is_err());
+        assert!(Cli::try_parse_from(["lumabot", "sync", "--force"]).is_ok());
+    }
+
+    #[test]
+    fn database_commands_skip_the_calendar_fetch() {
+        for args in [&["db"][..], &["db-missing"], &["db-delete", "--uid", "uid-1"], &["clear"]] {
+            let (cli, _) = parse(args);
+            assert!(!cli.command.unwrap().needs_calendar(), "{:?}", args);
+        }
+        let (cli, _) = parse(&["sync"]);
+        assert!(cli.command.unwrap().needs_calendar());
+    }
+}
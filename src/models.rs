@@ -2,6 +2,64 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
+use url::Url;
+
+/// The STATUS of a VEVENT
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventStatus {
+    #[default]
+    Confirmed,
+    Tentative,
+    Cancelled,
+}
+
+impl EventStatus {
+    /// Parses an iCal STATUS value, treating unknown values as confirmed
+    pub fn from_ical(value: &str) -> Self {
+        match value.trim().to_uppercase().as_str() {
+            "TENTATIVE" => EventStatus::Tentative,
+            "CANCELLED" => EventStatus::Cancelled,
+            _ => EventStatus::Confirmed,
+        }
+    }
+
+    /// The iCal STATUS value, as read back by `from_ical`
+    pub fn as_ical(self) -> &'static str {
+        match self {
+            EventStatus::Confirmed => "CONFIRMED",
+            EventStatus::Tentative => "TENTATIVE",
+            EventStatus::Cancelled => "CANCELLED",
+        }
+    }
+}
+
+/// The calendar-level METHOD an event was delivered with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CalendarMethod {
+    /// A subscribable calendar (also assumed when METHOD is absent)
+    #[default]
+    Publish,
+    /// An invitation, as sent by email
+    Request,
+    /// A reply from an attendee to an invitation
+    Reply,
+    /// A cancellation of a previously sent invitation
+    Cancel,
+}
+
+impl CalendarMethod {
+    /// Parses an iCal METHOD value, treating unknown values as publish
+    pub fn from_ical(value: &str) -> Self {
+        match value.trim().to_uppercase().as_str() {
+            "REQUEST" => CalendarMethod::Request,
+            "REPLY" => CalendarMethod::Reply,
+            "CANCEL" => CalendarMethod::Cancel,
+            _ => CalendarMethod::Publish,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -11,11 +69,88 @@ pub struct Event {
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
     pub url: Option<String>,
+    /// Key the database dedups on: the feed's own UID when it has one (see
+    /// `ical_uid`), otherwise derived from the content by `content_uid`.
+    /// Identical on every platform and toolchain; changing its derivation
+    /// duplicates stored rows.
     pub event_uid: String,
     pub api_id: Option<String>,
+    /// True for date-only (VALUE=DATE) events with no time component
+    #[serde(default)]
+    pub all_day: bool,
+    /// The event STATUS (confirmed, tentative or cancelled)
+    #[serde(default)]
+    pub status: EventStatus,
+    /// Display name (or address) of the ORGANIZER
+    #[serde(default)]
+    pub organizer: Option<String>,
+    /// Display names (or addresses) of every ATTENDEE
+    #[serde(default)]
+    pub attendees: Vec<String>,
+    /// Venue coordinates from GEO, as (latitude, longitude)
+    #[serde(default)]
+    pub geo: Option<(f64, f64)>,
+    /// Revision number from SEQUENCE, incremented by the organizer on each change
+    #[serde(default)]
+    pub sequence: Option<i32>,
+    /// When the event was last changed upstream, from LAST-MODIFIED
+    #[serde(default)]
+    pub last_modified: Option<DateTime<Utc>>,
+    /// METHOD of the calendar the event came from (publish unless it was an invitation)
+    #[serde(default)]
+    pub method: CalendarMethod,
+    /// Links found in the DESCRIPTION other than `url` (maps, video calls, ...)
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// Tags from CATEGORIES, e.g. "Tech" or "Social"
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// When the row was first stored; only set on events read from the database
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// The VEVENT's own UID property, as published by the feed
+    #[serde(default)]
+    pub ical_uid: Option<String>,
+    /// RECURRENCE-ID of a modified instance of a recurring event, which shares
+    /// `ical_uid` with its series
+    #[serde(default)]
+    pub recurrence_id: Option<String>,
+    /// The end given by the feed, when `end` was extended by the default
+    /// duration because the event had none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_end: Option<DateTime<Utc>>,
+    /// IDs older releases may have stored the event under; see `legacy_uids`
+    #[serde(skip)]
+    legacy_uids: Vec<String>,
 }
 
 impl Event {
+    /// Names of the fields in serialized events, in declaration order
+    pub const FIELDS: &'static [&'static str] = &[
+        "summary",
+        "description",
+        "location",
+        "start",
+        "end",
+        "url",
+        "event_uid",
+        "api_id",
+        "all_day",
+        "status",
+        "organizer",
+        "attendees",
+        "geo",
+        "sequence",
+        "last_modified",
+        "method",
+        "links",
+        "categories",
+        "created_at",
+        "ical_uid",
+        "recurrence_id",
+        "original_end",
+    ];
+
     pub fn new(
         summary: String,
         description: Option<String>,
@@ -24,43 +159,138 @@ impl Event {
         end: DateTime<Utc>,
         url: Option<String>,
     ) -> Self {
-        // Generate a deterministic ID for the event based on its content
-        // This will create the same ID for the same event each time
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
+        let mut event = Self {
+            summary,
+            description,
+            location,
+            start,
+            end,
+            url,
+            event_uid: String::new(),
+            api_id: None,
+            all_day: false,
+            status: EventStatus::default(),
+            organizer: None,
+            attendees: Vec::new(),
+            geo: None,
+            sequence: None,
+            last_modified: None,
+            method: CalendarMethod::default(),
+            links: Vec::new(),
+            categories: Vec::new(),
+            created_at: None,
+            ical_uid: None,
+            recurrence_id: None,
+            original_end: None,
+            legacy_uids: Vec::new(),
+        };
+        event.event_uid = event.content_uid();
+        event.validate();
+        event
+    }
+    
+    /// Derives an ID for the event from its content
+    ///
+    /// This creates the same ID for the same event each time, across builds and
+    /// Rust versions (unlike DefaultHasher). It is the `event_uid` of events
+    /// whose feed gives them no UID of their own.
+    pub fn content_uid(&self) -> String {
+        let normalize = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|v| Self::clean_string(v).split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase())
+                .unwrap_or_default()
+        };
+        let mut hasher = StableHasher::new();
+        hasher.field(self.summary.as_bytes());
+        hasher.field(&self.start.timestamp().to_le_bytes());
+        // The end, place and link tell apart same-titled sessions starting together;
+        // the description is left out so that editing it updates the same event.
+        // The feed's own end is used, so padding it with --default-duration
+        // doesn't change the ID
+        let end = self.original_end.unwrap_or(self.end);
+        hasher.field(&end.timestamp().to_le_bytes());
+        hasher.field(normalize(&self.location).as_bytes());
+        hasher.field(normalize(&self.url).trim_end_matches('/').as_bytes());
         
+        format!("{}-{}-{:x}", self.summary.replace(" ", "_"), self.start.timestamp(), hasher.finish())
+    }
+
+    /// The ID older releases derived with DefaultHasher, from the summary, start,
+    /// description and location as they appeared in the feed
+    fn default_hasher_uid(
+        summary: &str,
+        start: DateTime<Utc>,
+        description: Option<&str>,
+        location: Option<&str>,
+    ) -> String {
+        use std::collections::hash_map::DefaultHasher;
+
         let mut hasher = DefaultHasher::new();
         summary.hash(&mut hasher);
         start.timestamp().hash(&mut hasher);
-        if let Some(desc) = &description {
+        if let Some(desc) = description {
             desc.hash(&mut hasher);
         }
-        if let Some(loc) = &location {
+        if let Some(loc) = location {
             loc.hash(&mut hasher);
         }
-        
-        let hash = hasher.finish();
-        
-        let event_uid = format!("{}-{}-{:x}", 
-                               summary.replace(" ", "_"), 
-                               start.timestamp(),
-                               hash);
 
-        Self {
-            summary,
-            description,
-            location,
-            start,
-            end,
-            url,
-            event_uid,
-            api_id: None,
+        format!("{}-{}-{:x}", summary.replace(" ", "_"), start.timestamp(), hasher.finish())
+    }
+
+    /// Works out the IDs older releases may have stored this event under
+    ///
+    /// Called while parsing, once the feed UID is set: the old IDs hashed the
+    /// raw (still escaped) SUMMARY, DESCRIPTION and LOCATION values, and the
+    /// content UID was derived before a default URL was filled in.
+    pub fn record_legacy_uids(&mut self, summary: &str, description: Option<&str>, location: Option<&str>) {
+        let mut uids = Vec::new();
+        if self.ical_uid.is_some() {
+            uids.push(self.content_uid());
         }
+        uids.push(Self::default_hasher_uid(summary, self.start, description, location));
+        uids.retain(|uid| *uid != self.event_uid);
+        uids.dedup();
+        self.legacy_uids = uids;
+    }
+
+    /// IDs older releases may have stored this event under, most recent first
+    ///
+    /// A row found under one of these should be moved to `event_uid` rather than
+    /// left behind as a duplicate. Only parsed events have any.
+    pub fn legacy_uids(&self) -> &[String] {
+        &self.legacy_uids
+    }
+
+    /// Identifies the event by the UID its feed gave it instead of by its content
+    ///
+    /// A modified instance of a recurring event shares the UID of the series, so
+    /// its RECURRENCE-ID is appended to keep the two apart.
+    pub fn set_ical_uid(&mut self, uid: &str, recurrence_id: Option<&str>) {
+        self.event_uid = match recurrence_id {
+            Some(recurrence_id) => format!("{}#{}", uid, recurrence_id),
+            None => uid.to_string(),
+        };
+        self.ical_uid = Some(uid.to_string());
+        self.recurrence_id = recurrence_id.map(str::to_string);
+    }
+    
+    // Make sure the event never ends before it starts, clamping the end to the
+    // start. Returns false if the times had to be corrected.
+    pub fn validate(&mut self) -> bool {
+        if self.end >= self.start {
+            return true;
+        }
+        
+        self.end = self.start;
+        false
     }
     
     // Function removed to eliminate unused code warning
     
     // Create an event with an existing UID and API ID
+    #[allow(clippy::too_many_arguments)]
     pub fn with_uid_and_api_id(
         summary: String,
         description: Option<String>,
@@ -80,6 +310,21 @@ impl Event {
             url,
             event_uid,
             api_id,
+            all_day: false,
+            status: EventStatus::default(),
+            organizer: None,
+            attendees: Vec::new(),
+            geo: None,
+            sequence: None,
+            last_modified: None,
+            method: CalendarMethod::default(),
+            links: Vec::new(),
+            categories: Vec::new(),
+            created_at: None,
+            ical_uid: None,
+            recurrence_id: None,
+            original_end: None,
+            legacy_uids: Vec::new(),
         }
     }
     
@@ -96,42 +341,85 @@ impl Event {
              .to_string()
     }
     
-    /// Extract the slug from a Luma URL if available
-    pub fn extract_slug(&self) -> Option<String> {
-        if let Some(url) = &self.url {
-            // Clean the URL first
-            let clean_url = Self::clean_string(url);
-            
-            if clean_url.contains("lu.ma") {
-                // Try to extract the slug after the last slash
-                if let Some(slug) = clean_url.split('/').last() {
-                    if !slug.is_empty() {
-                        // Make sure the extracted slug is also cleaned
-                        return Some(Self::clean_string(slug));
-                    }
-                }
-                
-                // For URLs with /e/ pattern
-                if clean_url.contains("/e/") {
-                    if let Some(slug) = clean_url.split("/e/").last() {
-                        if !slug.is_empty() {
-                            // Make sure the extracted slug is also cleaned
-                            return Some(Self::clean_string(slug));
-                        }
-                    }
+    /// Decode iCal TEXT escape sequences (`\n`, `\,`, `\;`, `\\`)
+    pub fn unescape_ical(input: &str) -> String {
+        let mut output = String::with_capacity(input.len());
+        let mut chars = input.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                output.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('n') | Some('N') => output.push('\n'),
+                Some(',') => output.push(','),
+                Some(';') => output.push(';'),
+                Some('\\') => output.push('\\'),
+                Some(other) => {
+                    // Unknown escape: keep it verbatim
+                    output.push('\\');
+                    output.push(other);
                 }
+                None => output.push('\\'),
             }
         }
-        None
+
+        output
+    }
+
+    /// Extract the slug from a Luma URL if available
+    pub fn extract_slug(&self) -> Option<String> {
+        let clean_url = Self::clean_string(self.url.as_ref()?);
+        let parsed = Url::parse(&clean_url).ok()?;
+        
+        if !parsed.host_str()?.ends_with("lu.ma") {
+            return None;
+        }
+        
+        // The slug is the last non-empty path segment, which also covers the
+        // /e/{uid} form; the query string and fragment are never part of it
+        parsed
+            .path_segments()?
+            .rev()
+            .find(|segment| !segment.is_empty() && *segment != "e")
+            .map(Self::clean_string)
     }
     
     // Function removed to eliminate unused code warning
     
+    /// True if the event is tagged with `category` (case-insensitive)
+    pub fn has_category(&self, category: &str) -> bool {
+        let category = category.trim().to_lowercase();
+        self.categories.iter().any(|c| c.to_lowercase() == category)
+    }
+    
+    /// True if the event arrived as an invitation (METHOD:REQUEST) rather than from a subscription
+    pub fn is_invitation(&self) -> bool {
+        self.method == CalendarMethod::Request
+    }
+    
     // Calculate the duration of the event in minutes
     pub fn duration_minutes(&self) -> i64 {
         self.end.signed_duration_since(self.start).num_minutes()
     }
     
+    // Duration for people: "45m", "2h 30m" or "3d 2h", keeping the two largest
+    // units. An event ending before it starts is shown as "0m".
+    pub fn duration_human(&self) -> String {
+        let total = self.duration_minutes().max(0);
+        let (days, hours, minutes) = (total / (24 * 60), total / 60 % 24, total % 60);
+        
+        match (days, hours, minutes) {
+            (0, 0, m) => format!("{}m", m),
+            (0, h, 0) => format!("{}h", h),
+            (0, h, m) => format!("{}h {}m", h, m),
+            (d, 0, _) => format!("{}d", d),
+            (d, h, _) => format!("{}d {}h", d, h),
+        }
+    }
+    
     // Update or set the URL for this event
     #[allow(dead_code)]
     pub fn with_url(mut self, url: Option<String>) -> Self {
@@ -174,3 +462,194 @@ impl Hash for Event {
         // We don't hash optional fields as they might be None
     }
 }
+
+/// 64-bit FNV-1a, used for event UIDs (and cache file names) because its
+/// output is fixed by the algorithm rather than by the standard library version
+pub(crate) struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub(crate) fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Hashes one field, length-prefixed so that ("ab", "c") and ("a", "bc") differ
+    pub(crate) fn field(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn unescape_ical_decodes_each_escape() {
+        assert_eq!(Event::unescape_ical(r"line one\nline two"), "line one\nline two");
+        assert_eq!(Event::unescape_ical(r"LINE\NBREAK"), "LINE\nBREAK");
+        assert_eq!(Event::unescape_ical(r"Drinks\, snacks"), "Drinks, snacks");
+        assert_eq!(Event::unescape_ical(r"Room 1\; Floor 2"), "Room 1; Floor 2");
+        assert_eq!(Event::unescape_ical(r"C:\\path"), r"C:\path");
+    }
+
+    #[test]
+    fn unescape_ical_keeps_unknown_and_trailing_backslashes() {
+        assert_eq!(Event::unescape_ical(r"\x marks"), r"\x marks");
+        assert_eq!(Event::unescape_ical(r"ends with\"), r"ends with\");
+    }
+
+    fn slug_of(url: &str) -> Option<String> {
+        let start = Utc.with_ymd_and_hms(2030, 1, 15, 18, 0, 0).unwrap();
+        Event::new("Meetup".to_string(), None, None, start, start, Some(url.to_string())).extract_slug()
+    }
+
+    #[test]
+    fn extract_slug_ignores_query_strings_and_fragments() {
+        assert_eq!(slug_of("https://lu.ma/abc123?utm_source=foo").as_deref(), Some("abc123"));
+        assert_eq!(slug_of("https://lu.ma/abc123#details").as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn extract_slug_ignores_trailing_slashes() {
+        assert_eq!(slug_of("https://lu.ma/abc123/").as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn extract_slug_reads_the_uid_from_e_urls() {
+        assert_eq!(slug_of("https://lu.ma/e/evt-xyz").as_deref(), Some("evt-xyz"));
+        assert_eq!(slug_of("https://lu.ma/e/evt-xyz/?ref=home").as_deref(), Some("evt-xyz"));
+    }
+
+    #[test]
+    fn extract_slug_rejects_other_hosts() {
+        assert_eq!(slug_of("https://example.com/abc123"), None);
+        assert_eq!(slug_of("not a url"), None);
+    }
+
+    fn lasting(minutes: i64) -> Event {
+        let start = Utc.with_ymd_and_hms(2030, 1, 15, 18, 0, 0).unwrap();
+        let end = start + chrono::Duration::minutes(minutes);
+        Event::new("Meetup".to_string(), None, None, start, end, None)
+    }
+
+    #[test]
+    fn duration_human_shows_minutes_under_an_hour() {
+        assert_eq!(lasting(0).duration_human(), "0m");
+        assert_eq!(lasting(45).duration_human(), "45m");
+    }
+
+    #[test]
+    fn duration_human_shows_hours_and_minutes() {
+        assert_eq!(lasting(60).duration_human(), "1h");
+        assert_eq!(lasting(150).duration_human(), "2h 30m");
+        assert_eq!(lasting(23 * 60 + 59).duration_human(), "23h 59m");
+    }
+
+    #[test]
+    fn duration_human_shows_days_and_hours_for_multi_day_events() {
+        assert_eq!(lasting(24 * 60).duration_human(), "1d");
+        assert_eq!(lasting(3 * 24 * 60 + 2 * 60 + 15).duration_human(), "3d 2h");
+    }
+
+    #[test]
+    fn duration_human_treats_negative_durations_as_zero() {
+        // Event::new clamps the end, so invert the times afterwards
+        let mut inverted = lasting(30);
+        inverted.end = inverted.start - chrono::Duration::minutes(30);
+        assert_eq!(inverted.duration_human(), "0m");
+    }
+
+    fn office_hours(end_minutes: i64, location: Option<&str>) -> Event {
+        let start = Utc.with_ymd_and_hms(2030, 1, 15, 18, 0, 0).unwrap();
+        let end = start + chrono::Duration::minutes(end_minutes);
+        Event::new("Office Hours".to_string(), None, location.map(str::to_string), start, end, None)
+    }
+
+    #[test]
+    fn same_titled_events_starting_together_get_distinct_uids() {
+        let room_a = office_hours(60, Some("Room A"));
+
+        assert_ne!(room_a.event_uid, office_hours(60, Some("Room B")).event_uid);
+        assert_ne!(room_a.event_uid, office_hours(30, Some("Room A")).event_uid);
+        assert_ne!(room_a.event_uid, office_hours(60, None).event_uid);
+    }
+
+    #[test]
+    fn the_same_event_keeps_its_uid() {
+        let event = office_hours(60, Some("Room A"));
+
+        let mut edited = office_hours(60, Some("  room   a "));
+        edited.description = Some("Now with snacks".to_string());
+
+        assert_eq!(event.event_uid, office_hours(60, Some("Room A")).event_uid);
+        assert_eq!(event.content_uid(), edited.content_uid());
+    }
+
+    #[test]
+    fn legacy_uids_exclude_the_current_uid() {
+        let mut event = office_hours(60, Some("Room A"));
+        event.record_legacy_uids("Office Hours", None, Some("Room A"));
+        let default_hasher_uid = Event::default_hasher_uid("Office Hours", event.start, None, Some("Room A"));
+        assert_eq!(event.legacy_uids(), [default_hasher_uid.as_str()]);
+
+        let mut from_feed = office_hours(60, Some("Room A"));
+        from_feed.set_ical_uid("office-hours@lu.ma", None);
+        from_feed.record_legacy_uids("Office Hours", None, Some("Room A"));
+        assert_eq!(from_feed.legacy_uids(), [event.content_uid(), default_hasher_uid]);
+    }
+
+    #[test]
+    fn stable_hasher_is_fnv_1a() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = StableHasher::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn a_known_event_gets_a_known_uid() {
+        let start = Utc.with_ymd_and_hms(2030, 1, 15, 18, 0, 0).unwrap();
+        let event = Event::new(
+            "Rust Meetup".to_string(),
+            Some("Talks and pizza".to_string()),
+            Some("Community Hall".to_string()),
+            start,
+            start + chrono::Duration::hours(2),
+            Some("https://lu.ma/rust-meetup".to_string()),
+        );
+
+        assert_eq!(event.event_uid, "Rust_Meetup-1894730400-2fe6b3496d8224b");
+    }
+
+    #[test]
+    fn validate_clamps_an_end_before_the_start() {
+        let mut inverted = lasting(30);
+        inverted.end = inverted.start - chrono::Duration::minutes(30);
+        assert!(!inverted.validate());
+        assert_eq!(inverted.end, inverted.start);
+
+        let mut valid = lasting(30);
+        assert!(valid.validate());
+        assert_eq!(valid.duration_minutes(), 30);
+    }
+}
@@ -0,0 +1,154 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::{self, IsTerminal};
+
+/// Tally of an API enrichment run, driving its progress bar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EnrichCounts {
+    /// Events the run will go through
+    pub total: usize,
+    /// Events gone through so far, whatever the outcome
+    pub processed: usize,
+    /// Events whose API ID was looked up and saved
+    pub enriched: usize,
+    /// Failed lookups or saves
+    pub errors: usize,
+    /// Enriched events whose stored API ID was replaced by a different one
+    pub changed: usize,
+}
+
+impl EnrichCounts {
+    pub fn new(total: usize) -> Self {
+        Self { total, ..Self::default() }
+    }
+
+    /// Records an event whose API ID was resolved and saved
+    pub fn success(&mut self) {
+        self.processed += 1;
+        self.enriched += 1;
+    }
+
+    /// Records an event whose stored API ID was replaced by a different one
+    pub fn replaced(&mut self) {
+        self.success();
+        self.changed += 1;
+    }
+
+    /// Records an event whose lookup or save failed
+    pub fn error(&mut self) {
+        self.processed += 1;
+        self.errors += 1;
+    }
+
+    /// Records an event that needed no lookup
+    pub fn skip(&mut self) {
+        self.processed += 1;
+    }
+
+    /// Bar message, e.g. "3/10 enriched, 1 errors"
+    pub fn message(&self) -> String {
+        format!("{}/{} enriched, {} errors", self.enriched, self.total, self.errors)
+    }
+}
+
+/// Enrichment progress, drawn as a bar on stderr when it is a terminal
+///
+/// The bar is cleared when this is finished or dropped, so a summary printed
+/// afterwards starts on a clean line.
+pub struct EnrichProgress {
+    counts: EnrichCounts,
+    bar: Option<ProgressBar>,
+}
+
+impl EnrichProgress {
+    /// Starts tracking `total` events; no bar is drawn unless `show` is set and
+    /// stderr is a terminal
+    pub fn new(total: usize, show: bool) -> Self {
+        let bar = (show && io::stderr().is_terminal()).then(|| {
+            let bar = ProgressBar::new(total as u64);
+            bar.set_style(
+                ProgressStyle::with_template("{bar:30.cyan/blue} {pos}/{len} {msg}")
+                    .unwrap_or_else(|_| ProgressStyle::default_bar()),
+            );
+            bar
+        });
+
+        let progress = Self { counts: EnrichCounts::new(total), bar };
+        progress.redraw();
+        progress
+    }
+
+    pub fn success(&mut self) {
+        self.counts.success();
+        self.redraw();
+    }
+
+    pub fn replaced(&mut self) {
+        self.counts.replaced();
+        self.redraw();
+    }
+
+    pub fn error(&mut self) {
+        self.counts.error();
+        self.redraw();
+    }
+
+    pub fn skip(&mut self) {
+        self.counts.skip();
+        self.redraw();
+    }
+
+    /// Clears the bar and returns the final counts
+    pub fn finish(self) -> EnrichCounts {
+        self.counts
+    }
+
+    fn redraw(&self) {
+        if let Some(bar) = &self.bar {
+            bar.set_position(self.counts.processed as u64);
+            bar.set_message(self.counts.message());
+        }
+    }
+}
+
+impl Drop for EnrichProgress {
+    fn drop(&mut self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_track_each_outcome() {
+        let mut counts = EnrichCounts::new(5);
+        counts.success();
+        counts.replaced();
+        counts.error();
+        counts.skip();
+        counts.skip();
+
+        assert_eq!(
+            counts,
+            EnrichCounts { total: 5, processed: 5, enriched: 2, errors: 1, changed: 1 }
+        );
+        assert_eq!(counts.message(), "2/5 enriched, 1 errors");
+    }
+
+    #[test]
+    fn hidden_progress_still_counts() {
+        let mut progress = EnrichProgress::new(3, false);
+        assert!(progress.bar.is_none());
+        progress.success();
+        progress.error();
+        progress.skip();
+
+        let counts = progress.finish();
+        assert_eq!(counts.processed, 3);
+        assert_eq!(counts.enriched, 1);
+        assert_eq!(counts.errors, 1);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::display::in_zone;
+use crate::models::Event;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Subdirectory of the user state directory holding the reminder log
+const STATE_DIR_NAME: &str = "lumabot";
+
+/// How long after an event starts its reminder is remembered
+const FORGET_AFTER_DAYS: i64 = 1;
+
+/// Returns the events starting between `now` and `now + within`
+pub fn due_events(events: &[Event], now: DateTime<Utc>, within: Duration) -> Vec<&Event> {
+    let until = now + within;
+    events
+        .iter()
+        .filter(|e| e.start >= now && e.start <= until)
+        .collect()
+}
+
+/// Shows a desktop notification for an event: its summary, start time and location
+pub fn notify(event: &Event, timezone: Option<Tz>) -> Result<(), notify_rust::error::Error> {
+    let start = in_zone(event.start, timezone).format("%a, %b %d, %Y %I:%M %p").to_string();
+    let body = match &event.location {
+        Some(location) => format!("{}\n{}", start, location),
+        None => start,
+    };
+
+    Notification::new()
+        .appname("lumabot")
+        .summary(&event.summary)
+        .body(&body)
+        .show()
+        .map(|_| ())
+}
+
+/// Reminders already sent, so repeated runs (e.g. from cron) notify only once
+///
+/// Entries map an event UID to the start time it was announced for; an event
+/// that moves is announced again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReminderLog {
+    sent: HashMap<String, DateTime<Utc>>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl ReminderLog {
+    /// Loads the log from the user state directory, starting empty when it is
+    /// missing or unreadable
+    pub fn load() -> Self {
+        let path = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join(STATE_DIR_NAME).join("reminders.json"));
+
+        let mut log: Self = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        log.path = path;
+        log
+    }
+
+    /// True if a reminder was already sent for this event at its current start time
+    pub fn was_sent(&self, event: &Event) -> bool {
+        self.sent.get(&event.event_uid) == Some(&event.start)
+    }
+
+    /// Records that a reminder was sent for this event
+    pub fn record(&mut self, event: &Event) {
+        self.sent.insert(event.event_uid.clone(), event.start);
+    }
+
+    /// Writes the log back, dropping events that started more than a day before `now`
+    pub fn save(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        let cutoff = now - Duration::days(FORGET_AFTER_DAYS);
+        self.sent.retain(|_, start| *start >= cutoff);
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    fn event(summary: &str, start: &str) -> Event {
+        let start = utc(start);
+        Event::new(summary.to_string(), None, None, start, start + Duration::hours(1), None)
+    }
+
+    #[test]
+    fn events_starting_within_the_window_are_due() {
+        let now = utc("2030-01-15T12:00:00Z");
+        let events = [
+            event("Already Started", "2030-01-15T11:59:00Z"),
+            event("Starting Now", "2030-01-15T12:00:00Z"),
+            event("Soon", "2030-01-15T12:10:00Z"),
+            event("Window Edge", "2030-01-15T12:15:00Z"),
+            event("Later", "2030-01-15T12:16:00Z"),
+        ];
+
+        let due: Vec<&str> = due_events(&events, now, Duration::minutes(15))
+            .into_iter()
+            .map(|event| event.summary.as_str())
+            .collect();
+
+        assert_eq!(due, ["Starting Now", "Soon", "Window Edge"]);
+    }
+
+    #[test]
+    fn a_sent_reminder_is_repeated_only_when_the_event_moves() {
+        let mut log = ReminderLog::default();
+        let meetup = event("Rust Meetup", "2030-01-15T18:00:00Z");
+        assert!(!log.was_sent(&meetup));
+
+        log.record(&meetup);
+        assert!(log.was_sent(&meetup));
+
+        let mut moved = meetup.clone();
+        moved.start = utc("2030-01-15T19:00:00Z");
+        assert!(!log.was_sent(&moved));
+    }
+
+    #[test]
+    fn saving_forgets_events_that_started_over_a_day_ago() {
+        let mut log = ReminderLog::default();
+        let old = event("Old", "2030-01-13T18:00:00Z");
+        let recent = event("Recent", "2030-01-14T18:00:00Z");
+        log.record(&old);
+        log.record(&recent);
+
+        log.save(utc("2030-01-15T12:00:00Z")).unwrap();
+
+        assert!(!log.was_sent(&old));
+        assert!(log.was_sent(&recent));
+    }
+}
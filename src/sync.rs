@@ -0,0 +1,667 @@
+use crate::api::{AddEventOptions, LumaApi};
+use crate::database::{self, EventStore, SaveSummary};
+use crate::errors::CalendarError;
+use crate::models::Event;
+use crate::progress::{EnrichCounts, EnrichProgress};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::runtime::Handle;
+use tracing::{debug, error, info, warn};
+
+/// Options for `run_full_sync`
+#[derive(Debug, Clone, Copy)]
+pub struct SyncOptions {
+    /// Only add events starting within this many days to the Luma calendar
+    pub days: u32,
+    /// Store and enrich, but add nothing to the Luma calendar
+    pub skip_add: bool,
+    /// Leave out events that are neither new nor modified since the previous sync
+    pub incremental: bool,
+    /// Look up the API ID again for events that already have one, replacing it
+    /// when Luma has reassigned it
+    pub force: bool,
+    /// Draw a progress bar on stderr (when it is a terminal) while enriching
+    pub progress: bool,
+    /// Pause between calendar additions; `None` uses the API client's rate limit
+    pub add_delay: Option<std::time::Duration>,
+}
+
+/// What a full sync did, step by step
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Events read from the calendar
+    pub fetched: usize,
+    /// Result of storing the fetched events
+    pub stored: SaveSummary,
+    /// Stored events whose API ID was looked up and saved
+    pub enriched: usize,
+    /// Failed API ID lookups or saves
+    pub enrich_errors: usize,
+    /// Stored API IDs replaced by a different one (only with `force`)
+    pub changed_ids: usize,
+    /// Events added to the Luma calendar
+    pub added: Vec<Event>,
+    /// Failed calendar additions
+    pub add_errors: usize,
+    /// Stored events left out because they had not changed since the previous
+    /// sync (only with `incremental`)
+    pub unchanged: usize,
+    /// True when the sync stopped early because `cancel` was set
+    pub interrupted: bool,
+}
+
+/// Returns copies of `events` with cleaned URLs, giving events without one the
+/// default `https://lu.ma/e/{event_uid}` URL
+pub fn with_clean_urls(events: &[Event]) -> Vec<Event> {
+    events
+        .iter()
+        .map(|event| {
+            let mut event = event.clone();
+            event.url = Some(match &event.url {
+                Some(url) => Event::clean_string(url),
+                None => format!("https://lu.ma/e/{}", event.event_uid),
+            });
+            event
+        })
+        .collect()
+}
+
+/// True when a stored event was first saved or last modified upstream after `since`
+///
+/// Events whose feed sends no LAST-MODIFIED only count as changed when they are new.
+fn changed_since(event: &Event, since: DateTime<Utc>) -> bool {
+    let is_new = event.created_at.is_none_or(|created_at| created_at > since);
+    let modified = event.last_modified.is_some_and(|last_modified| last_modified > since);
+    is_new || modified
+}
+
+/// Looks up and saves the API ID of each stored event that lacks one
+///
+/// With `force`, events that already have an API ID are looked up again and the
+/// stored ID is replaced when it changed. Failed lookups or saves are counted
+/// and the rest carry on.
+pub fn enrich_stored_events(
+    events: &mut [Event],
+    db: &dyn EventStore,
+    api: &LumaApi,
+    rt: &Handle,
+    force: bool,
+    show_progress: bool,
+) -> EnrichCounts {
+    let mut progress = EnrichProgress::new(events.len(), show_progress);
+
+    for event in events.iter_mut() {
+        // Skip events that already have an API ID, unless rechecking them
+        if event.api_id.is_some() && !force {
+            debug!("Event already has API ID: {}", event.summary);
+            progress.skip();
+            continue;
+        }
+
+        let Some(slug) = event.extract_slug() else {
+            debug!("Could not extract slug from URL for event: {}", event.summary);
+            progress.skip();
+            continue;
+        };
+
+        debug!("Looking up API ID for event: {} (slug: '{}')", event.summary, slug);
+        match rt.block_on(api.lookup_event_id(&slug)) {
+            Ok(id) if event.api_id.as_deref() == Some(id.as_str()) => {
+                debug!("API ID unchanged: {}", id);
+                progress.skip();
+            }
+            Ok(id) => {
+                debug!("Found API ID: {}", id);
+                let previous = event.api_id.replace(id);
+
+                if let Err(e) = db.save_event(event) {
+                    debug!("Failed to save event: {}", e);
+                    progress.error();
+                } else if let Some(previous) = previous {
+                    debug!("Replaced changed API ID {}", previous);
+                    progress.replaced();
+                } else {
+                    debug!("Event updated successfully");
+                    progress.success();
+                }
+            }
+            Err(e) => {
+                debug!("API lookup failed for '{}': {}", slug, e);
+                progress.error();
+            }
+        }
+
+        // Add a small delay to respect rate limits
+        std::thread::sleep(api.rate_limit());
+    }
+
+    progress.finish()
+}
+
+/// Stores `events`, resolves missing API IDs for every stored event and adds
+/// the upcoming ones to the Luma calendar
+///
+/// Storage failures abort the sync. Failed lookups and additions are counted in
+/// the report and the sync carries on; their details are only logged at debug
+/// level.
+///
+/// With `force`, events that already have an API ID are looked up again and the
+/// stored ID is replaced when it changed.
+///
+/// With `incremental`, events unchanged since the previous sync (see
+/// `changed_since`) are neither looked up nor added. Events still missing an
+/// API ID are always looked up.
+///
+/// Setting `cancel` (e.g. on Ctrl-C) stops the lookup and add loops before their
+/// next event; what was already saved or added stays, and the report is marked
+/// as interrupted.
+///
+/// The sync time (see `database::last_sync`) is recorded once the add phase is
+/// over, unless the sync was interrupted, failed, or had failed lookups or
+/// additions.
+pub fn run_full_sync(
+    events: &[Event],
+    db: &dyn EventStore,
+    api: &LumaApi,
+    rt: &Handle,
+    options: SyncOptions,
+    cancel: &AtomicBool,
+) -> Result<SyncReport, CalendarError> {
+    let report = store_enrich_and_add(events, db, api, rt, options, cancel)?;
+
+    // Only a sync that ran to the end without failures counts as the baseline
+    // for the next incremental one; otherwise failed events would never be retried
+    let failures = report.enrich_errors + report.add_errors;
+    if !report.interrupted && failures == 0 {
+        if let Err(e) = database::record_last_sync(db) {
+            warn!("Failed to record the sync time: {}", e);
+        }
+    }
+
+    Ok(report)
+}
+
+/// The steps of `run_full_sync`, without recording the sync time
+fn store_enrich_and_add(
+    events: &[Event],
+    db: &dyn EventStore,
+    api: &LumaApi,
+    rt: &Handle,
+    options: SyncOptions,
+    cancel: &AtomicBool,
+) -> Result<SyncReport, CalendarError> {
+    let mut report = SyncReport {
+        fetched: events.len(),
+        ..SyncReport::default()
+    };
+
+    // Read before this sync overwrites it
+    let previous_sync = if options.incremental {
+        database::last_sync(db).unwrap_or_else(|e| {
+            warn!("Could not read the previous sync time, syncing every event: {}", e);
+            None
+        })
+    } else {
+        None
+    };
+
+    info!("Storing events in database...");
+    report.stored = db.save_events(&with_clean_urls(events))?;
+    info!("Stored events: {}", report.stored);
+
+    info!("Enriching events with API data...");
+    let mut db_events = db.get_all_events()?;
+    info!("Found {} events in database", db_events.len());
+
+    let now = Utc::now();
+    let future_cutoff = now + Duration::days(options.days as i64);
+    let is_upcoming = |event: &Event| event.start > now && event.start < future_cutoff;
+
+    // Upcoming events with an API ID, candidates for the Luma calendar
+    let mut events_to_add = Vec::new();
+    let mut progress = EnrichProgress::new(db_events.len(), options.progress);
+
+    for event in db_events.iter_mut() {
+        if cancel.load(Ordering::Relaxed) {
+            report.interrupted = true;
+            break;
+        }
+
+        if let Some(since) = previous_sync {
+            if event.api_id.is_some() && !changed_since(event, since) {
+                debug!("Event unchanged since the last sync: {}", event.summary);
+                report.unchanged += 1;
+                progress.skip();
+                continue;
+            }
+        }
+
+        if event.api_id.is_some() && !options.force {
+            debug!("Event already has API ID: {}", event.summary);
+            if is_upcoming(event) {
+                events_to_add.push(event.clone());
+            }
+            progress.skip();
+            continue;
+        }
+
+        let Some(slug) = event.extract_slug() else {
+            debug!("Could not extract slug from URL for event: {}", event.summary);
+            // A stored ID is still worth adding, even if it can't be rechecked
+            if event.api_id.is_some() && is_upcoming(event) {
+                events_to_add.push(event.clone());
+            }
+            progress.skip();
+            continue;
+        };
+
+        debug!("Looking up API ID for event: {} (slug: '{}')", event.summary, slug);
+        match rt.block_on(api.lookup_event_id(&slug)) {
+            Ok(id) if event.api_id.as_deref() == Some(id.as_str()) => {
+                debug!("API ID unchanged: {}", id);
+                if is_upcoming(event) {
+                    events_to_add.push(event.clone());
+                }
+                progress.skip();
+            }
+            Ok(id) => {
+                debug!("Found API ID: {}", id);
+                let previous = event.api_id.replace(id);
+
+                if let Err(e) = db.save_event(event) {
+                    debug!("Failed to save event: {}", e);
+                    progress.error();
+                } else {
+                    debug!("Event updated successfully");
+                    if let Some(previous) = previous {
+                        debug!(
+                            "API ID of {} changed from {} to {}",
+                            event.summary,
+                            previous,
+                            event.api_id.as_deref().unwrap_or_default()
+                        );
+                        progress.replaced();
+                    } else {
+                        progress.success();
+                    }
+                    if is_upcoming(event) {
+                        events_to_add.push(event.clone());
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("API lookup failed for '{}': {}", slug, e);
+                progress.error();
+                // Keep using a stored ID the lookup couldn't confirm
+                if event.api_id.is_some() && is_upcoming(event) {
+                    events_to_add.push(event.clone());
+                }
+            }
+        }
+
+        // Add a small delay to respect rate limits
+        std::thread::sleep(api.rate_limit());
+    }
+
+    let counts = progress.finish();
+    report.enriched = counts.enriched;
+    report.enrich_errors = counts.errors;
+    report.changed_ids = counts.changed;
+    if report.interrupted {
+        warn!("Sync interrupted during API enrichment");
+        return Ok(report);
+    }
+
+    info!("API enrichment complete. Success: {}, Errors: {}", report.enriched, report.enrich_errors);
+
+    if options.skip_add {
+        info!("Skipping adding events to calendar as requested");
+        return Ok(report);
+    }
+    if events_to_add.is_empty() {
+        info!("No future events found to add to your calendar");
+        return Ok(report);
+    }
+
+    info!("Found {} future events to add to your calendar", events_to_add.len());
+    let add_options = AddEventOptions::default();
+    let add_delay = options.add_delay.unwrap_or_else(|| api.rate_limit());
+    debug!("Waiting {:?} between calendar additions", add_delay);
+    rt.block_on(async {
+        for (index, event) in events_to_add.into_iter().enumerate() {
+            // Pause between additions to respect rate limits
+            if index > 0 {
+                tokio::time::sleep(add_delay).await;
+            }
+
+            if cancel.load(Ordering::Relaxed) {
+                warn!("Sync interrupted while adding events to the calendar");
+                report.interrupted = true;
+                break;
+            }
+
+            let Some(api_id) = event.api_id.as_deref() else {
+                continue;
+            };
+
+            debug!("Adding event to calendar: {} (API ID: {})", event.summary, api_id);
+            match api.add_event(api_id, &add_options).await {
+                Ok(_) => {
+                    debug!("Successfully added event to calendar: {}", event.summary);
+                    report.added.push(event);
+                }
+                Err(e) => {
+                    error!("Failed to add event to calendar: {}", e);
+                    report.add_errors += 1;
+                }
+            }
+        }
+    });
+
+    info!(
+        "Calendar addition complete. Success: {}, Errors: {}",
+        report.added.len(),
+        report.add_errors
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::DEFAULT_RETENTION_DAYS;
+    use crate::database::SqliteDatabase;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::runtime::Runtime;
+    use wiremock::matchers::{body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    fn options() -> SyncOptions {
+        SyncOptions {
+            days: 30,
+            skip_add: false,
+            incremental: false,
+            force: false,
+            progress: false,
+            add_delay: Some(std::time::Duration::ZERO),
+        }
+    }
+
+    fn event(summary: &str, start: DateTime<Utc>, slug: &str) -> Event {
+        Event::new(
+            summary.to_string(),
+            None,
+            None,
+            start,
+            start + Duration::hours(2),
+            Some(format!("https://lu.ma/{}", slug)),
+        )
+    }
+
+    async fn mock_lookup(server: &MockServer, slug: &str, api_id: &str) {
+        Mock::given(method("GET"))
+            .and(path("/entity/lookup"))
+            .and(query_param("slug", slug))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "entity": { "event": { "api_id": api_id } } })),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    async fn mock_add(server: &MockServer, api_id: &str) {
+        Mock::given(method("POST"))
+            .and(path("/calendar/add-event"))
+            .and(body_partial_json(json!({ "event_api_id": api_id })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "calendar_event_id": "calev-1" })))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn full_sync_stores_enriches_and_adds_upcoming_events() {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            mock_lookup(&server, "rust-meetup", "evt-rust").await;
+            mock_lookup(&server, "go-meetup", "evt-go").await;
+            mock_lookup(&server, "last-year", "evt-old").await;
+            mock_add(&server, "evt-rust").await;
+            mock_add(&server, "evt-go").await;
+            server
+        });
+
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        let now = Utc::now();
+        let events = vec![
+            event("Rust Meetup", now + Duration::days(2), "rust-meetup"),
+            event("Go Meetup", now + Duration::days(5), "go-meetup"),
+            event("Last Year", now - Duration::days(1), "last-year"),
+        ];
+        let api = LumaApi::for_mock_server(&server.uri());
+
+        let report = run_full_sync(&events, &db, &api, runtime.handle(), options(), &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(report.fetched, 3);
+        assert_eq!(report.stored.inserted, 3);
+        assert_eq!(report.enriched, 3);
+        assert_eq!(report.enrich_errors, 0);
+        assert_eq!(report.add_errors, 0);
+        assert!(!report.interrupted);
+        let added: Vec<&str> = report.added.iter().map(|event| event.summary.as_str()).collect();
+        assert_eq!(added, ["Rust Meetup", "Go Meetup"]);
+
+        let mut api_ids: Vec<String> = db.get_all_events().unwrap().into_iter().filter_map(|event| event.api_id).collect();
+        api_ids.sort();
+        assert_eq!(api_ids, ["evt-go", "evt-old", "evt-rust"]);
+        assert!(database::last_sync(&db).unwrap().is_some());
+
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn skip_add_only_stores_and_enriches() {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            mock_lookup(&server, "rust-meetup", "evt-rust").await;
+            server
+        });
+
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        let events = vec![event("Rust Meetup", Utc::now() + Duration::days(2), "rust-meetup")];
+        let api = LumaApi::for_mock_server(&server.uri());
+        let options = SyncOptions { skip_add: true, ..options() };
+
+        let report = run_full_sync(&events, &db, &api, runtime.handle(), options, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(report.enriched, 1);
+        assert!(report.added.is_empty());
+        runtime.block_on(server.verify());
+    }
+
+    /// Answers lookups like Luma, then raises `cancel` as if Ctrl-C arrived mid-request
+    struct CancelAfterLookup {
+        cancel: Arc<AtomicBool>,
+    }
+
+    impl Respond for CancelAfterLookup {
+        fn respond(&self, _: &Request) -> ResponseTemplate {
+            self.cancel.store(true, Ordering::Relaxed);
+            ResponseTemplate::new(200).set_body_json(json!({ "entity": { "event": { "api_id": "evt-first" } } }))
+        }
+    }
+
+    #[test]
+    fn cancelled_syncs_stop_before_the_next_event_and_keep_what_was_saved() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/entity/lookup"))
+                .respond_with(CancelAfterLookup { cancel: cancel.clone() })
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("POST")).respond_with(ResponseTemplate::new(500)).expect(0).mount(&server).await;
+            server
+        });
+
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        let events = vec![
+            event("Rust Meetup", Utc::now() + Duration::days(2), "rust-meetup"),
+            event("Go Meetup", Utc::now() + Duration::days(5), "go-meetup"),
+        ];
+        let api = LumaApi::for_mock_server(&server.uri());
+
+        let report = run_full_sync(&events, &db, &api, runtime.handle(), options(), &cancel).unwrap();
+
+        assert!(report.interrupted);
+        assert_eq!(report.stored.inserted, 2);
+        assert_eq!(report.enriched, 1);
+        assert!(report.added.is_empty());
+        let api_ids: Vec<Option<String>> = db.get_all_events().unwrap().into_iter().map(|event| event.api_id).collect();
+        assert_eq!(api_ids, [Some("evt-first".to_string()), None]);
+        // An interrupted sync is no baseline for the next incremental one
+        assert!(database::last_sync(&db).unwrap().is_none());
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn incremental_syncs_skip_the_api_for_unchanged_events() {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            mock_lookup(&server, "rust-meetup", "evt-rust").await;
+            mock_lookup(&server, "go-meetup", "evt-go").await;
+            mock_add(&server, "evt-rust").await;
+            mock_add(&server, "evt-go").await;
+            server
+        });
+
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        let events = vec![
+            event("Rust Meetup", Utc::now() + Duration::days(2), "rust-meetup"),
+            event("Go Meetup", Utc::now() + Duration::days(5), "go-meetup"),
+        ];
+        let api = LumaApi::for_mock_server(&server.uri());
+        let options = SyncOptions { incremental: true, ..options() };
+
+        let first = run_full_sync(&events, &db, &api, runtime.handle(), options, &AtomicBool::new(false)).unwrap();
+        assert_eq!(first.enriched, 2);
+        assert_eq!(first.added.len(), 2);
+        runtime.block_on(async {
+            server.verify().await;
+            server.reset().await;
+        });
+
+        let second = run_full_sync(&events, &db, &api, runtime.handle(), options, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(second.unchanged, 2);
+        assert_eq!(second.enriched, 0);
+        assert!(second.added.is_empty());
+        assert!(runtime.block_on(server.received_requests()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn forced_enrichment_replaces_only_changed_ids() {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            mock_lookup(&server, "rust-meetup", "evt-rust-new").await;
+            mock_lookup(&server, "go-meetup", "evt-go").await;
+            server
+        });
+
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        let mut rust = event("Rust Meetup", Utc::now() + Duration::days(2), "rust-meetup");
+        rust.api_id = Some("evt-rust-old".to_string());
+        let mut go = event("Go Meetup", Utc::now() + Duration::days(5), "go-meetup");
+        go.api_id = Some("evt-go".to_string());
+        db.save_events(&[rust, go]).unwrap();
+        let api = LumaApi::for_mock_server(&server.uri());
+
+        let mut stored = db.get_all_events().unwrap();
+        let counts = enrich_stored_events(&mut stored, &db, &api, runtime.handle(), true, false);
+
+        assert_eq!(counts.processed, 2);
+        assert_eq!(counts.enriched, 1);
+        assert_eq!(counts.changed, 1);
+        assert_eq!(counts.errors, 0);
+        let api_ids: Vec<Option<String>> = db.get_all_events().unwrap().into_iter().map(|event| event.api_id).collect();
+        assert_eq!(api_ids, [Some("evt-rust-new".to_string()), Some("evt-go".to_string())]);
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn enrichment_without_force_leaves_stored_ids_alone() {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            mock_lookup(&server, "go-meetup", "evt-go").await;
+            server
+        });
+
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        let mut rust = event("Rust Meetup", Utc::now() + Duration::days(2), "rust-meetup");
+        rust.api_id = Some("evt-rust-old".to_string());
+        db.save_events(&[rust, event("Go Meetup", Utc::now() + Duration::days(5), "go-meetup")]).unwrap();
+        let api = LumaApi::for_mock_server(&server.uri());
+
+        let mut stored = db.get_all_events().unwrap();
+        let counts = enrich_stored_events(&mut stored, &db, &api, runtime.handle(), false, false);
+
+        assert_eq!((counts.enriched, counts.changed), (1, 0));
+        let api_ids: Vec<Option<String>> = db.get_all_events().unwrap().into_iter().map(|event| event.api_id).collect();
+        assert_eq!(api_ids, [Some("evt-rust-old".to_string()), Some("evt-go".to_string())]);
+        runtime.block_on(server.verify());
+    }
+
+    #[test]
+    fn the_configured_delay_separates_calendar_additions() {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/calendar/add-event"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "calendar_event_id": "calev-1" })))
+                .expect(3)
+                .mount(&server)
+                .await;
+            server
+        });
+
+        let db = SqliteDatabase::open(":memory:", DEFAULT_RETENTION_DAYS).unwrap();
+        db.init_schema().unwrap();
+        let events: Vec<Event> = (1..=3)
+            .map(|day| {
+                let mut event = event(&format!("Meetup {}", day), Utc::now() + Duration::days(day), "meetup");
+                event.api_id = Some(format!("evt-{}", day));
+                event
+            })
+            .collect();
+        // The mock client has no rate limit, so any pause comes from the add delay
+        let api = LumaApi::for_mock_server(&server.uri());
+        let options = SyncOptions { add_delay: Some(std::time::Duration::from_millis(150)), ..options() };
+
+        let started = std::time::Instant::now();
+        let report = run_full_sync(&events, &db, &api, runtime.handle(), options, &AtomicBool::new(false)).unwrap();
+        let elapsed = started.elapsed();
+
+        assert_eq!(report.added.len(), 3);
+        // Two pauses: none before the first addition
+        assert!(elapsed >= std::time::Duration::from_millis(300), "{:?}", elapsed);
+        runtime.block_on(server.verify());
+    }
+}
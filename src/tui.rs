@@ -0,0 +1,226 @@
+use crate::display::in_zone;
+use crate::models::Event;
+use arboard::Clipboard;
+use chrono::Duration;
+use chrono_tz::Tz;
+use crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use std::io::{self, Stdout};
+
+type Term = Terminal<CrosstermBackend<Stdout>>;
+
+/// Opens a full-screen browser for `events` and blocks until the user quits
+///
+/// Keys: up/down (or k/j) move, `v` toggles the description, `enter` copies
+/// the URL to the clipboard and `q` (or esc) quits.
+pub fn run(events: &[Event], timezone: Option<Tz>) -> io::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = App::new(events, timezone).run(&mut terminal);
+
+    // Restore the terminal even when the event loop failed
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+/// State of the event browser
+struct App<'a> {
+    events: &'a [Event],
+    timezone: Option<Tz>,
+    list_state: ListState,
+    show_description: bool,
+    /// Kept open for the whole session: on X11 the copied text is lost once it is dropped
+    clipboard: Option<Clipboard>,
+    /// One-line message shown in the footer instead of the key help
+    status: Option<String>,
+}
+
+impl<'a> App<'a> {
+    fn new(events: &'a [Event], timezone: Option<Tz>) -> Self {
+        let mut list_state = ListState::default();
+        if !events.is_empty() {
+            list_state.select(Some(0));
+        }
+
+        Self {
+            events,
+            timezone,
+            list_state,
+            show_description: true,
+            clipboard: None,
+            status: None,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut Term) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let TermEvent::Key(key) = event::read()? else {
+                continue;
+            };
+            // Windows also reports key releases
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            self.status = None;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+                KeyCode::Char('v') => self.show_description = !self.show_description,
+                KeyCode::Enter => self.copy_url(),
+                _ => {}
+            }
+        }
+    }
+
+    fn selected(&self) -> Option<&'a Event> {
+        self.list_state.selected().and_then(|index| self.events.get(index))
+    }
+
+    /// Moves the highlight by `offset` rows, stopping at either end of the list
+    fn move_selection(&mut self, offset: isize) {
+        if self.events.is_empty() {
+            return;
+        }
+
+        let current = self.list_state.selected().unwrap_or(0);
+        let next = current.saturating_add_signed(offset).min(self.events.len() - 1);
+        self.list_state.select(Some(next));
+    }
+
+    /// Copies the highlighted event's URL to the system clipboard
+    fn copy_url(&mut self) {
+        let Some(url) = self.selected().and_then(|event| event.url.as_deref()) else {
+            self.status = Some("This event has no URL".to_string());
+            return;
+        };
+        let url = Event::clean_string(url);
+
+        if self.clipboard.is_none() {
+            match Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(e) => {
+                    self.status = Some(format!("Clipboard unavailable: {}", e));
+                    return;
+                }
+            }
+        }
+
+        let Some(clipboard) = &mut self.clipboard else {
+            return;
+        };
+        self.status = Some(match clipboard.set_text(url.clone()) {
+            Ok(()) => format!("Copied {}", url),
+            Err(e) => format!("Could not copy URL: {}", e),
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(frame.size());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(rows[0]);
+
+        let items: Vec<ListItem> = self
+            .events
+            .iter()
+            .map(|event| {
+                let start = in_zone(event.start, self.timezone);
+                ListItem::new(Line::from(vec![
+                    Span::styled(start.format("%a %b %d %Y ").to_string(), Style::default().fg(Color::Yellow)),
+                    Span::raw(event.summary.clone()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!("Events ({})", self.events.len())))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, panes[0], &mut self.list_state);
+
+        let details = Paragraph::new(self.details())
+            .block(Block::default().borders(Borders::ALL).title("Details"))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(details, panes[1]);
+
+        let footer = self
+            .status
+            .clone()
+            .unwrap_or_else(|| "↑/↓ move  v toggle description  enter copy URL  q quit".to_string());
+        frame.render_widget(Paragraph::new(footer).style(Style::default().fg(Color::DarkGray)), rows[1]);
+    }
+
+    /// Lines describing the highlighted event
+    fn details(&self) -> Vec<Line<'a>> {
+        let Some(event) = self.selected() else {
+            return vec![Line::from("No events to display.")];
+        };
+
+        let label = |name: &'static str| Span::styled(format!("{}: ", name), Style::default().fg(Color::Blue));
+        let start = in_zone(event.start, self.timezone);
+        let end = in_zone(event.end, self.timezone);
+
+        let time = if event.all_day {
+            // DTEND is exclusive for all-day events, so the last day is the day before
+            let last_day = (end - Duration::days(1)).date_naive().max(start.date_naive());
+            if last_day > start.date_naive() {
+                format!("{} - {} (all day)", start.format("%a, %b %d, %Y"), last_day.format("%a, %b %d, %Y"))
+            } else {
+                format!("{} (all day)", start.format("%a, %b %d, %Y"))
+            }
+        } else {
+            format!("{} - {}", start.format("%a, %b %d, %Y %I:%M %p"), end.format("%I:%M %p"))
+        };
+
+        let mut lines = vec![
+            Line::from(Span::styled(event.summary.clone(), Style::default().add_modifier(Modifier::BOLD))),
+            Line::from(""),
+            Line::from(vec![label("Time"), Span::raw(time)]),
+        ];
+
+        if event.is_invitation() {
+            let from = event.organizer.clone().unwrap_or_else(|| "unknown organizer".to_string());
+            lines.insert(1, Line::from(vec![
+                Span::styled("Invitation from ", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+                Span::raw(from),
+            ]));
+        }
+
+        if let Some(location) = &event.location {
+            lines.push(Line::from(vec![label("Location"), Span::raw(location.clone())]));
+        }
+
+        if let Some(url) = &event.url {
+            lines.push(Line::from(vec![label("URL"), Span::raw(Event::clean_string(url))]));
+        }
+
+        if self.show_description {
+            if let Some(description) = event.description.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+                lines.push(Line::from(""));
+                lines.extend(description.lines().map(|line| Line::from(line.to_string())));
+            }
+        }
+
+        lines
+    }
+}